@@ -18,26 +18,32 @@ use crossterm::{
 async fn main() -> Res<()> {
     tracing_subscriber::fmt::init();
 
-    let url_core = std::env::args().skip(1).next();
+    let mut args = std::env::args().skip(1);
+    let url_core = args.next();
     let url_core = url_core.unwrap_or_else(|| {
-        panic!("need to provide single argument with base URL for game service.")
+        panic!("need to provide an argument with base URL for game service (and optionally the game and its options).")
     });
+    // The game can be named after the URL; otherwise the service picks its
+    // default. Options for the game, like `size=4,win=3`, can follow.
+    let url_core = match args.next() {
+        Some(game) => url_core.with_game(&game),
+        None => url_core,
+    };
+    let options = args.next().unwrap_or_default();
 
-    let service = GameService::new(url_core);
+    let service = GameService::new(url_core, options);
 
 
     let mut session = service.fresh_game().await?;
 
     // stdout.execute(terminal::Clear(terminal::ClearType::All))?;
 
-    loop {
-        match handle_human_player(&mut session).await? {
-            Ok(move_description) => {
-                session.game_state = move_description.next_board;
-                session.player = move_description.next_player;
-            }
-            Err(QuitGame) => break,
-        }
+    while let Ok(move_description) = handle_human_player(&mut session).await? {
+        // Some games let a player take several moves in a row.
+        session.extra_turn = move_description.next_player == session.player;
+        session.game_state = move_description.next_board
+            .ok_or("the service did not say where the move leads")?;
+        session.player = move_description.next_player;
     }
     Ok(())
 }
@@ -46,7 +52,7 @@ struct QuitGame;
 
 async fn handle_human_player(session: &mut Session) -> Res<Result<MoveDescription, QuitGame>>
 {
-    let rendered = session.rendered_board().await?;
+    let RenderResponse { text: rendered, outcome, .. } = session.rendered_board().await?;
     let num_lines = rendered.lines().count();
 
     let stdout = &mut session.stdout;
@@ -58,15 +64,32 @@ async fn handle_human_player(session: &mut Session) -> Res<Result<MoveDescriptio
         .execute(terminal::Clear(terminal::ClearType::CurrentLine))?
         ;
 
+    // Once the game is over there is nothing left to choose; report how it
+    // ended and stop.
+    let result = match &outcome.result[..] {
+        "win" if outcome.points > 1 => Some(format!("Game over: victory goes to {}, for {} points",
+                                                    outcome.victors.join(", "), outcome.points)),
+        "win" => Some(format!("Game over: victory goes to {}", outcome.victors.join(", "))),
+        "draw" => Some("Game over: it's a draw".to_string()),
+        _ => None,
+    };
+    if let Some(result) = result {
+        session.stdout
+            .execute(Print(result))?
+            .execute(cursor::MoveTo(1, num_lines as u16 + 2))?
+            ;
+        return Ok(Err(QuitGame));
+    }
+
     session.stdout
         .execute(Print(&session.player))?
-        .execute(Print(" moves: "))?
+        .execute(Print(if session.extra_turn { " moves again: " } else { " moves: " }))?
         ;
 
     let moves = session.move_list().await?;
     for desc in &moves {
         session.stdout
-            .execute(Print(&desc.move_id))?
+            .execute(Print(&desc.move_name))?
             .execute(Print(" "))?
             ;
     }
@@ -141,12 +164,17 @@ async fn handle_human_player(session: &mut Session) -> Res<Result<MoveDescriptio
 
                     let event::KeyEvent { code, modifiers: _ } = event;
                     match code {
-                        event::KeyCode::Char('q') => { return Ok(Err(QuitGame)) }
+                        event::KeyCode::Esc => { return Ok(Err(QuitGame)) }
+                        // 'q' quits too, unless it starts the name of a move.
+                        event::KeyCode::Char('q') if input_choice.is_empty()
+                            && !moves.iter().any(|desc| desc.move_name.starts_with('q')) => {
+                            return Ok(Err(QuitGame))
+                        }
                         event::KeyCode::Enter => break,
                         event::KeyCode::Backspace => {
                             input_choice.pop();
                         }
-                        event::KeyCode::Char(c @ '0'..='9') => {
+                        event::KeyCode::Char(c) => {
                             input_choice.push(c);
                         }
                         _ => {}
@@ -158,11 +186,14 @@ async fn handle_human_player(session: &mut Session) -> Res<Result<MoveDescriptio
             clear_lines(&mut session.stdout, query_line, max_preview_length + 2)?;
 
             for desc in &moves {
-                if &desc.move_id == &input_choice {
+                if desc.names(&input_choice) {
+                    // Games with hidden information keep where a move
+                    // leads to themselves until it is made, so there is no
+                    // preview.
                     let rendered = preview_board(session,
                                                  query_line,
                                                  preview_length,
-                                                 Some(desc.next_board.clone())).await?;
+                                                 desc.next_board.clone()).await?;
                     if let Some(rendered) = rendered {
                         preview_length = rendered.lines().count();
                         max_preview_length = std::cmp::max(max_preview_length, preview_length);
@@ -173,10 +204,14 @@ async fn handle_human_player(session: &mut Session) -> Res<Result<MoveDescriptio
         }
 
         for desc in &moves {
-            if &desc.move_id == &input_choice {
+            if desc.names(&input_choice) {
                 // delete any past preview, then return the selected choice
                 clear_lines(&mut session.stdout, query_line , preview_length + 1)?;
-                return Ok(Ok(desc.clone()));
+                return match desc.next_board {
+                    Some(_) => Ok(Ok(desc.clone())),
+                    // Playing the move is what tells where it leads.
+                    None => Ok(Ok(session.play(desc).await?)),
+                };
             }
         }
 
@@ -184,7 +219,7 @@ async fn handle_human_player(session: &mut Session) -> Res<Result<MoveDescriptio
         session.stdout
             .execute(cursor::MoveTo(1, msg_line))?
             .execute(Print(&format!("You typed `{}`; but you need to select \
-                                     one of the {} moves listed above (or 'q' to quit)",
+                                     one of the {} moves listed above (or Esc to quit)",
                                     input_choice, moves.len())))?
             .execute(cursor::MoveTo(1, query_line + max_preview_length as u16 + 1))?
             .execute(cursor::MoveTo(1, msg_line + 1))?
@@ -222,20 +257,32 @@ fn render_board(stdout: &mut impl crossterm::ExecutableCommand,
 
 struct GameService {
     url_core: String,
+    // The options the game is created with.
+    options: String,
 }
 
 trait CommandCore: Sized {
+    fn with_game(&self, game: &str) -> Self;
     fn with_char(&self, c: char) -> Self;
     fn pushing(self, s: &str) -> Self;
     fn n(&self) -> Self { self.with_char('n') }
     fn r(&self, board: &str) -> Self { self.with_char('r').pushing(board) }
     fn l(&self, board: &str) -> Self { self.with_char('l').pushing(board) }
+    fn p(&self, board: &str, move_id: &str) -> Self { self.with_char('p').pushing(board).pushing("/").pushing(move_id) }
 }
 impl CommandCore for String {
     fn pushing(mut self, s: &str) -> Self {
         self.push_str(s);
         self
     }
+    fn with_game(&self, game: &str) -> Self {
+        let mut new_url = self.clone();
+        if !new_url.ends_with("/") {
+            new_url.push('/');
+        }
+        new_url.push_str(game);
+        new_url
+    }
     fn with_char(&self, c: char) -> Self {
         let mut new_url = self.clone();
         if !new_url.ends_with("/") {
@@ -266,30 +313,63 @@ struct FreshResponse {
 struct RenderResponse {
     command: String,
     text: String,
+    outcome: Outcome,
 }
 
+#[derive(Debug, Deserialize)]
+struct Outcome {
+    // "ongoing", "win" or "draw".
+    result: String,
+    victors: Vec<String>,
+    // What the win is worth, like 2 for a gammon; services from before it
+    // was sent always scored a win as one point.
+    #[serde(default = "one_point")]
+    points: u32,
+}
+
+fn one_point() -> u32 { 1 }
+
 #[derive(Debug, Deserialize)]
 struct ListMovesResponse {
     command: String,
     next_game_states: Vec<MoveDescription>,
 }
 
+#[derive(Debug, Deserialize)]
+struct PlayResponse {
+    selected_move_description: MoveDescription,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 struct MoveDescription {
     move_id: String,
-    next_board: String,
+    move_name: String,
+    move_long_name: String,
+    // Only once the move is made, in games with hidden information.
+    next_board: Option<String>,
     next_player: String,
 }
 
+impl MoveDescription {
+    /// Whether the player typed this move: its id, or its short or long
+    /// notation.
+    fn names(&self, input: &str) -> bool {
+        let input = input.trim();
+        self.move_id == input
+            || self.move_name.eq_ignore_ascii_case(input)
+            || self.move_long_name.eq_ignore_ascii_case(input)
+    }
+}
+
 impl GameService {
-    fn new(url_core: String) -> Self { GameService { url_core } }
+    fn new(url_core: String, options: String) -> Self { GameService { url_core, options } }
 
     async fn fresh_game(&self) -> Res<Session> {
-        let game_state = ask::<FreshResponse>(&self.url_core.n()).await?;
+        let game_state = ask::<FreshResponse>(&self.url_core.n().pushing(&self.options)).await?;
         let player = game_state.player;
         let game_state = game_state.parsed_game_state;
         let stdout = std::io::stdout();
-        Ok(Session { url_core: self.url_core.clone(), game_state, player, stdout })
+        Ok(Session { url_core: self.url_core.clone(), game_state, player, extra_turn: false, stdout })
     }
 }
 
@@ -298,16 +378,21 @@ struct Session {
     stdout: std::io::Stdout,
     game_state: String,
     player: String,
+    extra_turn: bool,
 }
 
 impl Session {
-    async fn rendered_board(&self) -> Res<String> {
-        let rendered = ask::<RenderResponse>(&self.url_core.r(&self.game_state)).await?;
-        Ok(rendered.text)
+    async fn rendered_board(&self) -> Res<RenderResponse> {
+        ask::<RenderResponse>(&self.url_core.r(&self.game_state)).await
     }
 
     async fn move_list(&self) -> Res<Vec<MoveDescription>> {
         let moves = ask::<ListMovesResponse>(&self.url_core.l(&self.game_state)).await?;
         Ok(moves.next_game_states)
     }
+
+    async fn play(&self, chosen: &MoveDescription) -> Res<MoveDescription> {
+        let played = ask::<PlayResponse>(&self.url_core.p(&self.game_state, &chosen.move_id)).await?;
+        Ok(played.selected_move_description)
+    }
 }
@@ -0,0 +1,535 @@
+use crate::game_core::{Game, Move, MoveId, Outcome, ParseError, Player, CHANCE, VICTORY};
+
+const CHECKERS: u8 = 15;
+
+/// The bar, as a point: a checker entering from it counts down from 25.
+const BAR: usize = 25;
+
+/// Backgammon between White ('W') and Black ('B'). White moves from point 24
+/// towards point 1 and bears off from its home board, points 1 to 6; Black
+/// moves the other way. Points are numbered from White's side of the board;
+/// move names give them from the mover's side, as players do.
+///
+/// A turn starts with a roll of the dice, a chance move whose id is the two
+/// dice as a two-digit number (e.g. 62 for a six and a two). Each checker move
+/// then uses up one die: its id is ten times the point it leaves (25 for the
+/// bar) plus the die. The very first roll is one die each, with White's die
+/// first; the higher one moves first, using both.
+#[derive(Clone, Debug)]
+pub struct BackgammonGame {
+    /// The checkers on points 1 to 24: positive counts are White's, negative
+    /// counts Black's.
+    pub points: [i8; 24],
+    /// The checkers on the bar, White's then Black's.
+    pub bar: [u8; 2],
+    /// The checkers borne off, White's then Black's.
+    pub off: [u8; 2],
+    /// Whose turn it is, or `None` before the opening roll.
+    pub player: Option<Player>,
+    /// The dice the player has yet to use, largest first; empty when the dice
+    /// are still to be rolled.
+    pub dice: Vec<u8>,
+}
+
+impl Default for BackgammonGame {
+    fn default() -> Self {
+        let mut points = [0; 24];
+        for (point, count) in &[(24, 2), (13, 5), (8, 3), (6, 5)] {
+            points[point - 1] = *count;
+            points[24 - point] = -*count;
+        }
+        BackgammonGame { points, bar: [0, 0], off: [0, 0], player: None, dice: Vec::new() }
+    }
+}
+
+fn opponent(p: Player) -> Player {
+    if p == 'W' { 'B' } else { 'W' }
+}
+
+fn index(p: Player) -> usize {
+    if p == 'W' { 0 } else { 1 }
+}
+
+fn sign(p: Player) -> i8 {
+    if p == 'W' { 1 } else { -1 }
+}
+
+/// Where `p`'s point `n` (counting from `p`'s side) is in `points`.
+fn slot(p: Player, n: usize) -> usize {
+    if p == 'W' { n - 1 } else { 24 - n }
+}
+
+/// A single checker move that the dice allow.
+struct Step {
+    from: usize,
+    die: u8,
+    hit: bool,
+    next: BackgammonGame,
+}
+
+impl BackgammonGame {
+    /// How many checkers `p` has on their point `n`.
+    fn count(&self, p: Player, n: usize) -> u8 {
+        (self.points[slot(p, n)] * sign(p)).max(0) as u8
+    }
+
+    fn is_over(&self) -> bool {
+        self.off.contains(&CHECKERS)
+    }
+
+    fn all_home(&self, p: Player) -> bool {
+        self.bar[index(p)] == 0 && (7..=24).all(|n| self.count(p, n) == 0)
+    }
+
+    /// The total number of points `p`'s checkers must still travel.
+    pub fn pip_count(&self, p: Player) -> u32 {
+        let board: u32 = (1..=24).map(|n| n as u32 * self.count(p, n) as u32).sum();
+        board + BAR as u32 * self.bar[index(p)] as u32
+    }
+
+    /// The winner and what they score: 1 point, 2 for a gammon (the loser has
+    /// borne off no checkers) or 3 for a backgammon (the loser also still has
+    /// a checker on the bar or in the winner's home board).
+    pub fn points_won(&self) -> Option<(Player, u32)> {
+        let winner = ['W', 'B'].iter().cloned().find(|p| self.off[index(*p)] == CHECKERS)?;
+        let loser = opponent(winner);
+        let points = if self.off[index(loser)] > 0 {
+            1
+        } else if self.bar[index(loser)] > 0 || (19..=24).any(|n| self.count(loser, n) > 0) {
+            3
+        } else {
+            2
+        };
+        Some((winner, points))
+    }
+
+    /// Moves one of `p`'s checkers from their point `from` by `die`, if the
+    /// rules allow it.
+    fn step(&self, p: Player, from: usize, die: u8) -> Option<Step> {
+        let i = index(p);
+        if from == BAR {
+            if self.bar[i] == 0 { return None; }
+        } else if self.bar[i] > 0 || self.count(p, from) == 0 {
+            return None;
+        }
+
+        let mut next = self.clone();
+        let k = next.dice.iter().position(|d| *d == die)?;
+        next.dice.remove(k);
+        if from == BAR {
+            next.bar[i] -= 1;
+        } else {
+            next.points[slot(p, from)] -= sign(p);
+        }
+
+        let to = from as i32 - die as i32;
+        let mut hit = false;
+        if to >= 1 {
+            let to = slot(p, to as usize);
+            match self.points[to] * sign(p) {
+                n if n < -1 => return None,
+                -1 => {
+                    hit = true;
+                    next.points[to] = 0;
+                    next.bar[index(opponent(p))] += 1;
+                }
+                _ => {}
+            }
+            next.points[to] += sign(p);
+        } else {
+            // Bearing off takes an exact roll, or a higher one if no checker
+            // is further from home.
+            if !self.all_home(p) { return None; }
+            if to < 0 && (from + 1..=6).any(|n| self.count(p, n) > 0) { return None; }
+            next.off[i] += 1;
+        }
+        Some(Step { from, die, hit, next })
+    }
+
+    /// Every checker move that one of the remaining dice allows.
+    fn steps(&self, p: Player) -> Vec<Step> {
+        let mut dice = self.dice.clone();
+        dice.dedup();
+        let mut v = Vec::new();
+        for die in dice {
+            for from in 1..=BAR {
+                v.extend(self.step(p, from, die));
+            }
+        }
+        v
+    }
+
+    /// The most of the remaining dice that `p` can use.
+    fn max_usable(&self, p: Player) -> usize {
+        let mut best = 0;
+        for s in self.steps(p) {
+            best = best.max(1 + s.next.max_usable(p));
+            if best == self.dice.len() { break; }
+        }
+        best
+    }
+
+    /// Ends `p`'s turn once the dice are used up, or once no more of them can
+    /// be used.
+    fn settle(&mut self, p: Player) {
+        if !self.dice.is_empty() && self.max_usable(p) == 0 {
+            self.dice.clear();
+        }
+        if self.dice.is_empty() {
+            self.player = Some(opponent(p));
+        }
+    }
+
+    fn rolls(&self) -> Vec<Move<Self>> {
+        let mut v = Vec::new();
+        for high in 1..=6 {
+            for low in 1..=6 {
+                let mut next_state = self.clone();
+                let p = match self.player {
+                    // The opening roll: one die each, and nobody may start
+                    // with doubles.
+                    None if high == low => continue,
+                    None => if high > low { 'W' } else { 'B' },
+                    Some(_) if low > high => continue,
+                    Some(p) => p,
+                };
+                next_state.player = Some(p);
+                next_state.dice = if high == low { vec![high; 4] } else { vec![high.max(low), high.min(low)] };
+                next_state.settle(p);
+                v.push(Move { id: 10 * high as MoveId + low as MoveId, end_game: None, next_state });
+            }
+        }
+        v
+    }
+}
+
+/// The character for a point: '0' if it is empty, and otherwise a letter
+/// counting the checkers ('A' for one, 'B' for two, ...), uppercase for White
+/// and lowercase for Black.
+fn point_char(n: i8) -> char {
+    match n {
+        0 => '0',
+        n if n > 0 => (b'A' + n as u8 - 1) as char,
+        n => (b'a' + (-n) as u8 - 1) as char,
+    }
+}
+
+impl Game for BackgammonGame {
+    const NAME: &'static str = "Backgammon";
+
+    const HAS_CHANCE_MOVES: bool = true;
+
+    fn current_player(&self) -> Player {
+        match self.player {
+            Some(p) if !self.dice.is_empty() || self.is_over() => p,
+            _ => CHANCE,
+        }
+    }
+
+    /// The serialized form is whose turn it is ('W', 'B', or 'O' before the
+    /// opening roll) and the dice left to use, then a '-', then a character
+    /// for each point from 1 to 24 (see `point_char`), then the checkers on
+    /// the bar and borne off, White's then Black's, as hexadecimal digits. For
+    /// example, the default game is "O-b0000E0C000eE000c0e0000B0000".
+    fn unparse(&self) -> String {
+        let mut s = String::new();
+        s.push(self.player.unwrap_or('O'));
+        s.extend(self.dice.iter().map(|d| (b'0' + d) as char));
+        s.push('-');
+        s.extend(self.points.iter().map(|n| point_char(*n)));
+        for n in self.bar.iter().chain(self.off.iter()) {
+            s.push(std::char::from_digit(*n as u32, 16).unwrap());
+        }
+        s
+    }
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let mut halves = input.splitn(2, '-');
+        let header: Vec<char> = halves.next().unwrap_or("").chars().collect();
+        let board: Vec<char> = halves.next().unwrap_or("").chars().collect();
+        // Where the board starts in the input, past the '-'.
+        let board_at = header.len() + 1;
+
+        let player = match header.first() {
+            Some(p @ 'W') | Some(p @ 'B') => Some(*p),
+            Some('O') => None,
+            _ => return Err(ParseError::unexpected(0, "whose turn it is: W, B or O (opening roll)")),
+        };
+        let mut dice = Vec::new();
+        for (i, c) in header.iter().enumerate().skip(1) {
+            match c.to_digit(10) {
+                Some(d) if (1..=6).contains(&d) => dice.push(d as u8),
+                _ => return Err(ParseError::unexpected(i, "a die from 1 to 6")),
+            }
+        }
+        dice.sort_unstable_by(|a, b| b.cmp(a));
+        let doubles = dice.windows(2).all(|w| w[0] == w[1]);
+        if dice.len() > 4 || dice.len() > 2 && !doubles || player.is_none() && !dice.is_empty() {
+            return Err(ParseError::invalid("at most two dice left, or four of doubles, and none before the opening roll"));
+        }
+
+        if board.len() != 24 + 4 {
+            let at = board_at.min(input.chars().count()) + board.len().min(24 + 4);
+            return Err(ParseError::wrong_length("'-', 24 points, then the bar and the borne off checkers").at(at));
+        }
+        let mut points = [0; 24];
+        for (i, c) in board[..24].iter().enumerate() {
+            points[i] = match c {
+                '0' => 0,
+                'A'..='O' => (*c as u8 - b'A' + 1) as i8,
+                'a'..='o' => -((*c as u8 - b'a' + 1) as i8),
+                _ => return Err(ParseError::unexpected(board_at + i, "a point: '0', or a count as A-O (White) or a-o (Black)")),
+            };
+        }
+        let mut counts = [0; 4];
+        for (i, c) in board[24..].iter().enumerate() {
+            match c.to_digit(16) {
+                Some(n) => counts[i] = n as u8,
+                None => return Err(ParseError::unexpected(board_at + 24 + i, "a checker count as a hexadecimal digit")),
+            }
+        }
+
+        let g = BackgammonGame { points, bar: [counts[0], counts[1]], off: [counts[2], counts[3]], player, dice };
+        for p in &['W', 'B'] {
+            let on_board: u8 = (1..=24).map(|n| g.count(*p, n)).sum();
+            if on_board + g.bar[index(*p)] + g.off[index(*p)] != CHECKERS {
+                return Err(ParseError::invalid(format!("exactly 15 checkers for {}", p)));
+            }
+        }
+        Ok(g)
+    }
+
+    /// Rolls of the dice, or checker moves that use as many of the dice as
+    /// possible (and the larger die, if only one of them can be used).
+    fn moves(&self) -> Vec<Move<Self>> {
+        let p = match self.current_player() {
+            _ if self.is_over() => return Vec::new(),
+            CHANCE => return self.rolls(),
+            p => p,
+        };
+        let most = self.max_usable(p);
+        let mut steps: Vec<Step> = self.steps(p)
+            .into_iter()
+            .filter(|s| 1 + s.next.max_usable(p) == most)
+            .collect();
+        let larger = self.dice[0];
+        if most == 1 && steps.iter().any(|s| s.die == larger) {
+            steps.retain(|s| s.die == larger);
+        }
+
+        steps.into_iter()
+            .map(|mut s| {
+                let end_game = if s.next.off[index(p)] == CHECKERS {
+                    s.next.dice.clear();
+                    Some(Some(p).into_iter().collect())
+                } else {
+                    s.next.settle(p);
+                    None
+                };
+                Move { id: 10 * s.from as MoveId + s.die as MoveId, end_game, next_state: s.next }
+            })
+            .collect()
+    }
+
+    /// Rolls are named like "6-2" (for the opening roll, White's die comes
+    /// first), and checker moves like "13/7", "bar/22", "6/off", with a '*'
+    /// for a hit, numbering the points from the mover's side.
+    fn move_name(&self, id: MoveId) -> String {
+        let (from, die) = ((id / 10) as usize, (id % 10) as u8);
+        let p = self.current_player();
+        if p == CHANCE {
+            return format!("{}-{}", from, die);
+        }
+        let hit = self.step(p, from, die).is_some_and(|s| s.hit);
+        let from_name = if from == BAR { "bar".to_string() } else { from.to_string() };
+        let to = from as i32 - die as i32;
+        let to_name = if to >= 1 { to.to_string() } else { "off".to_string() };
+        format!("{}/{}{}", from_name, to_name, if hit { "*" } else { "" })
+    }
+
+    fn move_long_name(&self, id: MoveId) -> String {
+        let (from, die) = ((id / 10) as usize, (id % 10) as u8);
+        let p = self.current_player();
+        if p == CHANCE {
+            return format!("roll {}", self.move_name(id));
+        }
+        let hit = self.step(p, from, die).is_some_and(|s| s.hit);
+        let from_name = if from == BAR { "the bar".to_string() } else { format!("point {}", from) };
+        let to = from as i32 - die as i32;
+        let to_name = if to >= 1 { format!("point {}", to) } else { "off the board".to_string() };
+        format!("move from {} to {}{}", from_name, to_name, if hit { ", hitting" } else { "" })
+    }
+
+    /// The board as seen by White: points 13 to 24 along the top and 12 down
+    /// to 1 along the bottom, with White's home board at the bottom right.
+    fn render_to_text(&self) -> String {
+        const ROWS: usize = 5;
+        // What to draw at `row` of the stack on point `n`: the checkers, with
+        // the last row counting them if the stack is taller than the rows.
+        let cell = |n: usize, row: usize| -> String {
+            let checkers = self.points[n - 1];
+            let (owner, count) = (if checkers > 0 { 'W' } else { 'B' }, checkers.unsigned_abs() as usize);
+            if row == ROWS - 1 && count > ROWS {
+                format!("{:>3}", count)
+            } else if row < count {
+                format!("{:>3}", owner)
+            } else {
+                format!("{:>3}", if row == 0 { '.' } else { ' ' })
+            }
+        };
+        let half = |points: &[usize], row: usize| -> String {
+            let mut s = String::new();
+            for (k, n) in points.iter().enumerate() {
+                if k == 6 { s.push_str("  |"); }
+                s.push_str(&cell(*n, row));
+            }
+            s
+        };
+        let labels = |points: &[usize]| -> String {
+            let mut s = String::new();
+            for (k, n) in points.iter().enumerate() {
+                if k == 6 { s.push_str("  |"); }
+                s.push_str(&format!("{:>3}", n));
+            }
+            s
+        };
+
+        let top: Vec<usize> = (13..=24).collect();
+        let bottom: Vec<usize> = (1..=12).rev().collect();
+        let mut s = format!("{}\n", labels(&top));
+        for row in 0..ROWS {
+            s.push_str(&format!("{}\n", half(&top, row)));
+        }
+        s.push_str(&format!("{:>20} W {}  B {}\n", "bar:", self.bar[0], self.bar[1]));
+        for row in (0..ROWS).rev() {
+            s.push_str(&format!("{}\n", half(&bottom, row)));
+        }
+        s.push_str(&format!("{}\n", labels(&bottom)));
+        s.push_str(&format!("W: {} off, {} pips   B: {} off, {} pips\n",
+                            self.off[0], self.pip_count('W'), self.off[1], self.pip_count('B')));
+        match (self.points_won(), self.player) {
+            (Some((winner, points)), _) => {
+                let how = match points { 1 => "the game", 2 => "a gammon", _ => "a backgammon" };
+                let unit = if points == 1 { "point" } else { "points" };
+                s.push_str(&format!("{} wins {} ({} {})\n", winner, how, points, unit));
+            }
+            (None, None) => s.push_str("the opening roll decides who starts\n"),
+            (None, Some(p)) if self.dice.is_empty() => s.push_str(&format!("{} to roll\n", p)),
+            (None, Some(p)) => {
+                let dice: Vec<String> = self.dice.iter().map(|d| d.to_string()).collect();
+                s.push_str(&format!("{} to move, dice {}\n", p, dice.join("-")));
+            }
+        }
+        s
+    }
+
+    /// The difference in pip counts (fewer is better); once the game is over,
+    /// a decisive victory or loss, counted once per point won, so that search
+    /// plays for gammons.
+    fn value_for(&self, p: Player) -> i64 {
+        if let Some((winner, points)) = self.points_won() {
+            let value = VICTORY * points as i64;
+            return if winner == p { value } else { -value };
+        }
+        self.pip_count(opponent(p)) as i64 - self.pip_count(p) as i64
+    }
+
+    /// Whoever bears off all their checkers first wins (see `points_won` for
+    /// by how much).
+    fn outcome(&self) -> Outcome {
+        match self.points_won() {
+            Some((winner, _)) => Outcome::win(winner),
+            None => Outcome::Ongoing,
+        }
+    }
+
+    fn points(&self) -> u32 {
+        self.points_won().map_or(0, |(_, points)| points)
+    }
+
+    /// Doubles can only come up one way, and any other roll two ways; every
+    /// opening roll is equally likely.
+    fn chance_weight(&self, id: MoveId) -> u32 {
+        if self.player.is_some() && id / 10 != id % 10 { 2 } else { 1 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_core::play;
+
+    /// `player` to move with `dice`, and checkers on the given points
+    /// (numbered from White's side; positive for White, negative for Black).
+    /// Every other checker has been borne off.
+    fn position(player: Player, dice: &[u8], checkers: &[(usize, i8)]) -> BackgammonGame {
+        let mut g = BackgammonGame { points: [0; 24], bar: [0, 0], off: [0, 0], player: Some(player), dice: dice.to_vec() };
+        for (n, count) in checkers {
+            g.points[n - 1] = *count;
+        }
+        for p in ['W', 'B'] {
+            let on_board: u8 = (1..=24).map(|n| g.count(p, n)).sum();
+            g.off[index(p)] = CHECKERS - on_board;
+        }
+        g
+    }
+
+    fn names(g: &BackgammonGame) -> Vec<String> {
+        g.moves().iter().map(|m| g.move_name(m.id)).collect()
+    }
+
+    #[test]
+    fn the_opening_roll_picks_who_starts() {
+        let rolls = BackgammonGame::default().moves();
+        assert_eq!(rolls.len(), 30);
+        let roll = |id| rolls.iter().find(|m| m.id == id).unwrap();
+        assert_eq!(roll(62).next_state.current_player(), 'W');
+        assert_eq!(roll(62).next_state.dice, [6, 2]);
+        assert_eq!(roll(26).next_state.current_player(), 'B');
+        assert!(rolls.iter().all(|m| m.id / 10 != m.id % 10));
+    }
+
+    #[test]
+    fn bearing_off_needs_every_checker_home() {
+        let g = position('W', &[6, 1], &[(7, 1), (1, 2), (20, -15)]);
+        assert!(g.step('W', 1, 1).is_none());
+        assert_eq!(names(&g), ["7/1", "7/6"]);
+    }
+
+    #[test]
+    fn bearing_off_with_a_higher_roll_takes_the_furthest_checker() {
+        let g = position('W', &[5, 4], &[(6, 1), (2, 1), (20, -15)]);
+        // The 5 cannot bear off from 2 while a checker is still on 6.
+        assert!(g.step('W', 2, 5).is_none());
+        let g = position('W', &[5, 4], &[(3, 1), (2, 1), (20, -15)]);
+        assert!(g.step('W', 3, 5).is_some_and(|s| s.next.off[0] == 14));
+        assert!(g.step('W', 2, 5).is_none());
+    }
+
+    #[test]
+    fn a_hit_sends_the_checker_to_the_bar_and_it_must_enter_first() {
+        let g = position('W', &[3, 1], &[(8, 1), (5, -1), (24, 14), (1, -14)]);
+        let hit = play(&g, "8/5*");
+        assert_eq!(hit.next_state.bar, [0, 1]);
+        let black = position('B', &[2, 1], &[(24, 14), (1, -14)]);
+        let black = BackgammonGame { bar: [0, 1], off: [1, 0], ..black };
+        assert!(names(&black).iter().all(|name| name.starts_with("bar/")));
+    }
+
+    #[test]
+    fn gammons_and_backgammons_score_more() {
+        // White has borne off every checker; Black has borne off five.
+        let single = position('B', &[], &[(20, -10)]);
+        assert_eq!(single.points_won(), Some(('W', 1)));
+        assert_eq!(single.points(), 1);
+        // Black has borne off none.
+        let gammon = position('B', &[], &[(20, -15)]);
+        assert_eq!(gammon.points(), 2);
+        assert_eq!(gammon.value_for('W'), 2 * VICTORY);
+        // ... and still has one in White's home board.
+        let backgammon = position('B', &[], &[(20, -14), (3, -1)]);
+        assert_eq!(backgammon.points(), 3);
+        assert_eq!(backgammon.outcome(), Outcome::win('W'));
+        assert_eq!(position('B', &[], &[(20, -15), (1, 1)]).points(), 0);
+    }
+}
@@ -0,0 +1,538 @@
+use crate::game_core::{Game, Move, MoveId, Outcome, ParseError, Player, Rng, VICTORY};
+
+/// The ocean is 10 by 10; rows are lettered A to J and columns numbered 1 to
+/// 10, so "B7" is the seventh cell of the second row. Cells are numbered row by
+/// row from 0, and move ids are cell numbers plus 1.
+pub const SIZE: usize = 10;
+const CELLS: usize = SIZE * SIZE;
+
+/// The fleet each player hides: a letter, a name and a length for each ship.
+pub const FLEET: [(char, &str, usize); 5] = [
+    ('C', "Carrier", 5),
+    ('B', "Battleship", 4),
+    ('R', "Cruiser", 3),
+    ('S', "Submarine", 3),
+    ('D', "Destroyer", 2),
+];
+
+/// How many placements `determinize` tries before it gives up on matching
+/// everything the player has seen.
+const PLACEMENT_BUDGET: usize = 20000;
+
+/// A ship is placed by its top (or leftmost) cell.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ship {
+    pub cell: usize,
+    pub vertical: bool,
+}
+
+impl Ship {
+    fn cells(self, length: usize) -> impl Iterator<Item = usize> {
+        let step = if self.vertical { SIZE } else { 1 };
+        (0..length).map(move |k| self.cell + k * step)
+    }
+
+    fn fits(self, length: usize) -> bool {
+        let (r, c) = (self.cell / SIZE, self.cell % SIZE);
+        if self.vertical { r + length <= SIZE } else { c + length <= SIZE }
+    }
+}
+
+pub type Fleet = [Ship; 5];
+
+/// Battleship for players 'A' and 'B', who take turns firing a single shot at
+/// the other's hidden fleet; whoever first sinks the whole enemy fleet wins. A
+/// ship is sunk once all of its cells are hit, and the shooter is told which
+/// ship it was. Fleets are laid out at random when the game begins.
+#[derive(Clone, Debug)]
+pub struct BattleshipGame {
+    /// A's fleet, then B's.
+    pub fleets: [Fleet; 2],
+    /// The cells that A has fired at, then those that B has fired at.
+    pub shots: [Vec<bool>; 2],
+    pub player: Player,
+}
+
+impl Default for BattleshipGame {
+    fn default() -> Self {
+        Self::random_start(&mut Rng::from_entropy())
+    }
+}
+
+fn opponent(p: Player) -> Player {
+    if p == 'A' { 'B' } else { 'A' }
+}
+
+fn index(p: Player) -> usize {
+    if p == 'A' { 0 } else { 1 }
+}
+
+fn cell_name(i: usize) -> String {
+    format!("{}{}", (b'A' + (i / SIZE) as u8) as char, i % SIZE + 1)
+}
+
+impl BattleshipGame {
+    /// The ship of `p`'s fleet covering `cell`, if any.
+    fn ship_at(&self, p: Player, cell: usize) -> Option<usize> {
+        let fleet = &self.fleets[index(p)];
+        (0..FLEET.len()).find(|s| fleet[*s].cells(FLEET[*s].2).any(|c| c == cell))
+    }
+
+    /// The cells of `p`'s waters that the opponent has fired at.
+    fn fired_at(&self, p: Player) -> &[bool] {
+        &self.shots[index(opponent(p))]
+    }
+
+    fn is_sunk(&self, p: Player, ship: usize) -> bool {
+        let fired_at = self.fired_at(p);
+        self.fleets[index(p)][ship].cells(FLEET[ship].2).all(|c| fired_at[c])
+    }
+
+    fn fleet_sunk(&self, p: Player) -> bool {
+        (0..FLEET.len()).all(|s| self.is_sunk(p, s))
+    }
+
+    fn hits_on(&self, p: Player) -> usize {
+        let fired_at = self.fired_at(p);
+        (0..CELLS).filter(|c| fired_at[*c] && self.ship_at(p, *c).is_some()).count()
+    }
+
+    /// What `p` has learned of the enemy's waters: '-' where `p` has not
+    /// fired, 'o' for a miss and 'x' for a hit.
+    fn marks(&self, p: Player) -> Vec<char> {
+        let target = opponent(p);
+        (0..CELLS)
+            .map(|c| match (self.shots[index(p)][c], self.ship_at(target, c)) {
+                (false, _) => '-',
+                (true, None) => 'o',
+                (true, Some(_)) => 'x',
+            })
+            .collect()
+    }
+
+    /// The chart of `p`'s own waters: ships by letter, hits on them as 'X',
+    /// the opponent's misses as 'o'.
+    fn own_chart(&self, p: Player) -> Vec<char> {
+        let fired_at = self.fired_at(p);
+        (0..CELLS)
+            .map(|c| match (fired_at[c], self.ship_at(p, c)) {
+                (true, Some(_)) => 'X',
+                (true, None) => 'o',
+                (false, Some(s)) => FLEET[s].0,
+                (false, None) => '.',
+            })
+            .collect()
+    }
+}
+
+fn unparse_fleet(fleet: &Fleet) -> String {
+    let mut s = String::new();
+    for ship in fleet {
+        s.push((b'a' + (ship.cell / SIZE) as u8) as char);
+        s.push((b'0' + (ship.cell % SIZE) as u8) as char);
+        s.push(if ship.vertical { 'v' } else { 'h' });
+    }
+    s
+}
+
+/// Parses the fleet serialized in `chars`, which start at position `at` of
+/// the input.
+fn parse_fleet(chars: &[char], at: usize) -> Result<Fleet, ParseError> {
+    let mut fleet = [Ship { cell: 0, vertical: false }; 5];
+    let mut occupied = [false; CELLS];
+    for (s, spec) in chars.chunks(3).enumerate() {
+        let at = at + 3 * s;
+        let r = match spec[0] {
+            r @ 'a'..='j' => r as usize - 'a' as usize,
+            _ => return Err(ParseError::unexpected(at, "a row from a to j")),
+        };
+        let c = match spec[1].to_digit(10) {
+            Some(c) => c as usize,
+            None => return Err(ParseError::unexpected(at + 1, "a column from 0 to 9")),
+        };
+        let vertical = match spec[2] {
+            'v' => true,
+            'h' => false,
+            _ => return Err(ParseError::unexpected(at + 2, "'h' (horizontally) or 'v' (vertically)")),
+        };
+        let ship = Ship { cell: r * SIZE + c, vertical };
+        if !ship.fits(FLEET[s].2) {
+            return Err(ParseError::invalid(format!("the {} within the ocean", FLEET[s].1)).at(at));
+        }
+        for cell in ship.cells(FLEET[s].2) {
+            if occupied[cell] {
+                return Err(ParseError::invalid(format!("the {} clear of other ships", FLEET[s].1)).at(at));
+            }
+            occupied[cell] = true;
+        }
+        fleet[s] = ship;
+    }
+    Ok(fleet)
+}
+
+fn unparse_shots(shots: &[bool]) -> String {
+    shots.chunks(4)
+        .map(|bits| {
+            let n = bits.iter().enumerate().fold(0, |acc, (i, b)| acc | ((*b as u32) << i));
+            std::char::from_digit(n, 16).unwrap()
+        })
+        .collect()
+}
+
+/// Parses the shots serialized in `hex`, which start at position `at` of the
+/// input.
+fn parse_shots(hex: &[char], at: usize) -> Result<Vec<bool>, ParseError> {
+    let mut shots = vec![false; CELLS];
+    for (i, c) in hex.iter().enumerate() {
+        let n = match c.to_digit(16) {
+            Some(n) => n,
+            None => return Err(ParseError::unexpected(at + i, "shots as hexadecimal digits")),
+        };
+        for bit in 0..4 {
+            shots[4 * i + bit] = n & (1 << bit) != 0;
+        }
+    }
+    Ok(shots)
+}
+
+/// The length of a serialized fleet (three characters per ship) and of a
+/// serialized set of shots (four cells per hexadecimal digit).
+const FLEET_LEN: usize = 3 * FLEET.len();
+const SHOTS_LEN: usize = CELLS / 4;
+
+impl Game for BattleshipGame {
+    const NAME: &'static str = "Battleship";
+
+    const HIDDEN_INFORMATION: bool = true;
+
+    /// Both fleets are laid out at random.
+    fn random_start(rng: &mut Rng) -> Self {
+        let unconstrained = |rng: &mut Rng| Layout::default().sample(rng).unwrap();
+        BattleshipGame {
+            fleets: [unconstrained(rng), unconstrained(rng)],
+            shots: [vec![false; CELLS], vec![false; CELLS]],
+            player: 'A',
+        }
+    }
+
+    fn current_player(&self) -> Player {
+        self.player
+    }
+
+    /// The serialized form is the player to move, A's fleet, B's fleet, the
+    /// cells A has fired at and the cells B has fired at. Each ship in a
+    /// fleet (in the order of `FLEET`) is its top or left cell, as a row
+    /// letter from 'a' and a column digit from '0', then 'h' or 'v' for
+    /// horizontal or vertical. Shots are a bitset of the cells in hexadecimal,
+    /// four cells per digit with the lowest cell in the lowest bit.
+    fn unparse(&self) -> String {
+        let mut s = String::new();
+        s.push(self.player);
+        s.push_str(&unparse_fleet(&self.fleets[0]));
+        s.push_str(&unparse_fleet(&self.fleets[1]));
+        s.push_str(&unparse_shots(&self.shots[0]));
+        s.push_str(&unparse_shots(&self.shots[1]));
+        s
+    }
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let chars: Vec<char> = input.chars().collect();
+        let len = 1 + 2 * FLEET_LEN + 2 * SHOTS_LEN;
+        if chars.len() != len {
+            return Err(ParseError::wrong_length("the player to move, two fleets and two sets of shots")
+                .at(chars.len().min(len)));
+        }
+        let player = match chars[0] {
+            p @ 'A' | p @ 'B' => p,
+            _ => return Err(ParseError::unexpected(0, "the player to move, A or B")),
+        };
+        let (fleets, shots) = chars[1..].split_at(2 * FLEET_LEN);
+        let shots_at = 1 + 2 * FLEET_LEN;
+        let g = BattleshipGame {
+            fleets: [parse_fleet(&fleets[..FLEET_LEN], 1)?, parse_fleet(&fleets[FLEET_LEN..], 1 + FLEET_LEN)?],
+            shots: [
+                parse_shots(&shots[..SHOTS_LEN], shots_at)?,
+                parse_shots(&shots[SHOTS_LEN..], shots_at + SHOTS_LEN)?,
+            ],
+            player,
+        };
+        let fired = |p: Player| g.shots[index(p)].iter().filter(|s| **s).count();
+        let (a, b) = (fired('A'), fired('B'));
+        if !(player == 'A' && a == b || player == 'B' && a == b + 1) {
+            return Err(ParseError::invalid("players taking turns firing, starting with A"));
+        }
+        Ok(g)
+    }
+
+    /// Move `i` fires at cell `i - 1`. Once a fleet is sunk there are no
+    /// moves left.
+    fn moves(&self) -> Vec<Move<Self>> {
+        if self.fleet_sunk('A') || self.fleet_sunk('B') {
+            return Vec::new();
+        }
+        let target = opponent(self.player);
+        let mut v = Vec::new();
+        for cell in 0..CELLS {
+            if self.shots[index(self.player)][cell] { continue; }
+            let mut next_state = self.clone();
+            next_state.shots[index(self.player)][cell] = true;
+            next_state.player = target;
+            let end_game = if next_state.fleet_sunk(target) {
+                Some(Some(self.player).into_iter().collect())
+            } else {
+                None
+            };
+            v.push(Move { id: cell as MoveId + 1, end_game, next_state });
+        }
+        v
+    }
+
+    fn move_name(&self, id: MoveId) -> String {
+        cell_name(id as usize - 1)
+    }
+
+    fn move_long_name(&self, id: MoveId) -> String {
+        format!("fire at {}", self.move_name(id))
+    }
+
+    /// Both fleets, laid bare: this gives the whole game away, so players
+    /// should see `render_for` instead.
+    fn render_to_text(&self) -> String {
+        let charts = [self.own_chart('A'), self.own_chart('B')];
+        let mut s = render_side_by_side(["A's fleet", "B's fleet"], charts);
+        s.push_str(&format!("{} to fire\n", self.player));
+        s
+    }
+
+    /// The hits on the enemy fleet, less the hits on one's own; once a fleet
+    /// is sunk, a decisive victory or loss.
+    fn value_for(&self, p: Player) -> i64 {
+        if self.fleet_sunk(opponent(p)) {
+            return VICTORY;
+        }
+        if self.fleet_sunk(p) {
+            return -VICTORY;
+        }
+        self.hits_on(opponent(p)) as i64 - self.hits_on(p) as i64
+    }
+
+    /// Whoever sinks the other's fleet first wins. Which fleet is sunk is
+    /// no secret, so every player may be told.
+    fn outcome(&self) -> Outcome {
+        match ['A', 'B'].iter().find(|p| self.fleet_sunk(opponent(**p))) {
+            Some(p) => Outcome::win(*p),
+            None => Outcome::Ongoing,
+        }
+    }
+
+    /// The player to move and `p`, `p`'s own fleet and the cells the opponent
+    /// has fired at (serialized as in `unparse`), what `p` has learned of the
+    /// enemy's waters (one character per cell: '-' not fired at, 'o' a miss,
+    /// 'x' a hit), then one character per enemy ship: its letter if sunk and
+    /// '-' otherwise.
+    fn observation(&self, p: Player) -> String {
+        let target = opponent(p);
+        let mut s = String::new();
+        s.push(self.player);
+        s.push(p);
+        s.push_str(&unparse_fleet(&self.fleets[index(p)]));
+        s.push_str(&unparse_shots(self.fired_at(p)));
+        s.extend(self.marks(p));
+        s.extend((0..FLEET.len()).map(|k| if self.is_sunk(target, k) { FLEET[k].0 } else { '-' }));
+        s
+    }
+
+    fn render_for(&self, p: Player) -> String {
+        let target = opponent(p);
+        let marks = self.marks(p).into_iter().map(|m| if m == '-' { '.' } else { m }).collect();
+        let mut s = render_side_by_side(["Your fleet", "Enemy waters"], [self.own_chart(p), marks]);
+        let sunk: Vec<&str> = (0..FLEET.len())
+            .filter(|k| self.is_sunk(target, *k))
+            .map(|k| FLEET[k].1)
+            .collect();
+        if !sunk.is_empty() {
+            s.push_str(&format!("Sunk: {}\n", sunk.join(", ")));
+        }
+        s.push_str(&format!("You are {}; {} to fire\n", p, self.player));
+        s
+    }
+
+    /// Keeps `p`'s fleet and all the shots, but lays out the enemy fleet
+    /// afresh wherever it could be, given the hits, misses and sunk ships that
+    /// `p` has seen. If no such layout turns up quickly, the sunk ships and
+    /// unexplained hits are disregarded, and failing even that, the enemy
+    /// fleet is left where it is.
+    fn determinize(&self, p: Player, seed: u64) -> Self {
+        let target = opponent(p);
+        let mut rng = Rng::new(seed);
+        let marks = self.marks(p);
+        let strict = Layout {
+            hits: marks.iter().map(|m| *m == 'x').collect(),
+            misses: marks.iter().map(|m| *m == 'o').collect(),
+            sunk: (0..FLEET.len()).map(|k| Some(self.is_sunk(target, k))).collect(),
+        };
+        let loose = Layout { hits: vec![false; CELLS], sunk: vec![None; FLEET.len()], ..strict.clone() };
+        let mut guess = self.clone();
+        if let Some(fleet) = strict.sample(&mut rng).or_else(|| loose.sample(&mut rng)) {
+            guess.fleets[index(target)] = fleet;
+        }
+        guess
+    }
+}
+
+/// What is known of a fleet's layout: cells with a hit (which must all be
+/// covered), cells with a miss (which must not be), and whether each ship is
+/// known to be sunk (all of its cells hit) or afloat.
+#[derive(Clone)]
+struct Layout {
+    hits: Vec<bool>,
+    misses: Vec<bool>,
+    sunk: Vec<Option<bool>>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout { hits: vec![false; CELLS], misses: vec![false; CELLS], sunk: vec![None; FLEET.len()] }
+    }
+}
+
+impl Layout {
+    /// A random fleet consistent with what is known, found by a randomized
+    /// backtracking search, or `None` if the search runs out of budget.
+    ///
+    /// While some hit is left uncovered, the search tries every ship that
+    /// could cover it, in every way; only then does it lay out the rest of
+    /// the fleet. (Laying ships out in order and checking the hits at the
+    /// end almost never covers them all within the budget.)
+    fn sample(&self, rng: &mut Rng) -> Option<Fleet> {
+        let mut fleet = [Ship { cell: 0, vertical: false }; 5];
+        let mut placed = [false; 5];
+        let mut occupied = vec![false; CELLS];
+        let mut budget = PLACEMENT_BUDGET;
+        if self.place(&mut placed, &mut fleet, &mut occupied, rng, &mut budget) { Some(fleet) } else { None }
+    }
+
+    fn place(&self,
+             placed: &mut [bool; 5],
+             fleet: &mut Fleet,
+             occupied: &mut [bool],
+             rng: &mut Rng,
+             budget: &mut usize) -> bool
+    {
+        let uncovered: Vec<usize> = (0..CELLS).filter(|c| self.hits[*c] && !occupied[*c]).collect();
+        let unplaced: Vec<usize> = (0..FLEET.len()).filter(|s| !placed[*s]).collect();
+        if unplaced.is_empty() {
+            return uncovered.is_empty();
+        }
+        // Every hit not yet covered needs one of the remaining ships.
+        let remaining: usize = unplaced.iter().map(|s| FLEET[*s].2).sum();
+        if uncovered.len() > remaining {
+            return false;
+        }
+        // A sunk ship lies on hits only, so once they are all covered there
+        // is nowhere left for one.
+        if uncovered.is_empty() && unplaced.iter().any(|s| self.sunk[*s] == Some(true)) {
+            return false;
+        }
+
+        // Cover the first uncovered hit with any remaining ship, or else lay
+        // out the next ship anywhere.
+        let ships = if uncovered.is_empty() { &unplaced[..1] } else { &unplaced[..] };
+        let mut candidates: Vec<(usize, Ship)> = ships.iter()
+            .flat_map(|s| (0..2 * CELLS).map(move |i| (*s, Ship { cell: i / 2, vertical: i % 2 == 1 })))
+            .filter(|(s, ship)| ship.fits(FLEET[*s].2))
+            .filter(|(s, ship)| uncovered.first().is_none_or(|h| ship.cells(FLEET[*s].2).any(|c| c == *h)))
+            .collect();
+        rng.shuffle(&mut candidates);
+        for (s, ship) in candidates {
+            if *budget == 0 {
+                return false;
+            }
+            *budget -= 1;
+            let length = FLEET[s].2;
+            if ship.cells(length).any(|c| occupied[c] || self.misses[c]) { continue; }
+            if let Some(sunk) = self.sunk[s] {
+                if ship.cells(length).all(|c| self.hits[c]) != sunk { continue; }
+            }
+            ship.cells(length).for_each(|c| occupied[c] = true);
+            fleet[s] = ship;
+            placed[s] = true;
+            if self.place(placed, fleet, occupied, rng, budget) {
+                return true;
+            }
+            placed[s] = false;
+            ship.cells(length).for_each(|c| occupied[c] = false);
+        }
+        false
+    }
+}
+
+/// Two labeled 10x10 charts next to each other.
+fn render_side_by_side(titles: [&str; 2], charts: [Vec<char>; 2]) -> String {
+    let header: String = (1..=SIZE).map(|c| format!("{:>3}", c)).collect();
+    let mut s = format!("  {:<33}{}\n", titles[0], titles[1]);
+    s.push_str(&format!(" {}     {}\n", header, header));
+    for r in 0..SIZE {
+        let row = |chart: &Vec<char>| -> String {
+            chart[r * SIZE..(r + 1) * SIZE].iter().map(|c| format!("{:>3}", c)).collect()
+        };
+        let label = (b'A' + r as u8) as char;
+        s.push_str(&format!("{}{}    {}{}\n", label, row(&charts[0]), label, row(&charts[1])));
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fleet(spec: &str) -> Fleet {
+        parse_fleet(&spec.chars().collect::<Vec<_>>(), 0).unwrap()
+    }
+
+    fn shots(cells: &[&str]) -> Vec<bool> {
+        let mut shots = vec![false; CELLS];
+        for name in cells {
+            shots[(0..CELLS).find(|c| cell_name(*c) == *name).unwrap()] = true;
+        }
+        shots
+    }
+
+    /// A has sunk B's destroyer, hit the carrier once and missed once; B has
+    /// missed four times. B's fleet is laid out as `b`.
+    fn game(b: &str) -> BattleshipGame {
+        BattleshipGame {
+            fleets: [fleet("a0hb0hc0hd0he0h"), fleet(b)],
+            shots: [shots(&["E1", "E2", "A1", "J10"]), shots(&["J1", "J2", "J3", "J4"])],
+            player: 'A',
+        }
+    }
+
+    #[test]
+    fn a_player_learns_nothing_of_unhit_enemy_ships() {
+        // The same hits, misses and sunk ship, with the unhit ships moved.
+        let (g, moved) = (game("a0hb0hc0hd0he0h"), game("a0hg0hh0hi0he0h"));
+        assert_ne!(g.unparse(), moved.unparse());
+        assert_eq!(g.observation('A'), moved.observation('A'));
+        assert_eq!(g.render_for('A'), moved.render_for('A'));
+        assert_eq!(g.moves_for('A'), moved.moves_for('A'));
+        assert!(g.render_for('A').contains("Sunk: Destroyer"));
+
+        // B's own fleet is no secret from B.
+        assert_ne!(g.observation('B'), moved.observation('B'));
+        assert!(g.moves_for('B').is_empty());
+    }
+
+    #[test]
+    fn determinize_keeps_every_hit_miss_and_sunk_ship() {
+        let g = game("a0hb0hc0hd0he0h");
+        let mut moved = false;
+        for seed in 0..20 {
+            let guess = g.determinize('A', seed);
+            assert_eq!(guess.observation('A'), g.observation('A'), "seed {}", seed);
+            assert_eq!(guess.fleets[0], g.fleets[0]);
+            moved |= guess.fleets[1] != g.fleets[1];
+        }
+        assert!(moved, "the enemy fleet is always left where it is");
+    }
+}
@@ -0,0 +1,194 @@
+//! Compares the ways of generating moves on a few positions of a game.
+//!
+//! For games with in-place moves, it compares building every next state with
+//! `Game::moves` against making and unmaking moves in place. It times walking
+//! the whole game tree a few moves deep, which is all move generation, and the
+//! search itself, where evaluating positions takes its share too. Both ways
+//! must agree.
+//!
+//! For games with lazy moves, it compares random playouts that pick each move
+//! from all of `Game::moves` against ones using `Game::random_move`.
+//!
+//! Usage: `local bench <game> [depth, or number of playouts]`
+
+use std::time::{Duration, Instant};
+
+use crate::game_core::{alpha_beta, playout, sample_chance, Game, Move, MoveApi, Rng, CHANCE};
+use crate::hex::HexGame;
+use crate::othello::OthelloGame;
+use crate::qubic::QubicGame;
+use crate::tictactoe::TicTacToeGame;
+
+/// How many positions are timed: the start, then positions reached by random
+/// moves from it.
+const POSITIONS: usize = 5;
+
+/// The longest playout, which no game here comes near.
+const MAX_PLAYOUT: usize = 10_000;
+
+/// Benchmarks the game named by the first of `args`, looking as many moves
+/// deep (or playing as many playouts) as the second says.
+pub fn run(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
+    let name = args.next().unwrap_or_else(|| QubicGame::NAME.to_string());
+    let count = args.next().map(|n| n.parse()).transpose()?;
+    if name.eq_ignore_ascii_case(TicTacToeGame::NAME) {
+        bench::<TicTacToeGame>(count.unwrap_or(9));
+    } else if name.eq_ignore_ascii_case(QubicGame::NAME) {
+        bench::<QubicGame>(count.unwrap_or(3));
+    } else if name.eq_ignore_ascii_case(HexGame::NAME) {
+        bench_playouts::<HexGame>(count.unwrap_or(100));
+    } else if name.eq_ignore_ascii_case(OthelloGame::NAME) {
+        bench_playouts::<OthelloGame>(count.unwrap_or(100));
+    } else {
+        let games = [TicTacToeGame::NAME, QubicGame::NAME, HexGame::NAME, OthelloGame::NAME];
+        return Err(format!("`{}` has nothing to compare; try {}", name, games.join(", ")).into());
+    }
+    Ok(())
+}
+
+/// Times `f` once with each way of making moves, returning the times.
+fn time_both<T: PartialEq + std::fmt::Debug>(what: &str, mut f: impl FnMut(MoveApi) -> T) -> [Duration; 2] {
+    let mut times = [Duration::ZERO; 2];
+    let mut results = Vec::new();
+    for (k, api) in [MoveApi::Moves, MoveApi::InPlace].iter().enumerate() {
+        let start = Instant::now();
+        results.push(f(*api));
+        times[k] = start.elapsed();
+    }
+    assert_eq!(results[0], results[1], "both ways of {} must agree", what);
+    println!("  {:10} {:10.2} ms with moves(), {:10.2} ms in place",
+             what,
+             times[0].as_secs_f64() * 1000.0,
+             times[1].as_secs_f64() * 1000.0);
+    times
+}
+
+fn bench<G: Game>(depth: usize) {
+    // A fixed seed, so that runs can be compared.
+    let mut rng = Rng::new(POSITIONS as u64);
+    let mut game = G::default();
+    let mut walking = [Duration::ZERO; 2];
+    let mut searching = [Duration::ZERO; 2];
+    for _ in 0..POSITIONS {
+        let moves = game.moves();
+        if moves.is_empty() {
+            break;
+        }
+        println!("{}", game.unparse());
+        let times = time_both("walking", |api| match api {
+            MoveApi::Moves => count_lines(&game, depth),
+            MoveApi::InPlace => count_lines_in_place(&mut game.clone(), depth),
+        });
+        walking = [walking[0] + times[0], walking[1] + times[1]];
+        let p = game.current_player();
+        let times = time_both("searching", |api| alpha_beta(&game, &moves, p, depth, api));
+        searching = [searching[0] + times[0], searching[1] + times[1]];
+
+        // On to a later position, two random moves on.
+        for _ in 0..2 {
+            let moves = game.moves();
+            if let Some(m) = moves.get(rng.below(moves.len().max(1))) {
+                if m.end_game.is_none() {
+                    game = m.next_state.clone();
+                }
+            }
+        }
+    }
+    for (what, times) in &[("walking", walking), ("searching", searching)] {
+        println!("{} {} moves deep took {:.2} ms with moves() and {:.2} ms in place: {:.1} times as fast",
+                 what,
+                 depth,
+                 times[0].as_secs_f64() * 1000.0,
+                 times[1].as_secs_f64() * 1000.0,
+                 times[0].as_secs_f64() / times[1].as_secs_f64());
+    }
+}
+
+/// The number of lines of play from `state`, each `depth` moves long or
+/// ending the game sooner.
+fn count_lines<G: Game>(state: &G, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    state.moves()
+        .iter()
+        .map(|m| if m.end_game.is_some() { 1 } else { count_lines(&m.next_state, depth - 1) })
+        .sum()
+}
+
+/// Like `count_lines`, making and unmaking moves on `state`.
+fn count_lines_in_place<G: Game>(state: &mut G, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut count = 0;
+    for id in state.move_ids() {
+        let made = state.make_move(id);
+        count += if made.end_game.is_some() { 1 } else { count_lines_in_place(state, depth - 1) };
+        state.unmake_move(id, made.undo);
+    }
+    count
+}
+
+/// Times `count` random playouts from the start of the game each way.
+fn bench_playouts<G: Game>(count: usize) {
+    let mut times = [Duration::ZERO; 2];
+    let mut moves_made = [0; 2];
+    for (k, with_random_move) in [false, true].iter().enumerate() {
+        let mut rng = Rng::new(count as u64);
+        let start = Instant::now();
+        for _ in 0..count {
+            moves_made[k] += if *with_random_move {
+                playout_length(&G::default(), &mut rng, |g, rng| g.random_move(rng))
+            } else {
+                playout_length(&G::default(), &mut rng, |g, rng| {
+                    let mut moves = g.moves();
+                    match moves.len() {
+                        0 => None,
+                        n => Some(moves.swap_remove(rng.below(n))),
+                    }
+                })
+            };
+        }
+        times[k] = start.elapsed();
+    }
+    // The same playouts again, as `game_core::playout` plays them.
+    let mut rng = Rng::new(count as u64);
+    let finished = (0..count).filter(|_| playout(&G::default(), &mut rng, MAX_PLAYOUT).is_some()).count();
+    assert_eq!(finished, count, "every playout must finish");
+    println!("{} playouts ({} and {} moves) took {:.2} ms picking from moves() and {:.2} ms with random_move: {:.1} times as fast",
+             count,
+             moves_made[0],
+             moves_made[1],
+             times[0].as_secs_f64() * 1000.0,
+             times[1].as_secs_f64() * 1000.0,
+             times[0].as_secs_f64() / times[1].as_secs_f64());
+}
+
+/// The number of moves in a random playout from `state`, picking each move
+/// with `pick`.
+fn playout_length<G: Game>(state: &G,
+                           rng: &mut Rng,
+                           pick: impl Fn(&G, &mut Rng) -> Option<Move<G>>) -> usize
+{
+    let mut state = state.clone();
+    for length in 1..=MAX_PLAYOUT {
+        let m = if state.current_player() == CHANCE {
+            let moves = state.moves();
+            if moves.is_empty() {
+                return length - 1;
+            }
+            sample_chance(&state, &moves, rng).clone()
+        } else {
+            match pick(&state, rng) {
+                Some(m) => m,
+                None => return length - 1,
+            }
+        };
+        if m.end_game.is_some() {
+            return length;
+        }
+        state = m.next_state;
+    }
+    MAX_PLAYOUT
+}
@@ -0,0 +1,358 @@
+//! Counterfactual regret minimization (CFR), which solves two-player zero-sum
+//! games with hidden information, like poker, ahead of time.
+//!
+//! Such games cannot be searched move by move the way `game_core::search`
+//! does: a player cannot know which state they are in, only their
+//! *information set*, the states they cannot tell apart (in this
+//! architecture, those with the same `Game::observation`). And good play has
+//! to be unpredictable, mixing moves at random, or it gives itself away.
+//!
+//! CFR plays the game against itself over and over, walking the whole game
+//! tree each time. At every information set it keeps, for each move, the
+//! regret of not always having made that move, and plays each move in
+//! proportion to its positive regret. The average of the strategies played
+//! converges to a Nash equilibrium. This implements CFR+, a variant that
+//! forgets negative regrets and weighs later iterations more, which converges
+//! much faster.
+//!
+//! Payoffs are the `value_for` of finished games, which must sum to zero.
+
+use std::collections::HashMap;
+
+use crate::game_core::{sample_policy, Game, Move, MoveId, Player, Rng, CHANCE};
+
+/// A mixed strategy for each information set: how likely each move is.
+#[derive(Clone, Debug, Default)]
+pub struct Strategy {
+    policies: HashMap<String, Vec<(MoveId, f64)>>,
+}
+
+impl Strategy {
+    /// The probability of each move in `info_set` (an observation), if the
+    /// strategy knows it.
+    pub fn policy(&self, info_set: &str) -> Option<&[(MoveId, f64)]> {
+        self.policies.get(info_set).map(|p| &p[..])
+    }
+
+    /// Picks one of `moves` (the moves of `state`) at random by the policy
+    /// for what the player to move observes, or uniformly if the strategy
+    /// does not know it.
+    pub fn sample<'a, G: Game>(&self, state: &G, moves: &'a [Move<G>], rng: &mut Rng) -> &'a Move<G> {
+        let info_set = state.observation(state.current_player());
+        sample_policy(self.policy(&info_set).unwrap_or(&[]), moves, rng)
+    }
+
+    /// The probability of each of `moves` by `policy`, or uniform if there
+    /// is none.
+    fn probabilities(&self, info_set: &str, moves: &[MoveId]) -> Vec<f64> {
+        match self.policy(info_set) {
+            Some(policy) => moves.iter()
+                .map(|id| policy.iter().find(|(m, _)| m == id).map_or(0.0, |(_, q)| *q))
+                .collect(),
+            None => vec![1.0 / moves.len() as f64; moves.len()],
+        }
+    }
+
+    /// Each information set of the strategy, with its policy, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Vec<(MoveId, f64)>)> {
+        let mut entries: Vec<_> = self.policies.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        entries.into_iter()
+    }
+}
+
+/// What CFR keeps for an information set.
+struct InfoSet {
+    moves: Vec<MoveId>,
+    /// The regret of not having made each move (never negative, in CFR+).
+    regrets: Vec<f64>,
+    /// The regrets added in the current iteration, which CFR+ only floors
+    /// at zero once the whole iteration is done.
+    new_regrets: Vec<f64>,
+    /// The strategies played so far, each weighted by how likely the player
+    /// was to get here and by the iteration.
+    strategy_sums: Vec<f64>,
+}
+
+impl InfoSet {
+    /// The strategy for the next iteration: each move in proportion to its
+    /// regret (or uniform, if nothing is regretted).
+    fn current_strategy(&self) -> Vec<f64> {
+        let total: f64 = self.regrets.iter().sum();
+        if total > 0.0 {
+            self.regrets.iter().map(|r| r / total).collect()
+        } else {
+            vec![1.0 / self.moves.len() as f64; self.moves.len()]
+        }
+    }
+
+    fn average_strategy(&self) -> Vec<f64> {
+        let total: f64 = self.strategy_sums.iter().sum();
+        if total > 0.0 {
+            self.strategy_sums.iter().map(|s| s / total).collect()
+        } else {
+            vec![1.0 / self.moves.len() as f64; self.moves.len()]
+        }
+    }
+}
+
+/// Trains a strategy for the game starting at `root` (which should include
+/// the chance moves that deal any hidden cards).
+pub struct Cfr<G> {
+    root: G,
+    players: Vec<Player>,
+    info_sets: HashMap<String, InfoSet>,
+    iterations: usize,
+}
+
+impl<G: Game> Cfr<G> {
+    pub fn new(root: G) -> Self {
+        let mut players = Vec::new();
+        collect_players(&root, &mut players);
+        players.sort_unstable();
+        Cfr { root, players, info_sets: HashMap::new(), iterations: 0 }
+    }
+
+    /// How many iterations have been run.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// Runs `iterations` more iterations, updating each player in turn.
+    pub fn train(&mut self, iterations: usize) {
+        for _ in 0..iterations {
+            self.iterations += 1;
+            let weight = self.iterations as f64;
+            for p in self.players.clone() {
+                let root = self.root.clone();
+                self.traverse(&root, p, 1.0, 1.0, weight);
+                for info_set in self.info_sets.values_mut() {
+                    for (regret, new_regret) in info_set.regrets.iter_mut().zip(&mut info_set.new_regrets) {
+                        *regret = (*regret + *new_regret).max(0.0);
+                        *new_regret = 0.0;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The value of `state` for `p`, updating `p`'s regrets and average
+    /// strategy along the way. `reach` is how likely `p`'s own moves are to
+    /// lead here, and `others_reach` how likely everyone else's (and
+    /// chance's) are.
+    fn traverse(&mut self, state: &G, p: Player, reach: f64, others_reach: f64, weight: f64) -> f64 {
+        let moves = state.moves();
+        if moves.is_empty() {
+            return state.value_for(p) as f64;
+        }
+        let mover = state.current_player();
+        if mover == CHANCE {
+            let total: u32 = moves.iter().map(|m| state.chance_weight(m.id)).sum();
+            return moves.iter()
+                .map(|m| {
+                    let q = state.chance_weight(m.id) as f64 / total as f64;
+                    q * self.traverse(&m.next_state, p, reach, others_reach * q, weight)
+                })
+                .sum();
+        }
+
+        let key = state.observation(mover);
+        let strategy = self.info_sets
+            .entry(key.clone())
+            .or_insert_with(|| InfoSet {
+                moves: moves.iter().map(|m| m.id).collect(),
+                regrets: vec![0.0; moves.len()],
+                new_regrets: vec![0.0; moves.len()],
+                strategy_sums: vec![0.0; moves.len()],
+            })
+            .current_strategy();
+
+        if mover != p {
+            return moves.iter()
+                .zip(&strategy)
+                .filter(|(_, q)| **q > 0.0)
+                .map(|(m, q)| q * self.traverse(&m.next_state, p, reach, others_reach * q, weight))
+                .sum();
+        }
+
+        let values: Vec<f64> = moves.iter()
+            .zip(&strategy)
+            .map(|(m, q)| self.traverse(&m.next_state, p, reach * q, others_reach, weight))
+            .collect();
+        let value: f64 = values.iter().zip(&strategy).map(|(v, q)| v * q).sum();
+        let info_set = self.info_sets.get_mut(&key).unwrap();
+        for (k, v) in values.iter().enumerate() {
+            info_set.new_regrets[k] += others_reach * (v - value);
+            info_set.strategy_sums[k] += weight * reach * strategy[k];
+        }
+        value
+    }
+
+    /// The average strategy so far, which is what converges to equilibrium.
+    pub fn strategy(&self) -> Strategy {
+        let policies = self.info_sets.iter()
+            .map(|(key, info_set)| {
+                let policy = info_set.moves.iter().cloned().zip(info_set.average_strategy()).collect();
+                (key.clone(), policy)
+            })
+            .collect();
+        Strategy { policies }
+    }
+
+    /// How far the average strategy is from equilibrium: how much better than
+    /// it each player could do, on average, by playing a best response to
+    /// the others' strategies. It is zero at equilibrium, and measured in the
+    /// same units as `value_for`.
+    pub fn exploitability(&self) -> f64 {
+        let strategy = self.strategy();
+        let total: f64 = self.players.iter().map(|p| best_response_value(&self.root, *p, &strategy)).sum();
+        total / self.players.len() as f64
+    }
+}
+
+/// Adds the players who make choices anywhere in the game tree below `state`.
+fn collect_players<G: Game>(state: &G, players: &mut Vec<Player>) {
+    let mover = state.current_player();
+    let moves = state.moves();
+    if !moves.is_empty() && mover != CHANCE && !players.contains(&mover) {
+        players.push(mover);
+    }
+    for m in moves {
+        collect_players(&m.next_state, players);
+    }
+}
+
+/// The value for `p` of the game starting at `root` when everyone else plays
+/// `strategy` and `p` plays the best response to it.
+pub fn best_response_value<G: Game>(root: &G, p: Player, strategy: &Strategy) -> f64 {
+    // p's best move in an information set depends on every state in it, each
+    // weighted by how likely the others (and chance) are to lead there.
+    let mut states = HashMap::new();
+    collect_states(root, p, strategy, 1.0, &mut states);
+    let mut br = BestResponse { p, strategy, states, choices: HashMap::new(), values: HashMap::new() };
+    br.value(root)
+}
+
+/// Groups the states where `p` is to move by information set, each with how
+/// likely the others are to lead there.
+fn collect_states<G: Game>(state: &G,
+                           p: Player,
+                           strategy: &Strategy,
+                           others_reach: f64,
+                           states: &mut HashMap<String, Vec<(G, f64)>>)
+{
+    let moves = state.moves();
+    if moves.is_empty() || others_reach == 0.0 {
+        return;
+    }
+    let mover = state.current_player();
+    let probabilities = move_probabilities(state, &moves, p, strategy);
+    if mover == p {
+        states.entry(state.observation(p)).or_default().push((state.clone(), others_reach));
+    }
+    for (m, q) in moves.iter().zip(probabilities) {
+        collect_states(&m.next_state, p, strategy, others_reach * q, states);
+    }
+}
+
+/// How likely each of `moves` is, from `p`'s point of view: by chance, by
+/// `strategy`, or (for `p`'s own moves) certain.
+fn move_probabilities<G: Game>(state: &G, moves: &[Move<G>], p: Player, strategy: &Strategy) -> Vec<f64> {
+    let mover = state.current_player();
+    if mover == CHANCE {
+        let total: u32 = moves.iter().map(|m| state.chance_weight(m.id)).sum();
+        moves.iter().map(|m| state.chance_weight(m.id) as f64 / total as f64).collect()
+    } else if mover == p {
+        vec![1.0; moves.len()]
+    } else {
+        let ids: Vec<MoveId> = moves.iter().map(|m| m.id).collect();
+        strategy.probabilities(&state.observation(mover), &ids)
+    }
+}
+
+struct BestResponse<'a, G> {
+    p: Player,
+    strategy: &'a Strategy,
+    states: HashMap<String, Vec<(G, f64)>>,
+    /// The best move in each of `p`'s information sets, once worked out.
+    choices: HashMap<String, MoveId>,
+    /// The value of each state for `p`, once worked out.
+    values: HashMap<String, f64>,
+}
+
+impl<'a, G: Game> BestResponse<'a, G> {
+    fn value(&mut self, state: &G) -> f64 {
+        let key = state.unparse();
+        if let Some(v) = self.values.get(&key) {
+            return *v;
+        }
+        let moves = state.moves();
+        let v = if moves.is_empty() {
+            state.value_for(self.p) as f64
+        } else if state.current_player() == self.p {
+            let choice = self.choice(&state.observation(self.p));
+            let m = moves.iter().find(|m| m.id == choice).unwrap();
+            self.value(&m.next_state)
+        } else {
+            let probabilities = move_probabilities(state, &moves, self.p, self.strategy);
+            moves.iter()
+                .zip(probabilities)
+                .filter(|(_, q)| *q > 0.0)
+                .map(|(m, q)| q * self.value(&m.next_state))
+                .sum()
+        };
+        self.values.insert(key, v);
+        v
+    }
+
+    /// The best move for `p` in `info_set`: the one with the most value,
+    /// summed over the states `p` might be in.
+    fn choice(&mut self, info_set: &str) -> MoveId {
+        if let Some(choice) = self.choices.get(info_set) {
+            return *choice;
+        }
+        let states = self.states.get(info_set).cloned().unwrap_or_default();
+        let mut totals: Vec<(MoveId, f64)> = Vec::new();
+        for (state, reach) in &states {
+            for m in state.moves() {
+                let v = reach * self.value(&m.next_state);
+                match totals.iter_mut().find(|(id, _)| *id == m.id) {
+                    Some((_, total)) => *total += v,
+                    None => totals.push((m.id, v)),
+                }
+            }
+        }
+        let choice = totals.iter()
+            .fold(None, |best: Option<(MoveId, f64)>, (id, v)| match best {
+                Some((_, b)) if b >= *v => best,
+                _ => Some((*id, *v)),
+            })
+            .map(|(id, _)| id)
+            .unwrap();
+        self.choices.insert(info_set.to_string(), choice);
+        choice
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_core::VICTORY;
+    use crate::poker::KuhnPokerGame;
+
+    #[test]
+    fn kuhn_poker_converges_to_its_known_value() {
+        let root = KuhnPokerGame::undealt();
+        let mut cfr = Cfr::new(root.clone());
+        cfr.train(1000);
+        assert!(cfr.exploitability() / (VICTORY as f64) < 0.001);
+
+        // At equilibrium, the first player loses 1/18 of a chip per hand, and
+        // neither player can do better than that against the other.
+        let strategy = cfr.strategy();
+        let a = best_response_value(&root, 'A', &strategy) / VICTORY as f64;
+        let b = best_response_value(&root, 'B', &strategy) / VICTORY as f64;
+        assert!((a + 1.0 / 18.0).abs() < 0.002, "A's value {}", a);
+        assert!((b - 1.0 / 18.0).abs() < 0.002, "B's value {}", b);
+    }
+}
@@ -0,0 +1,511 @@
+use crate::game_core::{Game, Move, MoveId, Outcome, ParseError, Player, VICTORY};
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+
+/// Moving a piece from hole `f` to hole `t` (counting from 1) is move
+/// `MOVE * f + t`.
+const MOVE: MoveId = 1000;
+
+/// A player who cannot move at all passes with this move.
+pub const PASS: MoveId = 1;
+
+const PIECES: usize = 10;
+
+/// The six directions between neighboring holes, in axial coordinates
+/// (`q` increasing to the right, `r` increasing downwards). Hopping goes
+/// twice as far along one of them.
+const DIRECTIONS: [(i32, i32); 6] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, -1), (-1, 1)];
+
+/// The holes of the star, which is made of two overlapping triangles.
+struct Star {
+    /// The axial coordinates of each hole, row by row from the top.
+    coords: Vec<(i32, i32)>,
+    /// Each hole's neighbor in each of the `DIRECTIONS`.
+    neighbors: Vec<[Option<usize>; 6]>,
+    /// The point of the star each hole is in, numbered clockwise from the
+    /// top, or `None` for holes in the central hexagon.
+    corners: Vec<Option<usize>>,
+    /// The holes are named by row, from 'a' at the top to 'q' at the
+    /// bottom, and position in the row from the left, like "e7".
+    names: Vec<String>,
+}
+
+fn corner_of((q, r): (i32, i32)) -> Option<usize> {
+    let s = -q - r;
+    if r < -4 { Some(0) }
+    else if q > 4 { Some(1) }
+    else if s < -4 { Some(2) }
+    else if r > 4 { Some(3) }
+    else if q < -4 { Some(4) }
+    else if s > 4 { Some(5) }
+    else { None }
+}
+
+/// The hole at the far tip of each point of the star.
+const TIPS: [(i32, i32); 6] = [(4, -8), (8, -4), (4, 4), (-4, 8), (-8, 4), (-4, -4)];
+
+fn star() -> &'static Star {
+    static STAR: OnceLock<Star> = OnceLock::new();
+    STAR.get_or_init(|| {
+        // A hole is in the star if it is in either triangle: the one with no
+        // coordinate above 4, or the one with none below -4.
+        let mut coords = Vec::new();
+        for r in -8..=8 {
+            for q in -8..=8 {
+                let s = -q - r;
+                if (q <= 4 && r <= 4 && s <= 4) || (q >= -4 && r >= -4 && s >= -4) {
+                    coords.push((q, r));
+                }
+            }
+        }
+        let find = |c: (i32, i32)| coords.iter().position(|x| *x == c);
+        let neighbors = coords.iter()
+            .map(|(q, r)| {
+                let mut n = [None; 6];
+                for (k, (dq, dr)) in DIRECTIONS.iter().enumerate() {
+                    n[k] = find((q + dq, r + dr));
+                }
+                n
+            })
+            .collect();
+        let corners = coords.iter().map(|c| corner_of(*c)).collect();
+        let mut names = Vec::new();
+        for (i, (_, r)) in coords.iter().enumerate() {
+            let position = coords[..i].iter().filter(|(_, r2)| r2 == r).count() + 1;
+            names.push(format!("{}{}", (b'a' + (r + 8) as u8) as char, position));
+        }
+        Star { coords, neighbors, corners, names }
+    })
+}
+
+fn distance((q1, r1): (i32, i32), (q2, r2): (i32, i32)) -> i32 {
+    let (dq, dr) = (q1 - q2, r1 - r2);
+    dq.abs().max(dr.abs()).max((dq + dr).abs())
+}
+
+/// Chinese Checkers on the star board, for 2, 3 or 6 players named 'A', 'B'
+/// and so on, seated clockwise from the top point of the star. Each player
+/// starts with ten pieces filling their point and races them across to the
+/// opposite point.
+///
+/// On their turn, a player either steps a piece to a neighboring empty hole,
+/// or hops it over a neighboring piece (anyone's) to the empty hole just
+/// beyond, and then as many more hops as they like. Each move is the whole
+/// turn, from where the piece starts to where it ends up; move ids are
+/// `MOVE` times the starting hole plus the final hole, counting holes from 1
+/// row by row from the top.
+///
+/// A player has finished once their target point is full and at least one of
+/// its pieces is theirs (so that nobody can stop them finishing by leaving a
+/// piece there). Finished players sit out, and the game is over when only
+/// one player has yet to finish. `end_game` then lists the others in the
+/// order they finished, first place first.
+#[derive(Clone, Debug)]
+pub struct ChineseCheckersGame {
+    pub players: usize,
+    /// Each hole is a player's letter or '-'.
+    pub board: Vec<char>,
+    pub player: Player,
+    /// The players who have finished, in order.
+    pub finished: Vec<Player>,
+}
+
+impl Default for ChineseCheckersGame {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl ChineseCheckersGame {
+    /// A new game for `players` players, which must be 2, 3 or 6.
+    pub fn new(players: usize) -> Self {
+        let mut g = ChineseCheckersGame {
+            players,
+            board: vec!['-'; star().coords.len()],
+            player: 'A',
+            finished: Vec::new(),
+        };
+        for seat in 0..players {
+            let p = (b'A' + seat as u8) as char;
+            let home = g.home(p);
+            for i in 0..g.board.len() {
+                if star().corners[i] == Some(home) {
+                    g.board[i] = p;
+                }
+            }
+        }
+        g
+    }
+
+    fn seat(&self, p: Player) -> usize {
+        (p as u8 - b'A') as usize
+    }
+
+    /// The point of the star where `p` starts.
+    fn home(&self, p: Player) -> usize {
+        self.seat(p) * 6 / self.players
+    }
+
+    /// The point of the star that `p` must fill.
+    fn target(&self, p: Player) -> usize {
+        (self.home(p) + 3) % 6
+    }
+
+    fn has_finished(&self, p: Player) -> bool {
+        let target: Vec<usize> = (0..self.board.len())
+            .filter(|i| star().corners[*i] == Some(self.target(p)))
+            .collect();
+        target.iter().all(|i| self.board[*i] != '-') && target.iter().any(|i| self.board[*i] == p)
+    }
+
+    /// How far `p`'s pieces have to go: the sum of their distances from the
+    /// tip of `p`'s target point, plus, for each piece not yet in it, the
+    /// distance to the nearest hole there that `p` has still to fill. (Hex
+    /// distance from the tip alone would be the same all along a row, leaving
+    /// nothing to draw stragglers in.)
+    fn distance_to_go(&self, p: Player) -> i32 {
+        let star = star();
+        let target = self.target(p);
+        let tip = TIPS[target];
+        let unfilled: Vec<(i32, i32)> = (0..self.board.len())
+            .filter(|i| star.corners[*i] == Some(target) && self.board[*i] != p)
+            .map(|i| star.coords[i])
+            .collect();
+        (0..self.board.len())
+            .filter(|i| self.board[*i] == p)
+            .map(|i| {
+                let to_tip = distance(star.coords[i], tip);
+                if star.corners[i] == Some(target) {
+                    to_tip
+                } else {
+                    to_tip + unfilled.iter().map(|c| distance(star.coords[i], *c)).min().unwrap_or(0)
+                }
+            })
+            .sum()
+    }
+
+    fn is_over(&self) -> bool {
+        self.finished.len() + 1 >= self.players
+    }
+
+    /// The player after `p` in seating order who has yet to finish.
+    fn next_player(&self, p: Player) -> Player {
+        let mut seat = self.seat(p);
+        loop {
+            seat = (seat + 1) % self.players;
+            let q = (b'A' + seat as u8) as char;
+            if !self.finished.contains(&q) {
+                return q;
+            }
+        }
+    }
+
+    /// Where the piece on `from` can end its turn, each with the hole it came
+    /// from on its last step or hop.
+    fn destinations(&self, from: usize) -> Vec<(usize, usize)> {
+        let star = star();
+        let mut found = Vec::new();
+        let mut seen = vec![false; self.board.len()];
+        seen[from] = true;
+        let mut queue = VecDeque::from(vec![from]);
+        while let Some(at) = queue.pop_front() {
+            for k in 0..DIRECTIONS.len() {
+                let over = match star.neighbors[at][k] {
+                    Some(over) if over != from && self.board[over] != '-' => over,
+                    _ => continue,
+                };
+                match star.neighbors[over][k] {
+                    Some(to) if !seen[to] && self.board[to] == '-' => {
+                        seen[to] = true;
+                        found.push((to, at));
+                        queue.push_back(to);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        // A neighboring hole that can also be reached by hopping is only
+        // listed once.
+        for to in star.neighbors[from].iter().flatten() {
+            if self.board[*to] == '-' && !seen[*to] {
+                found.push((*to, from));
+            }
+        }
+        found
+    }
+}
+
+impl Game for ChineseCheckersGame {
+    const NAME: &'static str = "ChineseCheckers";
+
+    fn current_player(&self) -> Player {
+        self.player
+    }
+
+    /// The serialized form is the number of players, the player to move, the
+    /// players who have finished (in order), then a '-' and the 121 holes,
+    /// row by row from the top, each a player's letter or '0' when empty.
+    fn unparse(&self) -> String {
+        let mut s = self.players.to_string();
+        s.push(self.player);
+        s.extend(self.finished.iter());
+        s.push('-');
+        s.extend(self.board.iter().map(|c| if *c == '-' { '0' } else { *c }));
+        s
+    }
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let (head, holes) = match input.find('-') {
+            Some(k) => (&input[..k], &input[k + 1..]),
+            None => {
+                let at = input.chars().count();
+                return Err(ParseError::wrong_length("<players><player><finished>-<holes>").at(at));
+            }
+        };
+        // Where the holes start in the input, past the '-'.
+        let holes_at = head.chars().count() + 1;
+        let mut head = head.chars();
+        let players = match head.next().and_then(|c| c.to_digit(10)) {
+            Some(n @ 2) | Some(n @ 3) | Some(n @ 6) => n as usize,
+            _ => return Err(ParseError::unexpected(0, "the number of players: 2, 3 or 6")),
+        };
+        let is_player = |c: char| c >= 'A' && c < (b'A' + players as u8) as char;
+        let player = match head.next() {
+            Some(p) if is_player(p) => p,
+            _ => return Err(ParseError::unexpected(1, "the player to move")),
+        };
+        let finished: Vec<Player> = head.collect();
+        if let Some(i) = finished.iter().position(|p| !is_player(*p) || *p == player) {
+            return Err(ParseError::unexpected(2 + i, "a finished player, other than the one to move"));
+        }
+        let len = star().coords.len();
+        if holes.chars().count() != len {
+            let at = holes_at + holes.chars().count().min(len);
+            return Err(ParseError::wrong_length("121 holes").at(at));
+        }
+        let mut board = Vec::new();
+        for (i, c) in holes.chars().enumerate() {
+            board.push(match c {
+                '0' => '-',
+                c if is_player(c) => c,
+                _ => return Err(ParseError::unexpected(holes_at + i, "'0', or one of the players")),
+            });
+        }
+        for seat in 0..players {
+            let p = (b'A' + seat as u8) as char;
+            if board.iter().filter(|c| **c == p).count() != PIECES {
+                return Err(ParseError::invalid(format!("ten pieces for {}", p)));
+            }
+        }
+        Ok(ChineseCheckersGame { players, board, player, finished })
+    }
+
+    fn moves(&self) -> Vec<Move<Self>> {
+        if self.is_over() {
+            return Vec::new();
+        }
+        let p = self.player;
+        let mut v = Vec::new();
+        for from in (0..self.board.len()).filter(|i| self.board[*i] == p) {
+            for (to, _) in self.destinations(from) {
+                let mut next_state = self.clone();
+                next_state.board[from] = '-';
+                next_state.board[to] = p;
+                if next_state.has_finished(p) {
+                    next_state.finished.push(p);
+                }
+                next_state.player = next_state.next_player(p);
+                let end_game = if next_state.is_over() {
+                    Some(next_state.finished.iter().cloned().collect())
+                } else {
+                    None
+                };
+                let id = MOVE * (from as MoveId + 1) + to as MoveId + 1;
+                v.push(Move { id, end_game, next_state });
+            }
+        }
+        if v.is_empty() {
+            let mut next_state = self.clone();
+            next_state.player = self.next_player(p);
+            v.push(Move { id: PASS, end_game: None, next_state });
+        }
+        v
+    }
+
+    /// Moves are named by the holes the piece passes through, like "d3-e3"
+    /// for a step or "d3-f3-h5" for two hops.
+    fn move_name(&self, id: MoveId) -> String {
+        if id == PASS {
+            return "pass".to_string();
+        }
+        let (from, to) = ((id / MOVE) as usize - 1, (id % MOVE) as usize - 1);
+        let came_from = self.destinations(from);
+        let mut path = vec![to];
+        while let Some((_, before)) = came_from.iter().find(|(hole, _)| hole == path.last().unwrap()) {
+            path.push(*before);
+            if *before == from {
+                break;
+            }
+        }
+        path.iter().rev().map(|i| star().names[*i].as_str()).collect::<Vec<_>>().join("-")
+    }
+
+    fn move_long_name(&self, id: MoveId) -> String {
+        if id == PASS {
+            return "pass".to_string();
+        }
+        let name = self.move_name(id);
+        let path: Vec<&str> = name.split('-').collect();
+        let (from, to) = (path[0], path[path.len() - 1]);
+        match &path[1..path.len() - 1] {
+            [] => format!("move from {} to {}", from, to),
+            via => format!("move from {} to {} by way of {}", from, to, via.join(", ")),
+        }
+    }
+
+    /// Each row is labeled with its letter; holes are numbered from the left
+    /// within their row.
+    fn render_to_text(&self) -> String {
+        let star = star();
+        let mut s = String::new();
+        for r in -8..=8 {
+            let mut line = [' '; 25];
+            for (i, (q, _)) in star.coords.iter().enumerate().filter(|(_, (_, r2))| *r2 == r) {
+                let x = (2 * q + r + 12) as usize;
+                line[x] = if self.board[i] == '-' { '.' } else { self.board[i] };
+            }
+            s.push((b'a' + (r + 8) as u8) as char);
+            s.push_str("  ");
+            s.push_str(line.iter().collect::<String>().trim_end());
+            s.push('\n');
+        }
+        if !self.finished.is_empty() {
+            let places: Vec<String> = self.finished.iter().map(|p| p.to_string()).collect();
+            s.push_str(&format!("finished: {}\n", places.join(", ")));
+        }
+        if !self.is_over() {
+            s.push_str(&format!("{} to move\n", self.player));
+        }
+        s
+    }
+
+    /// Finishing is a victory, worth less for each place further back, and
+    /// being left last is a loss. Until then, what counts is how much closer
+    /// `p`'s pieces are to home than those of the closest rival.
+    fn value_for(&self, p: Player) -> i64 {
+        if let Some(place) = self.finished.iter().position(|q| *q == p) {
+            return VICTORY >> place;
+        }
+        if self.is_over() {
+            return -VICTORY;
+        }
+        let rival = (0..self.players)
+            .map(|seat| (b'A' + seat as u8) as char)
+            .filter(|q| *q != p && !self.finished.contains(q))
+            .map(|q| self.distance_to_go(q))
+            .min()
+            .unwrap_or(0);
+        (rival - self.distance_to_go(p)) as i64
+    }
+
+    /// Once only one player has yet to finish, the others have won, in the
+    /// order they finished.
+    fn outcome(&self) -> Outcome {
+        if self.is_over() { Outcome::Win(self.finished.iter().cloned().collect()) } else { Outcome::Ongoing }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_core::play;
+
+    fn hole(name: &str) -> usize {
+        star().names.iter().position(|n| n == name).unwrap()
+    }
+
+    /// The holes of point `corner` of the star.
+    fn point(corner: usize) -> Vec<usize> {
+        (0..star().coords.len()).filter(|i| star().corners[*i] == Some(corner)).collect()
+    }
+
+    /// An empty board for `players` players, with `player` to move.
+    fn empty(players: usize, player: Player) -> ChineseCheckersGame {
+        ChineseCheckersGame { players, board: vec!['-'; star().coords.len()], player, finished: Vec::new() }
+    }
+
+    fn names(g: &ChineseCheckersGame) -> Vec<String> {
+        g.moves().iter().map(|m| g.move_name(m.id)).collect()
+    }
+
+    #[test]
+    fn pieces_step_or_hop_out_of_the_starting_point() {
+        let start = ChineseCheckersGame::default();
+        let opening = names(&start);
+        assert!(opening.contains(&"d1-e5".to_string()));
+        assert!(opening.contains(&"c1-e5".to_string()));
+        // The point is full, so every move ends in row e, just outside it.
+        assert!(opening.iter().all(|name| name.contains('e')));
+        let m = play(&start, "c1-e5");
+        assert_eq!(m.id, MOVE * (hole("c1") as MoveId + 1) + hole("e5") as MoveId + 1);
+        assert_eq!(m.next_state.player, 'B');
+    }
+
+    #[test]
+    fn hops_chain_over_anyone_and_may_stop_along_the_way() {
+        let mut g = empty(2, 'A');
+        g.board[hole("e5")] = 'A';
+        g.board[hole("f5")] = 'B';
+        g.board[hole("h5")] = 'A';
+        let moves = names(&g);
+        assert!(moves.contains(&"e5-g5".to_string()));
+        assert!(moves.contains(&"e5-g5-i5".to_string()));
+        assert!(!moves.iter().any(|name| name.starts_with("e5-f5")));
+        assert_eq!(g.move_long_name(play(&g, "e5-g5-i5").id), "move from e5 to i5 by way of g5");
+    }
+
+    #[test]
+    fn players_finish_in_order_and_the_last_one_left_loses() {
+        // A is one step from filling the bottom point. B is one step from
+        // filling the upper left one, which C's pieces have all but filled:
+        // that still counts for B, since B's piece ends up there.
+        let mut g = empty(3, 'A');
+        for i in point(3) {
+            g.board[i] = 'A';
+        }
+        g.board[hole("n1")] = '-';
+        g.board[hole("m5")] = 'A';
+        for i in point(5) {
+            g.board[i] = 'C';
+        }
+        g.board[hole("e4")] = '-';
+        g.board[hole("e5")] = 'B';
+
+        let m = play(&g, "m5-n1");
+        assert_eq!(m.end_game, None);
+        assert_eq!(m.next_state.finished, ['A']);
+        assert_eq!(m.next_state.player, 'B');
+        assert_eq!(m.next_state.outcome(), Outcome::Ongoing);
+
+        let m = play(&m.next_state, "e5-e4");
+        assert_eq!(m.end_game, Some(['A', 'B'].iter().cloned().collect()));
+        let end = m.next_state;
+        assert_eq!(end.outcome(), Outcome::Win(['A', 'B'].iter().cloned().collect()));
+        assert!(end.moves().is_empty());
+        assert_eq!(end.value_for('A'), VICTORY);
+        assert_eq!(end.value_for('B'), VICTORY >> 1);
+        assert_eq!(end.value_for('C'), -VICTORY);
+    }
+
+    #[test]
+    fn a_player_with_no_moves_passes() {
+        // A's only piece sits in the tip, walled in by B's.
+        let mut g = empty(2, 'A');
+        for i in point(0) {
+            g.board[i] = 'B';
+        }
+        g.board[hole("a1")] = 'A';
+        assert_eq!(names(&g), ["pass"]);
+        assert_eq!(g.moves()[0].next_state.player, 'B');
+    }
+}
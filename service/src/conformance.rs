@@ -0,0 +1,272 @@
+//! Checks that any `Game` keeps the promises the rest of the crate relies on,
+//! at every state reached by random playouts:
+//!
+//! - `parse` reads back what `unparse` wrote, to the same state (and `options`
+//!   can create the game again, with the same options);
+//! - move ids are unique, and `move_ids`, `lazy_moves`, `random_move` and
+//!   making moves in place (whether the game does that itself or not) all
+//!   agree with `moves`;
+//! - `current_player` survives the round trip, and is only `CHANCE` in games
+//!   with chance moves;
+//! - a move's `end_game` says the same as its next state's `outcome`, which
+//!   `value_for` agrees with: victors value the end above everyone else, and in
+//!   a draw, everyone values it alike;
+//! - a game that is over has no moves, and one that is not has some;
+//! - only a win scores any points;
+//! - random playouts end.
+//!
+//! `conformance_tests!` makes a test of these checks for each game it is given;
+//! every game in the registry must be given to it.
+
+use std::collections::{BTreeSet, HashSet};
+
+use crate::game_core::{sample_chance, Game, GameOptions, Move, MoveId, Outcome, Player, Rng, CHANCE};
+
+/// How thoroughly to check a game.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// How many random playouts to check, from each starting state.
+    pub playouts: usize,
+    /// How long a playout may go on before it counts as never ending.
+    pub max_moves: usize,
+    /// Whether random play must end the game. Puzzles, which only end when
+    /// solved, can wander about forever, and so can races like Chinese
+    /// Checkers, where random moves hardly ever bring every piece home.
+    pub playouts_end: bool,
+    /// Options for more starting states (see `GameOptions`), besides the
+    /// default one.
+    pub options: &'static [&'static str],
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { playouts: 10, max_moves: 10_000, playouts_end: true, options: &[] }
+    }
+}
+
+/// Checks `G` as `config` says, panicking at the first broken promise.
+pub fn check<G: Game>(config: &Config) {
+    // A fixed seed, so that failures can be reproduced: even games that start
+    // at random start from the same place every time.
+    let mut rng = Rng::new(0xc0ffee);
+    let mut starts = vec![G::random_start(&mut rng)];
+    for options in config.options {
+        let parsed = GameOptions::parse(options).and_then(G::with_options);
+        starts.push(parsed.unwrap_or_else(|e| panic!("{}: options {:?} failed: {}", G::NAME, options, e)));
+    }
+    for start in &starts {
+        for _ in 0..config.playouts {
+            let length = check_playout(start, &mut rng, config.max_moves);
+            if config.playouts_end {
+                assert!(length.is_some(),
+                        "{}: a random playout from {:?} went on for {} moves",
+                        G::NAME, start.unparse(), config.max_moves);
+            }
+        }
+    }
+}
+
+/// Plays `start` out at random, checking every state on the way, and returns
+/// how many moves it took to end (or `None` if it did not end).
+fn check_playout<G: Game>(start: &G, rng: &mut Rng, max_moves: usize) -> Option<usize> {
+    let mut state = start.clone();
+    // The players who have moved so far, between whom `value_for` must agree
+    // with the outcome.
+    let mut players = BTreeSet::new();
+    for length in 1..=max_moves {
+        if state.current_player() != CHANCE {
+            players.insert(state.current_player());
+        }
+        let moves = check_state(&state, &players);
+        let m = if state.current_player() == CHANCE {
+            sample_chance(&state, &moves, rng).clone()
+        } else {
+            let m = state.random_move(rng).expect("a game that is not over has a random move");
+            let same = moves.iter().find(|n| n.id == m.id);
+            assert!(same.is_some_and(|n| n.next_state.unparse() == m.next_state.unparse()),
+                    "{}: the random move {} from {:?} is not one of its moves",
+                    G::NAME, m.id, state.unparse());
+            m
+        };
+        if m.end_game.is_some() {
+            return Some(length);
+        }
+        state = m.next_state;
+    }
+    None
+}
+
+/// Checks `state`, which must not be over unless no move has ended it yet,
+/// returning its moves.
+fn check_state<G: Game>(state: &G, players: &BTreeSet<Player>) -> Vec<Move<G>> {
+    let unparsed = state.unparse();
+    let fail = |what: String| -> ! { panic!("{}: at {:?}, {}", G::NAME, unparsed, what) };
+
+    // Round trips.
+    match G::parse(&unparsed) {
+        Ok(parsed) => {
+            if parsed.unparse() != unparsed {
+                fail(format!("parsing it gives {:?}", parsed.unparse()));
+            }
+            if parsed.current_player() != state.current_player() {
+                fail(format!("parsing it makes {} the player to move, not {}",
+                             parsed.current_player(), state.current_player()));
+            }
+        }
+        Err(e) => fail(format!("it does not parse: {}", e)),
+    }
+    let options = state.options();
+    match GameOptions::parse(&options).and_then(G::with_options) {
+        Ok(created) if created.options() != options => {
+            fail(format!("its options {:?} create a game with options {:?}", options, created.options()));
+        }
+        Ok(_) => {}
+        Err(e) => fail(format!("its options {:?} do not create a game: {}", options, e)),
+    }
+    if state.current_player() == CHANCE && !G::HAS_CHANCE_MOVES {
+        fail("chance moves, in a game without them".to_string());
+    }
+
+    // The moves, every way of finding them.
+    let moves = state.moves();
+    let ids: Vec<MoveId> = moves.iter().map(|m| m.id).collect();
+    if ids.iter().collect::<HashSet<_>>().len() != ids.len() {
+        fail(format!("move ids {:?} repeat", ids));
+    }
+    match (state.outcome().is_over(), moves.is_empty()) {
+        (true, false) => fail(format!("the game is over ({}), but there are moves", state.outcome())),
+        (false, true) => fail("the game is not over, but there are no moves".to_string()),
+        _ => {}
+    }
+    match (state.outcome(), state.points()) {
+        (Outcome::Win(_), 0) => fail("the game is won, for no points".to_string()),
+        (Outcome::Ongoing, points) | (Outcome::Draw, points) if points > 0 => {
+            fail(format!("nobody has won, but the outcome is worth {} points", points));
+        }
+        _ => {}
+    }
+    let sorted = |ids: Vec<MoveId>| ids.into_iter().collect::<BTreeSet<_>>();
+    if sorted(state.move_ids()) != sorted(ids.clone()) {
+        fail(format!("move_ids {:?} differ from the moves' {:?}", state.move_ids(), ids));
+    }
+    if G::LAZY_MOVES {
+        let lazy = state.lazy_moves().map(|m| m.id).collect();
+        if sorted(lazy) != sorted(ids.clone()) {
+            fail("lazy_moves differ from moves".to_string());
+        }
+    }
+
+    for m in &moves {
+        let next = &m.next_state;
+        let outcome = next.outcome();
+        if Outcome::from_end_game(m.end_game.clone()) != outcome {
+            fail(format!("move {} ends the game with {:?}, but leads to a state where {}",
+                         m.id, m.end_game, outcome));
+        }
+        // The default `make_move` builds every move to make one, so it is
+        // only tried on the first move, which is enough to check it.
+        if G::IN_PLACE_MOVES || m.id == moves[0].id {
+            let mut in_place = state.clone();
+            let made = in_place.make_move(m.id);
+            if in_place.unparse() != next.unparse() || made.end_game != m.end_game {
+                fail(format!("making move {} in place leads to {:?}, not {:?}",
+                             m.id, in_place.unparse(), next.unparse()));
+            }
+            in_place.unmake_move(m.id, made.undo);
+            if in_place.unparse() != unparsed {
+                fail(format!("unmaking move {} leaves {:?}", m.id, in_place.unparse()));
+            }
+        }
+
+        if outcome.is_over() && !next.moves().is_empty() {
+            fail(format!("move {} ends the game, but there are moves after it", m.id));
+        }
+
+        // However the game ended, `value_for` must see it the same way.
+        match &outcome {
+            Outcome::Ongoing => {}
+            Outcome::Win(victors) => {
+                for v in victors {
+                    for p in players.iter().filter(|p| !victors.contains(p)) {
+                        if next.value_for(*v) <= next.value_for(*p) {
+                            fail(format!("move {} wins for {}, but {} values it {} and {} values it {}",
+                                         m.id, v, v, next.value_for(*v), p, next.value_for(*p)));
+                        }
+                    }
+                }
+            }
+            Outcome::Draw => {
+                let values: BTreeSet<i64> = players.iter().map(|p| next.value_for(*p)).collect();
+                if values.len() > 1 {
+                    fail(format!("move {} draws, but the players value it differently: {:?}", m.id, values));
+                }
+            }
+        }
+    }
+    moves
+}
+
+/// Makes a test running `check` for each game given, as `name: Game`, or
+/// `name: Game = config` to check it other than by default. It also makes
+/// a test that every game in the registry was given.
+macro_rules! conformance_tests {
+    (@config) => { Config::default() };
+    (@config $config:expr) => { $config };
+    ($($test:ident: $game:ty $(= $config:expr)?),* $(,)?) => {
+        $(
+            #[test]
+            fn $test() {
+                check::<$game>(&conformance_tests!(@config $($config)?));
+            }
+        )*
+
+        #[test]
+        fn every_game_is_checked() {
+            let checked: BTreeSet<&str> = [$(<$game as Game>::NAME),*].iter().copied().collect();
+            for name in crate::registry::names() {
+                assert!(checked.contains(name), "{} needs a conformance test", name);
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backgammon::BackgammonGame;
+    use crate::battleship::BattleshipGame;
+    use crate::chinese_checkers::ChineseCheckersGame;
+    use crate::dots_and_boxes::DotsAndBoxesGame;
+    use crate::go::GoGame;
+    use crate::hex::HexGame;
+    use crate::kalah::KalahGame;
+    use crate::nim::NimGame;
+    use crate::nine_mens_morris::NineMensMorrisGame;
+    use crate::othello::OthelloGame;
+    use crate::pentago::PentagoGame;
+    use crate::poker::{KuhnPokerGame, LeducPokerGame};
+    use crate::quarto::QuartoGame;
+    use crate::qubic::QubicGame;
+    use crate::sliding_puzzle::SlidingPuzzleGame;
+    use crate::tictactoe::TicTacToeGame;
+
+    conformance_tests! {
+        tictactoe: TicTacToeGame = Config { options: &["size=4,win=3,first=O"], ..Config::default() },
+        othello: OthelloGame,
+        nim: NimGame = Config { options: &["heaps=1-2-7-9,misere"], ..Config::default() },
+        kalah: KalahGame = Config { options: &["seeds=2,first=N"], ..Config::default() },
+        dots_and_boxes: DotsAndBoxesGame = Config { options: &["size=4x2"], ..Config::default() },
+        hex: HexGame = Config { options: &["size=5,swap=no"], ..Config::default() },
+        go: GoGame = Config { playouts: 3, options: &["superko"], ..Config::default() },
+        battleship: BattleshipGame,
+        backgammon: BackgammonGame,
+        quarto: QuartoGame,
+        nine_mens_morris: NineMensMorrisGame,
+        pentago: PentagoGame,
+        qubic: QubicGame,
+        chinese_checkers: ChineseCheckersGame = Config { playouts: 2, max_moves: 500, playouts_end: false, ..Config::default() },
+        sliding_puzzle: SlidingPuzzleGame = Config { playouts: 2, max_moves: 200, playouts_end: false, ..Config::default() },
+        kuhn_poker: KuhnPokerGame,
+        leduc_poker: LeducPokerGame,
+    }
+}
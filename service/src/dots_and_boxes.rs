@@ -0,0 +1,293 @@
+use crate::game_core::{Game, GameOptions, Move, MoveId, Outcome, ParseError, Player, VICTORY};
+use smallvec::SmallVec;
+
+/// Dots and Boxes on a grid of `width` by `height` boxes. Players take turns
+/// drawing edges between adjacent dots; whoever draws the fourth edge of a box
+/// owns it and must draw another edge. Grids have at most 9 boxes on a side.
+///
+/// Edges are numbered from 0: first the horizontal edges, row by row from the
+/// top, then the vertical edges, row by row. Move ids are edge numbers plus 1.
+#[derive(Clone, Debug)]
+pub struct DotsAndBoxesGame {
+    pub width: usize,
+    pub height: usize,
+    pub edges: Vec<bool>,
+    /// The owner of each box, row by row, or '-' if it is still open.
+    pub owners: Vec<char>,
+    pub player: Player,
+}
+
+impl Default for DotsAndBoxesGame {
+    fn default() -> Self {
+        Self::new(3, 3)
+    }
+}
+
+fn opponent(p: Player) -> Player {
+    if p == 'A' { 'B' } else { 'A' }
+}
+
+impl DotsAndBoxesGame {
+    pub fn new(width: usize, height: usize) -> Self {
+        let num_edges = (height + 1) * width + height * (width + 1);
+        DotsAndBoxesGame {
+            width,
+            height,
+            edges: vec![false; num_edges],
+            owners: vec!['-'; width * height],
+            player: 'A',
+        }
+    }
+
+    fn num_horizontal(&self) -> usize {
+        (self.height + 1) * self.width
+    }
+
+    /// The edges of box (`r`, `c`): top, bottom, left and right.
+    fn box_edges(&self, r: usize, c: usize) -> [usize; 4] {
+        let top = r * self.width + c;
+        let left = self.num_horizontal() + r * (self.width + 1) + c;
+        [top, top + self.width, left, left + 1]
+    }
+
+    fn is_complete(&self, r: usize, c: usize) -> bool {
+        self.box_edges(r, c).iter().all(|e| self.edges[*e])
+    }
+
+    fn score(&self, p: Player) -> usize {
+        self.owners.iter().filter(|o| **o == p).count()
+    }
+}
+
+impl Game for DotsAndBoxesGame {
+    const NAME: &'static str = "DotsAndBoxes";
+
+    fn current_player(&self) -> Player {
+        self.player
+    }
+
+    /// The serialized form is `<width>x<height><player><edges><owners>`, where
+    /// `<edges>` is a bitset of the drawn edges in hexadecimal (four edges per
+    /// digit, lowest edge in the lowest bit) and `<owners>` has one character
+    /// per box: 'A', 'B' or '-'. For example, the default game is
+    /// "3x3A000000---------".
+    fn unparse(&self) -> String {
+        let hex: String = self.edges.chunks(4)
+            .map(|bits| {
+                let n = bits.iter().enumerate().fold(0, |acc, (i, b)| acc | ((*b as u32) << i));
+                std::char::from_digit(n, 16).unwrap()
+            })
+            .collect();
+        let owners: String = self.owners.iter().collect();
+        format!("{}x{}{}{}{}", self.width, self.height, self.player, hex, owners)
+    }
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let chars: Vec<char> = input.chars().collect();
+        if chars.len() < 4 { return Err(ParseError::wrong_length("<width>x<height><player>, then the edges and boxes").at(chars.len())); }
+        let mut g = match (chars[0].to_digit(10), chars[1], chars[2].to_digit(10)) {
+            (Some(w), 'x', Some(h)) if w >= 1 && h >= 1 => Self::new(w as usize, h as usize),
+            (Some(w), 'x', _) if w >= 1 => return Err(ParseError::unexpected(2, "a grid height from 1 to 9")),
+            (Some(w), _, _) if w >= 1 => return Err(ParseError::unexpected(1, "'x' between the grid width and height")),
+            _ => return Err(ParseError::unexpected(0, "a grid width from 1 to 9")),
+        };
+        g.player = match chars[3] {
+            p @ 'A' | p @ 'B' => p,
+            _ => return Err(ParseError::unexpected(3, "the player to move, A or B")),
+        };
+
+        let num_hex = g.edges.len().div_ceil(4);
+        let len = 4 + num_hex + g.owners.len();
+        if chars.len() != len {
+            return Err(ParseError::wrong_length(format!("{} edge digits and {} box owners for the grid size",
+                                                        num_hex, g.owners.len())).at(chars.len().min(len)));
+        }
+        let (hex, owners) = chars[4..].split_at(num_hex);
+        for (i, c) in hex.iter().enumerate() {
+            let n = match c.to_digit(16) {
+                Some(n) => n,
+                None => return Err(ParseError::unexpected(4 + i, "edges as hexadecimal digits")),
+            };
+            for bit in 0..4 {
+                if n & (1 << bit) != 0 {
+                    match g.edges.get_mut(4 * i + bit) {
+                        Some(e) => *e = true,
+                        None => return Err(ParseError::unexpected(4 + i, "no edges past the last one")),
+                    }
+                }
+            }
+        }
+
+        for (i, o) in owners.iter().cloned().enumerate() {
+            let complete = g.is_complete(i / g.width, i % g.width);
+            match o {
+                'A' | 'B' if complete => g.owners[i] = o,
+                '-' if !complete => {}
+                'A' | 'B' | '-' => return Err(ParseError::invalid("owners for just the completed boxes").at(4 + num_hex + i)),
+                _ => return Err(ParseError::unexpected(4 + num_hex + i, "a box owner, A, B or -")),
+            }
+        }
+        Ok(g)
+    }
+
+    /// `size`, the width and height of the grid in boxes, like "4x3".
+    fn with_options(mut options: GameOptions) -> Result<Self, ParseError> {
+        let (width, height) = match options.text("size") {
+            Some((text, at)) => match text.as_bytes() {
+                [w @ b'1'..=b'9', b'x', h @ b'1'..=b'9'] => ((w - b'0') as usize, (h - b'0') as usize),
+                _ => return Err(ParseError::invalid_option(at, "a grid size like 4x3, each side from 1 to 9")),
+            },
+            None => (3, 3),
+        };
+        options.finish(&["size"])?;
+        Ok(DotsAndBoxesGame::new(width, height))
+    }
+
+    fn options(&self) -> String {
+        format!("size={}x{}", self.width, self.height)
+    }
+
+    fn moves(&self) -> Vec<Move<Self>> {
+        let mut v = Vec::new();
+        for e in 0..self.edges.len() {
+            if self.edges[e] { continue; }
+            let mut next = self.clone();
+            next.edges[e] = true;
+            let mut completed = false;
+            for r in 0..self.height {
+                for c in 0..self.width {
+                    let b = r * self.width + c;
+                    if next.owners[b] == '-' && next.is_complete(r, c) {
+                        next.owners[b] = self.player;
+                        completed = true;
+                    }
+                }
+            }
+            if !completed {
+                next.player = opponent(self.player);
+            }
+            let end_game = if next.edges.iter().all(|e| *e) {
+                Some(victors(&next))
+            } else {
+                None
+            };
+            v.push(Move { id: e as MoveId + 1, end_game, next_state: next });
+        }
+        v
+    }
+
+    fn render_to_text(&self) -> String {
+        let mut s = String::new();
+        for r in 0..=self.height {
+            for c in 0..self.width {
+                s.push('+');
+                s.push_str(if self.edges[r * self.width + c] { "---" } else { "   " });
+            }
+            s.push_str("+\n");
+            if r == self.height { break; }
+            for c in 0..=self.width {
+                let left = self.num_horizontal() + r * (self.width + 1) + c;
+                s.push(if self.edges[left] { '|' } else { ' ' });
+                if c < self.width {
+                    let owner = self.owners[r * self.width + c];
+                    s.push_str(&format!(" {} ", if owner == '-' { ' ' } else { owner }));
+                }
+            }
+            s.push('\n');
+        }
+        s.push_str(&format!("A: {}  B: {}  ({} to move)\n", self.score('A'), self.score('B'), self.player));
+        s
+    }
+
+    /// The difference in boxes owned; once every edge is drawn, a decisive
+    /// victory or loss (or nothing, for a tie).
+    fn value_for(&self, p: Player) -> i64 {
+        let diff = self.score(p) as i64 - self.score(opponent(p)) as i64;
+        if self.edges.iter().all(|e| *e) {
+            diff.signum() * VICTORY
+        } else {
+            diff
+        }
+    }
+
+    fn outcome(&self) -> Outcome {
+        if self.edges.iter().all(|e| *e) {
+            Outcome::from_end_game(Some(victors(self)))
+        } else {
+            Outcome::Ongoing
+        }
+    }
+}
+
+/// The players with the most boxes. A tie lists both players.
+fn victors(g: &DotsAndBoxesGame) -> SmallVec<[Player; 1]> {
+    let (a, b) = (g.score('A'), g.score('B'));
+    let mut v = SmallVec::new();
+    if a >= b { v.push('A'); }
+    if b >= a { v.push('B'); }
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_core::{play, search};
+
+    /// A row of four boxes with every edge drawn but the three between them:
+    /// a chain that whoever moves can take in three moves.
+    const CHAIN: &str = "4x1Aff11----";
+
+    #[test]
+    fn completing_a_box_earns_another_edge() {
+        let g = DotsAndBoxesGame::default();
+        assert_eq!(play(&g, "1").next_state.current_player(), 'B');
+
+        let chain = DotsAndBoxesGame::parse(CHAIN).unwrap();
+        let first = play(&chain, "10");
+        assert_eq!(first.next_state.owners, ['A', '-', '-', '-']);
+        assert_eq!(first.next_state.current_player(), 'A');
+        // The middle edge completes nothing, so it hands over the chain.
+        assert_eq!(play(&chain, "11").next_state.current_player(), 'B');
+    }
+
+    #[test]
+    fn one_edge_can_complete_two_boxes() {
+        let chain = DotsAndBoxesGame::parse(CHAIN).unwrap();
+        let taken = play(&play(&chain, "10").next_state, "11").next_state;
+        let last = play(&taken, "12");
+        assert_eq!(last.next_state.owners, ['A'; 4]);
+        assert_eq!(last.end_game, Some(Some('A').into_iter().collect()));
+    }
+
+    #[tokio::test]
+    async fn search_takes_the_whole_chain() {
+        let chain = DotsAndBoxesGame::parse(CHAIN).unwrap();
+        let moves = chain.moves();
+        let chosen = search(&chain, &moves, 'A').await;
+        assert_ne!(chosen.id, 11);
+    }
+
+    #[test]
+    fn a_tie_lists_both_players() {
+        // Two boxes side by side, one each.
+        let mut g = DotsAndBoxesGame::new(2, 1);
+        g.edges = vec![true; g.edges.len()];
+        g.owners = vec!['A', 'B'];
+        assert_eq!(g.outcome(), Outcome::Win(['A', 'B'].iter().cloned().collect()));
+        assert_eq!(g.value_for('A'), 0);
+    }
+
+    #[test]
+    fn the_grid_size_is_an_option() {
+        let options = |text: &str| GameOptions::parse(text).and_then(DotsAndBoxesGame::with_options);
+        let g = options("size=4x2").unwrap();
+        assert_eq!((g.width, g.height), (4, 2));
+        assert_eq!(g.unparse(), "4x2A000000--------");
+        assert_eq!(g.options(), "size=4x2");
+        assert_eq!(options("").unwrap().options(), "size=3x3");
+        for bad in ["size=0x3", "size=10x3", "size=4", "size=4x", "size", "width=4"] {
+            assert!(options(bad).is_err(), "{}", bad);
+        }
+        assert_eq!(options("size=4y2").unwrap_err().position, Some(5));
+    }
+}
@@ -0,0 +1,166 @@
+//! `Game` cannot be a trait object: it has associated constants, and its
+//! methods take and return `Self`. `DynGame` is an object-safe counterpart,
+//! implemented for every game, so that code choosing the game at run time
+//! (see `registry`) can handle any of them as a `Box<dyn DynGame>`.
+
+use smallvec::SmallVec;
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::game_core::{self, Game, GameOptions, MoveId, Notation, Outcome, ParseError, Player, Rng};
+use crate::solver;
+
+/// A game state of some game, known only at run time. The methods mirror
+/// those of `Game`, which describes them in full.
+pub trait DynGame: Send + Sync {
+    /// `Game::NAME`.
+    fn name(&self) -> &'static str;
+    fn hidden_information(&self) -> bool;
+    fn has_chance_moves(&self) -> bool;
+    fn puzzle(&self) -> bool;
+
+    fn unparse(&self) -> String;
+    fn options(&self) -> String;
+    fn current_player(&self) -> Player;
+    fn moves(&self) -> Vec<DynMove>;
+    fn move_name(&self, id: MoveId) -> String;
+    fn notation(&self, id: MoveId) -> Notation;
+    fn find_move(&self, input: &str) -> Option<MoveId>;
+    fn render_to_text(&self) -> String;
+    fn value_for(&self, p: Player) -> i64;
+    fn outcome(&self) -> Outcome;
+    fn points(&self) -> u32;
+    fn observation(&self, p: Player) -> String;
+    fn render_for(&self, p: Player) -> String;
+    fn moves_for(&self, p: Player) -> Vec<MoveId>;
+
+    /// Picks a move for the player to move, by `game_core::search`. The game
+    /// must not be over.
+    fn select(&self) -> Pin<Box<dyn Future<Output = DynMove> + Send + '_>>;
+
+    /// Picks the outcome of a chance state at random, by
+    /// `game_core::sample_chance`.
+    fn sample_chance(&self, rng: &mut Rng) -> DynMove;
+
+    /// For puzzles, the shortest solution (by `solver::solve`), or `None` if
+    /// there is none; `TooBig` if the solver gave up before it could tell.
+    fn solution(&self) -> Result<Option<Vec<Step>>, solver::TooBig>;
+
+    fn clone_box(&self) -> Box<dyn DynGame>;
+}
+
+impl Clone for Box<dyn DynGame> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// A move, with the state it is made from.
+pub type Step = (Box<dyn DynGame>, DynMove);
+
+/// A `game_core::Move` of a `DynGame`.
+#[derive(Clone)]
+pub struct DynMove {
+    pub id: MoveId,
+    pub next_state: Box<dyn DynGame>,
+    pub end_game: Option<SmallVec<[Player; 1]>>,
+}
+
+impl<G: Game + Send + Sync + 'static> From<game_core::Move<G>> for DynMove {
+    fn from(m: game_core::Move<G>) -> Self {
+        DynMove { id: m.id, next_state: Box::new(m.next_state), end_game: m.end_game }
+    }
+}
+
+impl<G: Game + Send + Sync + 'static> DynGame for G {
+    fn name(&self) -> &'static str { G::NAME }
+    fn hidden_information(&self) -> bool { G::HIDDEN_INFORMATION }
+    fn has_chance_moves(&self) -> bool { G::HAS_CHANCE_MOVES }
+    fn puzzle(&self) -> bool { G::PUZZLE }
+
+    fn unparse(&self) -> String { Game::unparse(self) }
+    fn options(&self) -> String { Game::options(self) }
+    fn current_player(&self) -> Player { Game::current_player(self) }
+    fn moves(&self) -> Vec<DynMove> { Game::moves(self).into_iter().map(DynMove::from).collect() }
+    fn move_name(&self, id: MoveId) -> String { Game::move_name(self, id) }
+    fn notation(&self, id: MoveId) -> Notation { Game::notation(self, id) }
+    fn find_move(&self, input: &str) -> Option<MoveId> { Game::find_move(self, input) }
+    fn render_to_text(&self) -> String { Game::render_to_text(self) }
+    fn value_for(&self, p: Player) -> i64 { Game::value_for(self, p) }
+    fn outcome(&self) -> Outcome { Game::outcome(self) }
+    fn points(&self) -> u32 { Game::points(self) }
+    fn observation(&self, p: Player) -> String { Game::observation(self, p) }
+    fn render_for(&self, p: Player) -> String { Game::render_for(self, p) }
+    fn moves_for(&self, p: Player) -> Vec<MoveId> { Game::moves_for(self, p) }
+
+    fn select(&self) -> Pin<Box<dyn Future<Output = DynMove> + Send + '_>> {
+        Box::pin(async move {
+            let moves = Game::moves(self);
+            let choice = game_core::search(self, &moves, Game::current_player(self)).await;
+            choice.clone().into()
+        })
+    }
+
+    fn sample_chance(&self, rng: &mut Rng) -> DynMove {
+        let moves = Game::moves(self);
+        game_core::sample_chance(self, &moves, rng).clone().into()
+    }
+
+    fn solution(&self) -> Result<Option<Vec<Step>>, solver::TooBig> {
+        let solution = match solver::solve(self)? {
+            Some(solution) => solution,
+            None => return Ok(None),
+        };
+        let steps = solver::moves_along(self, &solution)
+            .into_iter()
+            .map(|(state, m)| (Box::new(state) as Box<dyn DynGame>, m.into()))
+            .collect();
+        Ok(Some(steps))
+    }
+
+    fn clone_box(&self) -> Box<dyn DynGame> {
+        Box::new(self.clone())
+    }
+}
+
+/// A game, as opposed to a state of it: how to start or parse one.
+pub struct GameType {
+    pub name: &'static str,
+    /// `Game::HIDDEN_INFORMATION`, which decides how states are handed out
+    /// before there is one to ask.
+    pub hidden_information: bool,
+    new_game_with: ParseFn,
+    parse: ParseFn,
+}
+
+type ParseFn = fn(&str) -> Result<Box<dyn DynGame>, ParseError>;
+
+impl GameType {
+    pub const fn of<G: Game + Send + Sync + 'static>() -> Self {
+        GameType {
+            name: G::NAME,
+            hidden_information: G::HIDDEN_INFORMATION,
+            new_game_with: new_game_with::<G>,
+            parse: parse::<G>,
+        }
+    }
+
+    /// A fresh game, created with `options` (see `GameOptions`); with none,
+    /// as by `Default::default()`.
+    pub fn new_game_with(&self, options: &str) -> Result<Box<dyn DynGame>, ParseError> {
+        (self.new_game_with)(options)
+    }
+
+    /// `Game::parse`.
+    pub fn parse(&self, input: &str) -> Result<Box<dyn DynGame>, ParseError> {
+        (self.parse)(input)
+    }
+}
+
+fn new_game_with<G: Game + Send + Sync + 'static>(options: &str) -> Result<Box<dyn DynGame>, ParseError> {
+    Ok(Box::new(G::with_options(GameOptions::parse(options)?)?))
+}
+
+fn parse<G: Game + Send + Sync + 'static>(input: &str) -> Result<Box<dyn DynGame>, ParseError> {
+    Ok(Box::new(G::parse(input)?))
+}
@@ -4,6 +4,7 @@
 use smallvec::SmallVec;
 use std::borrow::Cow;
 use std::convert::TryFrom;
+use std::ops::RangeInclusive;
 
 /// To simplify things, we will identify players by single characters,
 /// and build that assumption into the architecture.
@@ -12,6 +13,11 @@ use std::convert::TryFrom;
 /// and 'Y' stand for "red" and "yellow" players.
 pub type Player = char;
 
+/// The "player" to move in chance states, such as when dice are about to be
+/// rolled. Its moves are the possible outcomes; nobody chooses between them,
+/// they just happen at random, weighted by `Game::chance_weight`.
+pub const CHANCE: Player = '?';
+
 /// Each move for a given game-state is identified by a positive number.
 ///
 /// For example, the locations on a tic-tac-toe board can be labelled with
@@ -52,12 +58,293 @@ pub struct Move<B: Game> {
     pub end_game: Option<SmallVec<[Player; 1]>>,
 }
 
+impl<B: Game> Move<B> {
+    /// How to write this move, made from `from`.
+    pub fn notation(&self, from: &B) -> Notation {
+        from.notation(self.id)
+    }
+}
+
+/// The ways of writing a move, for people rather than for programs (which
+/// use its id).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notation {
+    /// As players write it down, like "c4" or "13/7*".
+    pub short: String,
+    /// Spelled out so it reads on its own, like "place on c4".
+    pub long: String,
+    /// Who does what, like "R: place on c4".
+    pub descriptive: String,
+}
+
+/// How a game stands: still going on, or over and either won or drawn.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Ongoing,
+    /// The game is over, won by the players listed (in games like Chinese
+    /// Checkers, in the order they finished).
+    Win(SmallVec<[Player; 1]>),
+    Draw,
+}
+
+impl Outcome {
+    /// The outcome that a move's `end_game` describes.
+    pub fn from_end_game(end_game: Option<SmallVec<[Player; 1]>>) -> Self {
+        match end_game {
+            None => Outcome::Ongoing,
+            Some(victors) if victors.is_empty() => Outcome::Draw,
+            Some(victors) => Outcome::Win(victors),
+        }
+    }
+
+    /// A game won by `p` alone.
+    pub fn win(p: Player) -> Self {
+        Outcome::Win(Some(p).into_iter().collect())
+    }
+
+    /// The `end_game` of a move leading to a state with this outcome.
+    pub fn end_game(&self) -> Option<SmallVec<[Player; 1]>> {
+        match self {
+            Outcome::Ongoing => None,
+            Outcome::Win(victors) => Some(victors.clone()),
+            Outcome::Draw => Some(SmallVec::new()),
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        *self != Outcome::Ongoing
+    }
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Outcome::Ongoing => write!(f, "the game goes on"),
+            Outcome::Win(victors) => {
+                let names: Vec<String> = victors.iter().map(|p| p.to_string()).collect();
+                write!(f, "victory goes to {}", names.join(", "))
+            }
+            Outcome::Draw => write!(f, "it's a draw"),
+        }
+    }
+}
+
+/// Why `Game::parse` rejected its input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// The index of the offending character in the input (counting
+    /// characters, from 0), if one is to blame.
+    pub position: Option<usize>,
+    /// What the input should have held instead, like "'X', 'O' or '-'".
+    pub expected: Cow<'static, str>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The input, or some part of it, is too long or too short.
+    WrongLength,
+    /// A character that does not belong where it is.
+    UnexpectedCharacter,
+    /// Every part is well formed, but together they describe a state that
+    /// cannot come up in play, like a tictactoe board with more O's than X's.
+    InvalidState,
+    /// A creation option (see `GameOptions`) the game does not have.
+    UnknownOption,
+    /// A creation option with a value the game cannot take.
+    InvalidOption,
+}
+
+impl ParseError {
+    pub fn wrong_length(expected: impl Into<Cow<'static, str>>) -> Self {
+        ParseError { kind: ParseErrorKind::WrongLength, position: None, expected: expected.into() }
+    }
+
+    pub fn unexpected(position: usize, expected: impl Into<Cow<'static, str>>) -> Self {
+        ParseError { kind: ParseErrorKind::UnexpectedCharacter, position: Some(position), expected: expected.into() }
+    }
+
+    pub fn invalid(expected: impl Into<Cow<'static, str>>) -> Self {
+        ParseError { kind: ParseErrorKind::InvalidState, position: None, expected: expected.into() }
+    }
+
+    pub fn unknown_option(position: usize, expected: impl Into<Cow<'static, str>>) -> Self {
+        ParseError { kind: ParseErrorKind::UnknownOption, position: Some(position), expected: expected.into() }
+    }
+
+    pub fn invalid_option(position: usize, expected: impl Into<Cow<'static, str>>) -> Self {
+        ParseError { kind: ParseErrorKind::InvalidOption, position: Some(position), expected: expected.into() }
+    }
+
+    /// The same error, blamed on the character at `position`.
+    pub fn at(self, position: usize) -> Self {
+        ParseError { position: Some(position), ..self }
+    }
+
+    /// The same error, for input that was part of a larger input starting
+    /// `offset` characters further on.
+    pub fn shifted(self, offset: usize) -> Self {
+        ParseError { position: self.position.map(|p| p + offset), ..self }
+    }
+}
+
+impl ParseErrorKind {
+    /// A stable, machine-readable name for the kind of error, as the service
+    /// reports it.
+    pub fn code(self) -> &'static str {
+        match self {
+            ParseErrorKind::WrongLength => "wrong_length",
+            ParseErrorKind::UnexpectedCharacter => "unexpected_character",
+            ParseErrorKind::InvalidState => "invalid_state",
+            ParseErrorKind::UnknownOption => "unknown_option",
+            ParseErrorKind::InvalidOption => "invalid_option",
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.kind {
+            ParseErrorKind::WrongLength => write!(f, "wrong length")?,
+            ParseErrorKind::UnexpectedCharacter => write!(f, "unexpected character")?,
+            ParseErrorKind::InvalidState => write!(f, "impossible game state")?,
+            ParseErrorKind::UnknownOption => write!(f, "unknown option")?,
+            ParseErrorKind::InvalidOption => write!(f, "invalid option")?,
+        }
+        if let Some(position) = self.position {
+            write!(f, " at position {}", position)?;
+        }
+        write!(f, ": expected {}", self.expected)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Options for creating a game, like "size=9,misere": separated by commas,
+/// each a name and `=value`, or just a name, which turns the option on. A game
+/// takes the options it has one by one, then calls `finish` to reject any it
+/// does not have; options that are not given keep their defaults.
+#[derive(Debug)]
+pub struct GameOptions<'a> {
+    given: Vec<GivenOption<'a>>,
+}
+
+#[derive(Debug)]
+struct GivenOption<'a> {
+    name: &'a str,
+    value: Option<&'a str>,
+    /// Where the option starts in the input.
+    position: usize,
+    taken: bool,
+}
+
+impl<'a> GameOptions<'a> {
+    pub fn parse(input: &'a str) -> Result<Self, ParseError> {
+        let mut given = Vec::new();
+        let mut position = 0;
+        for option in input.split(',').filter(|_| !input.is_empty()) {
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (option, None),
+            };
+            if name.is_empty() {
+                return Err(ParseError::unexpected(position, "the name of an option"));
+            }
+            if given.iter().any(|g: &GivenOption| g.name == name) {
+                return Err(ParseError::invalid_option(position, format!("`{}` only once", name)));
+            }
+            given.push(GivenOption { name, value, position, taken: false });
+            position += option.chars().count() + 1;
+        }
+        Ok(GameOptions { given })
+    }
+
+    /// Takes the option `name`, returning its value and where that starts.
+    fn take(&mut self, name: &str) -> Option<(Option<&'a str>, usize)> {
+        let option = self.given.iter_mut().find(|g| g.name == name)?;
+        option.taken = true;
+        Some((option.value, option.position + name.chars().count() + 1))
+    }
+
+    /// The number given for the option `name`, which must be in `range`.
+    pub fn number(&mut self, name: &str, range: RangeInclusive<usize>) -> Result<Option<usize>, ParseError> {
+        let (value, at) = match self.take(name) {
+            Some(option) => option,
+            None => return Ok(None),
+        };
+        match value.and_then(|v| v.parse().ok()) {
+            Some(n) if range.contains(&n) => Ok(Some(n)),
+            _ => Err(ParseError::invalid_option(
+                at, format!("`{}` to be a number from {} to {}", name, range.start(), range.end()))),
+        }
+    }
+
+    /// The number given for the option `name`, which must be one of `allowed`.
+    pub fn number_of(&mut self, name: &str, allowed: &[usize]) -> Result<Option<usize>, ParseError> {
+        let (value, at) = match self.take(name) {
+            Some(option) => option,
+            None => return Ok(None),
+        };
+        match value.and_then(|v| v.parse().ok()) {
+            Some(n) if allowed.contains(&n) => Ok(Some(n)),
+            _ => {
+                let allowed: Vec<String> = allowed.iter().map(|n| n.to_string()).collect();
+                Err(ParseError::invalid_option(at, format!("`{}` to be one of {}", name, allowed.join(", "))))
+            }
+        }
+    }
+
+    /// Whether the option `name` is on: given alone, or as `yes` or `no`.
+    pub fn flag(&mut self, name: &str) -> Result<Option<bool>, ParseError> {
+        match self.take(name) {
+            None => Ok(None),
+            Some((None, _)) | Some((Some("yes"), _)) => Ok(Some(true)),
+            Some((Some("no"), _)) => Ok(Some(false)),
+            Some((Some(_), at)) => Err(ParseError::invalid_option(at, format!("`{}` to be yes or no", name))),
+        }
+    }
+
+    /// The player given for the option `name`, which must be one of `players`.
+    pub fn player(&mut self, name: &str, players: &[Player]) -> Result<Option<Player>, ParseError> {
+        let (value, at) = match self.take(name) {
+            Some(option) => option,
+            None => return Ok(None),
+        };
+        let mut chars = value.unwrap_or("").chars();
+        match (chars.next(), chars.next()) {
+            (Some(p), None) if players.contains(&p) => Ok(Some(p)),
+            _ => {
+                let names: Vec<String> = players.iter().map(|p| p.to_string()).collect();
+                Err(ParseError::invalid_option(at, format!("`{}` to be one of {}", name, names.join(", "))))
+            }
+        }
+    }
+
+    /// The raw text given for the option `name`, for options whose values the
+    /// game reads itself; errors in it are to be shifted by the position
+    /// returned along with it.
+    pub fn text(&mut self, name: &str) -> Option<(&'a str, usize)> {
+        self.take(name).map(|(value, at)| (value.unwrap_or(""), at))
+    }
+
+    /// Checks that every option given was taken, naming the ones that were
+    /// (`known`) if not.
+    pub fn finish(self, known: &[&str]) -> Result<(), ParseError> {
+        match self.given.iter().find(|g| !g.taken) {
+            None => Ok(()),
+            Some(g) if known.is_empty() => Err(ParseError::unknown_option(g.position, "no options, as there are none")),
+            Some(g) => Err(ParseError::unknown_option(g.position, format!("one of the options {}", known.join(", ")))),
+        }
+    }
+}
+
 /// A `Game` represents the state of a turn-based game. You can serialize or
 /// deserialize it from a string, you can render it to a human-readable block of
 /// text, or you can query it for a list of possible actions ("moves") to take
 /// on the game state.
 ///
-/// A valid initial game state can be build via `Default::default()`.
+/// A valid initial game state can be build via `Default::default()`, or,
+/// for games that start from a random deal or layout, `random_start`.
 ///
 /// The games we use as examples will be simple enough to have human-readable
 /// strings for their serialized representation. For example, tictactoe can be
@@ -71,8 +358,30 @@ pub trait Game: Sized + Clone + Default {
     const NAME: &'static str;
 
     /// Deserializes an input string to an instance of the game, or returns an
-    /// error with a description of why deserialization failed.
-    fn parse(input: &str) -> Result<Self, Cow<str>>;
+    /// error saying what is wrong with it, and where.
+    fn parse(input: &str) -> Result<Self, ParseError>;
+
+    /// A new game, created with the given options. By default, a game has no
+    /// options, and starts as `Default::default()`.
+    fn with_options(options: GameOptions) -> Result<Self, ParseError> {
+        options.finish(&[])?;
+        Ok(Self::default())
+    }
+
+    /// A new game whose random start (the cards dealt, or the fleets laid
+    /// out) is drawn from `rng`, so that it can be had again from the same
+    /// seed. Games that start at random use this for `Default`, with
+    /// `Rng::from_entropy`; the rest start as `Default::default()`.
+    fn random_start(_rng: &mut Rng) -> Self {
+        Self::default()
+    }
+
+    /// The options this game was created with, as `with_options` takes them,
+    /// including the ones left to their defaults; empty for games without
+    /// options. Clients can tell from this which variant they are playing.
+    fn options(&self) -> String {
+        String::new()
+    }
 
     /// Converts a game state to its corresponding serialized string.
     fn unparse(&self) -> String;
@@ -81,7 +390,7 @@ pub trait Game: Sized + Clone + Default {
     fn current_player(&self) -> Player;
 
     /// Produces the set of "moves" that are available to take from the current
-    /// game state.
+    /// game state. Once the game is over (see `outcome`), there are none.
     ///
     /// Note that while in most cases a "move" will correspond to a player's
     /// move in the game (e.g. putting an 'X' in the upper-right corner in
@@ -91,6 +400,50 @@ pub trait Game: Sized + Clone + Default {
     /// "move" will be to select the new position for that piece on the board).
     fn moves(&self) -> Vec<Move<Self>>;
 
+    /// A human-friendly name for the move `id` from this state, such as "c4"
+    /// for a board coordinate: its short notation. By default, moves are just
+    /// named by their ids.
+    fn move_name(&self, id: MoveId) -> String {
+        id.to_string()
+    }
+
+    /// The long notation for the move `id`, which makes sense without the
+    /// board in front of you, like "place on c4". By default, the short one.
+    fn move_long_name(&self, id: MoveId) -> String {
+        self.move_name(id)
+    }
+
+    /// Describes the move `id` from this state, saying who makes it. By
+    /// default, the player (or chance) and the long notation.
+    fn describe_move(&self, id: MoveId) -> String {
+        match self.current_player() {
+            CHANCE => format!("chance: {}", self.move_long_name(id)),
+            p => format!("{}: {}", p, self.move_long_name(id)),
+        }
+    }
+
+    /// Every notation for the move `id`.
+    fn notation(&self, id: MoveId) -> Notation {
+        Notation {
+            short: self.move_name(id),
+            long: self.move_long_name(id),
+            descriptive: self.describe_move(id),
+        }
+    }
+
+    /// The move from this state that `input` names, by its id or any of its
+    /// notations (ignoring case), if there is one.
+    fn find_move(&self, input: &str) -> Option<MoveId> {
+        let input = input.trim();
+        let ids = self.move_ids();
+        ids.iter().find(|id| id.to_string() == input).copied().or_else(|| {
+            ids.into_iter().find(|id| {
+                let Notation { short, long, descriptive } = self.notation(*id);
+                [short, long, descriptive].iter().any(|n| n.eq_ignore_ascii_case(input))
+            })
+        })
+    }
+
     /// Renders the game state into a human visible depiction of the globally
     /// visible board.
     fn render_to_text(&self) -> String;
@@ -100,12 +453,559 @@ pub trait Game: Sized + Clone + Default {
     /// `p`, then `value_for(p)` might return 100,000 here, and if it is a
     /// guaranteed loss, it might return -100,000.
     fn value_for(&self, p: Player) -> i64;
+
+    /// Whether the game is over in this state, and if so, how it ended. This
+    /// agrees with `Move::end_game` for the move that led here, but can be
+    /// asked of any state, including one just parsed.
+    fn outcome(&self) -> Outcome;
+
+    /// How many points the victors score once the game is won, in games
+    /// where some wins count for more than others (like a gammon in
+    /// Backgammon). Otherwise a win is worth one point, and a draw or a game
+    /// still going on nothing.
+    fn points(&self) -> u32 {
+        if matches!(self.outcome(), Outcome::Win(_)) { 1 } else { 0 }
+    }
+
+    /// Whether some of the game state is kept secret from some of the players,
+    /// like the layout of each fleet in Battleship. The serialized state and
+    /// `render_to_text` of such games give everything away, so players must
+    /// only ever be shown `observation`, `render_for` and `moves_for`.
+    const HIDDEN_INFORMATION: bool = false;
+
+    /// Serializes everything `p` knows about the game state: two states with
+    /// the same observation for `p` are indistinguishable to `p`.
+    fn observation(&self, _p: Player) -> String {
+        self.unparse()
+    }
+
+    /// Renders the game state as `p` sees it.
+    fn render_for(&self, _p: Player) -> String {
+        self.render_to_text()
+    }
+
+    /// The ids of the moves that `p` may choose from: the current player's
+    /// moves, and none for anyone else.
+    fn moves_for(&self, p: Player) -> Vec<MoveId> {
+        if p == self.current_player() {
+            self.moves().iter().map(|m| m.id).collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// A game state that `p` cannot tell apart from this one, with whatever is
+    /// hidden from `p` guessed at random from `seed`. Search plays out such
+    /// guesses rather than peeking at the real state.
+    fn determinize(&self, _p: Player, _seed: u64) -> Self {
+        self.clone()
+    }
+
+    /// Whether the game has chance states, where `current_player` is `CHANCE`.
+    const HAS_CHANCE_MOVES: bool = false;
+
+    /// How likely the chance move `id` is, relative to the other moves of
+    /// this chance state. For example, in Backgammon each roll of doubles has
+    /// weight 1 and every other roll has weight 2 (as 6-5 can come up two
+    /// ways).
+    fn chance_weight(&self, _id: MoveId) -> u32 {
+        1
+    }
+
+    /// Whether the game is a puzzle for a single player, like the 15-puzzle,
+    /// rather than a contest. A puzzle is over once it is solved, which is
+    /// the lone player's victory: the move that solves it has `end_game`
+    /// listing just them.
+    ///
+    /// In puzzles, `value_for` is a heuristic: minus an estimate of how many
+    /// moves are left to solve the puzzle, and 0 once it is solved. For
+    /// `solver::solve` to find the shortest solutions, the estimate must
+    /// never be too high.
+    const PUZZLE: bool = false;
+
+    /// For games solved ahead of time (like poker, by `cfr`), a mixed
+    /// strategy for the player to move: the probability of choosing each of
+    /// their moves. It may only depend on what the player to move can see.
+    /// When there is one, `search` just picks a move at random by it.
+    fn policy(&self) -> Option<Vec<(MoveId, f64)>> {
+        None
+    }
+
+    /// Whether the game implements `lazy_moves` to build its moves one at a
+    /// time, so that search builds no more next states than it looks at
+    /// before alpha-beta cuts it off. Search takes such moves in the order
+    /// given, without first putting the moves that keep the turn ahead of the
+    /// rest, so games with extra turns should list those first.
+    const LAZY_MOVES: bool = false;
+
+    /// The moves of `moves`, in the same order, each built only once it is
+    /// asked for. Games with `LAZY_MOVES` implement this, and implement
+    /// `moves` by collecting it.
+    fn lazy_moves(&self) -> Box<dyn Iterator<Item = Move<Self>> + '_> {
+        Box::new(self.moves().into_iter())
+    }
+
+    /// One of the moves, each as likely as any other (whatever its
+    /// `chance_weight`), or `None` if there are none. Random playouts call
+    /// this at every step, so games that can pick a legal move without
+    /// building them all (say, any empty cell) should.
+    fn random_move(&self, rng: &mut Rng) -> Option<Move<Self>> {
+        let mut moves = self.moves();
+        match moves.len() {
+            0 => None,
+            n => Some(moves.swap_remove(rng.below(n))),
+        }
+    }
+
+    /// Whether the game implements `make_move` and `unmake_move` itself, so
+    /// that search can try moves out on a single state instead of building
+    /// every next state with `moves`. (The default ones build them anyway.)
+    const IN_PLACE_MOVES: bool = false;
+
+    /// The ids of the moves from this state, in the same order as `moves`.
+    ///
+    /// Search in place tries moves in this order, without first putting the
+    /// moves that keep the turn ahead of the rest as it otherwise would, so
+    /// games with extra turns should list those first.
+    fn move_ids(&self) -> Vec<MoveId> {
+        self.moves().iter().map(|m| m.id).collect()
+    }
+
+    /// Makes the move `id` (one of `move_ids`), turning this state into that
+    /// move's `next_state`. By default, this finds the move among `moves` and
+    /// keeps the state from before it to put back; games with
+    /// `IN_PLACE_MOVES` do better.
+    fn make_move(&mut self, id: MoveId) -> MadeMove<Self> {
+        let m = self.moves().into_iter().find(|m| m.id == id).expect("moves are made from move_ids");
+        let before = std::mem::replace(self, m.next_state);
+        MadeMove { end_game: m.end_game, undo: Undo::State(Box::new(before)) }
+    }
+
+    /// Takes back the move `id`, given the `undo` that `make_move` returned
+    /// for it, restoring this state to what it was before. By default, this
+    /// puts back the state that the default `make_move` kept.
+    fn unmake_move(&mut self, _id: MoveId, undo: Undo<Self>) {
+        if let Undo::State(before) = undo {
+            *self = *before;
+        }
+    }
+}
+
+/// What `Game::make_move` reports about the move it made.
+#[derive(Clone, Debug)]
+pub struct MadeMove<B> {
+    /// As for `Move::end_game`.
+    pub end_game: Option<SmallVec<[Player; 1]>>,
+
+    /// Whatever else `Game::unmake_move` needs to take the move back.
+    pub undo: Undo<B>,
 }
 
-/// Chooses the "best" move for `p` amongst a provided set of moves.
-pub async fn search<B: Game>(moves: &[Move<B>], _p: Player) -> &Move<B> {
-    // Note: this strategy can clearly be improved upon.
-    &moves[0]
+/// How `Game::unmake_move` takes a move back.
+#[derive(Clone, Debug)]
+pub enum Undo<B> {
+    /// Whatever the game needs, packed into 64 bits by the game (for
+    /// example, a bitmask of captured pieces).
+    Packed(u64),
+    /// The whole state from before the move, as the default
+    /// `Game::make_move` keeps it.
+    State(Box<B>),
+}
+
+/// A small xorshift random number generator, for games that shuffle or deal
+/// at random. It is not cryptographically secure: anything kept from players
+/// must not be derived from a seed they could guess.
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift gets stuck at zero, so scramble the seed first.
+        Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    /// A generator seeded from the operating system's randomness (by way of
+    /// the random keys that the standard library gives each `HashMap`).
+    pub fn from_entropy() -> Self {
+        use std::hash::{BuildHasher, Hasher};
+        Rng::new(std::collections::hash_map::RandomState::new().build_hasher().finish())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number from 0 up to (but excluding) `n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// A number from 0 up to (but excluding) 1.
+    pub fn fraction(&mut self) -> f64 {
+        // The top 53 bits, as many as an f64 holds exactly.
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+/// The valuation that `value_for` should give a finished game that `p` won
+/// (or, when negated, lost).
+pub const VICTORY: i64 = 100000;
+
+/// How many moves ahead `search` looks.
+const SEARCH_DEPTH: usize = 3;
+
+/// How many extra moves `search` will follow along a single line of play when
+/// a player moves several times in a row (e.g. completing boxes in Dots and
+/// Boxes). Such chains do not use up `SEARCH_DEPTH`, so that the search sees
+/// what the opponent does once the chain is over; this bounds them, since
+/// chains can be arbitrarily long.
+const CHAIN_EXTENSION: usize = 4;
+
+/// How many guesses at the hidden state `search` plays out in games with
+/// hidden information, and how far it looks in each.
+const DETERMINIZATIONS: u64 = 8;
+const HIDDEN_SEARCH_DEPTH: usize = 1;
+
+/// How many moves ahead `search` looks in games with chance moves, where every
+/// chance state multiplies the work by its number of outcomes.
+const CHANCE_SEARCH_DEPTH: usize = 1;
+
+/// Chooses the "best" move for `p` amongst a provided set of moves, which are
+/// the moves of `state`.
+///
+/// This is a depth-limited minimax with alpha-beta pruning. It does not assume
+/// that players alternate: each state is maximized or minimized according to
+/// whether `p` is its `current_player`, so passes and extra turns are handled
+/// naturally.
+///
+/// In games with hidden information, searching the real state would cheat, so
+/// instead each move is scored by its total value over several states that `p`
+/// cannot tell apart from the real one (see `Game::determinize`).
+///
+/// Chance states are valued by the weighted average over their outcomes
+/// ("expectiminimax"). If `p` is `CHANCE` itself, a move is just picked at
+/// random, as it is for games with a precomputed `Game::policy`.
+pub async fn search<'a, B: Game>(state: &B, moves: &'a [Move<B>], p: Player) -> &'a Move<B> {
+    if p == CHANCE {
+        return sample_chance(state, moves, &mut Rng::from_entropy());
+    }
+    if let Some(policy) = state.policy() {
+        return sample_policy(&policy, moves, &mut Rng::from_entropy());
+    }
+    if B::HIDDEN_INFORMATION {
+        return search_hidden(state, moves, p);
+    }
+    let depth = if B::HAS_CHANCE_MOVES { CHANCE_SEARCH_DEPTH } else { SEARCH_DEPTH };
+    let api = if B::IN_PLACE_MOVES { MoveApi::InPlace } else { MoveApi::Moves };
+    let (best, _) = alpha_beta(state, moves, p, depth, api);
+    &moves[best]
+}
+
+/// How `alpha_beta` tries out moves below the first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveApi {
+    /// Building each next state, with `Game::moves`.
+    Moves,
+    /// Making and unmaking moves on a single state, with `Game::make_move`.
+    InPlace,
+}
+
+/// The minimax search behind `search`, looking `depth` moves ahead: the index
+/// of the best of `moves` (the moves of `state`) for `p`, and its value.
+pub fn alpha_beta<B: Game>(state: &B, moves: &[Move<B>], p: Player, depth: usize, api: MoveApi) -> (usize, i64) {
+    let mut scratch = state.clone();
+    let mut best = 0;
+    let mut best_value = i64::MIN;
+    for (i, m) in moves.iter().enumerate() {
+        let value = match api {
+            MoveApi::Moves => move_value(m, p, p, depth, CHAIN_EXTENSION, best_value, i64::MAX),
+            MoveApi::InPlace => {
+                let made = scratch.make_move(m.id);
+                let value = made_value(&mut scratch, &made, p, p, depth, CHAIN_EXTENSION, best_value, i64::MAX);
+                scratch.unmake_move(m.id, made.undo);
+                value
+            }
+        };
+        if value > best_value {
+            best = i;
+            best_value = value;
+        }
+    }
+    (best, best_value)
+}
+
+/// Picks one of the moves of the chance state `state` at random, according to
+/// their weights.
+pub fn sample_chance<'a, B: Game>(state: &B, moves: &'a [Move<B>], rng: &mut Rng) -> &'a Move<B> {
+    let total: u32 = moves.iter().map(|m| state.chance_weight(m.id)).sum();
+    let mut roll = rng.below(total as usize) as u32;
+    for m in moves {
+        let weight = state.chance_weight(m.id);
+        if roll < weight {
+            return m;
+        }
+        roll -= weight;
+    }
+    unreachable!("the weights add up to the total")
+}
+
+/// Picks one of `moves` at random, with the probabilities given by `policy`.
+/// Moves that `policy` leaves out are never picked, unless it leaves them all
+/// out, when any move is as likely as any other.
+pub fn sample_policy<'a, B: Game>(policy: &[(MoveId, f64)], moves: &'a [Move<B>], rng: &mut Rng) -> &'a Move<B> {
+    let probability = |m: &Move<B>| policy.iter().find(|(id, _)| *id == m.id).map_or(0.0, |(_, q)| *q);
+    let total: f64 = moves.iter().map(probability).sum();
+    if total <= 0.0 {
+        return &moves[rng.below(moves.len())];
+    }
+    let mut roll = rng.fraction() * total;
+    for m in moves {
+        roll -= probability(m);
+        if roll < 0.0 {
+            return m;
+        }
+    }
+    // Rounding may leave a sliver over for the last move with any chance.
+    moves.iter().rev().find(|m| probability(m) > 0.0).unwrap()
+}
+
+/// Plays `state` out to the end at random, as Monte Carlo methods do: each
+/// move by `Game::random_move`, except chance moves, which go by their
+/// weights. Returns the victors, or `None` if no move has ended the game
+/// within `max_moves` moves.
+// The service does not play out games (yet); `local bench` does.
+#[allow(dead_code)]
+pub fn playout<B: Game>(state: &B, rng: &mut Rng, max_moves: usize) -> Option<SmallVec<[Player; 1]>> {
+    let mut state = state.clone();
+    for _ in 0..max_moves {
+        let m = if state.current_player() == CHANCE {
+            let moves = state.moves();
+            if moves.is_empty() {
+                return None;
+            }
+            sample_chance(&state, &moves, rng).clone()
+        } else {
+            state.random_move(rng)?
+        };
+        if m.end_game.is_some() {
+            return m.end_game;
+        }
+        state = m.next_state;
+    }
+    None
+}
+
+/// The move from `state` that `input` names, by its id or any of its
+/// notations (as `Game::find_move` reads it), for tests that know it is
+/// there.
+#[cfg(test)]
+pub fn play<B: Game>(state: &B, input: &str) -> Move<B> {
+    let id = state.find_move(input)
+        .unwrap_or_else(|| panic!("{} is not a move from {:?}", input, state.unparse()));
+    state.moves().into_iter().find(|m| m.id == id).unwrap()
+}
+
+fn search_hidden<'a, B: Game>(state: &B, moves: &'a [Move<B>], p: Player) -> &'a Move<B> {
+    use std::hash::{Hash, Hasher};
+
+    // Seeding from the observation keeps the choice from depending on
+    // anything `p` cannot see.
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    state.observation(p).hash(&mut hasher);
+    let seed = hasher.finish();
+
+    let mut totals = vec![0; moves.len()];
+    for s in 0..DETERMINIZATIONS {
+        let guess = state.determinize(p, seed.wrapping_add(s));
+        for guessed_move in guess.moves() {
+            if let Some(i) = moves.iter().position(|m| m.id == guessed_move.id) {
+                totals[i] += move_value(&guessed_move, p, p, HIDDEN_SEARCH_DEPTH, 0, i64::MIN, i64::MAX);
+            }
+        }
+    }
+    let best = (0..moves.len()).max_by_key(|i| totals[*i]).unwrap();
+    &moves[best]
+}
+
+/// The value of a finished game worth `value`, reached with `depth` moves of
+/// the search still to go. A win is worth a little more the sooner it comes
+/// (and a loss the later), or else a search that can always win next turn
+/// might put it off forever.
+fn end_value(value: i64, depth: usize) -> i64 {
+    value + value.signum() * depth as i64
+}
+
+/// Evaluates the state that `m` leads to for `p`, where `mover` is the player
+/// taking `m`.
+fn move_value<B: Game>(m: &Move<B>,
+                       p: Player,
+                       mover: Player,
+                       depth: usize,
+                       extension: usize,
+                       alpha: i64,
+                       beta: i64) -> i64
+{
+    if m.end_game.is_some() {
+        return end_value(m.next_state.value_for(p), depth);
+    }
+    let next = m.next_state.current_player();
+    if mover == CHANCE && next != CHANCE {
+        // The outcome of a chance move belongs to the turn that follows it.
+        minimax(&m.next_state, p, depth, extension, alpha, beta)
+    } else if next == mover && extension > 0 {
+        minimax(&m.next_state, p, depth, extension - 1, alpha, beta)
+    } else {
+        minimax(&m.next_state, p, depth - 1, extension, alpha, beta)
+    }
+}
+
+fn minimax<B: Game>(state: &B,
+                    p: Player,
+                    depth: usize,
+                    extension: usize,
+                    alpha: i64,
+                    beta: i64) -> i64
+{
+    if depth == 0 {
+        return state.value_for(p);
+    }
+    let mover = state.current_player();
+    if B::LAZY_MOVES && mover != CHANCE {
+        let mut moves = state.lazy_moves().peekable();
+        if moves.peek().is_none() {
+            return state.value_for(p);
+        }
+        return best_of(moves, p, mover, depth, extension, alpha, beta);
+    }
+    let mut moves = state.moves();
+    if moves.is_empty() {
+        return state.value_for(p);
+    }
+    if mover == CHANCE {
+        let total: i64 = moves.iter().map(|m| state.chance_weight(m.id) as i64).sum();
+        let weighted: i64 = moves.iter()
+            .map(|m| {
+                let value = move_value(m, p, mover, depth, extension, i64::MIN, i64::MAX);
+                state.chance_weight(m.id) as i64 * value
+            })
+            .sum();
+        return weighted / total;
+    }
+    // Moves that keep the turn are often strong, so look at them first.
+    moves.sort_by_key(|m| m.next_state.current_player() != mover);
+    best_of(moves.into_iter(), p, mover, depth, extension, alpha, beta)
+}
+
+/// The value for `p` of the best of `moves` for `mover`, taking them in order
+/// until alpha-beta cuts off the rest.
+fn best_of<B: Game>(moves: impl Iterator<Item = Move<B>>,
+                    p: Player,
+                    mover: Player,
+                    depth: usize,
+                    extension: usize,
+                    mut alpha: i64,
+                    mut beta: i64) -> i64
+{
+    let maximizing = mover == p;
+    let mut best = if maximizing { i64::MIN } else { i64::MAX };
+    for m in moves {
+        let value = move_value(&m, p, mover, depth, extension, alpha, beta);
+        if maximizing {
+            best = best.max(value);
+            alpha = alpha.max(value);
+        } else {
+            best = best.min(value);
+            beta = beta.min(value);
+        }
+        if alpha >= beta {
+            break;
+        }
+    }
+    best
+}
+
+/// Like `move_value`, for a move just made in place by `Game::make_move`,
+/// which turned `state` into the move's next state.
+#[allow(clippy::too_many_arguments)]
+fn made_value<B: Game>(state: &mut B,
+                       made: &MadeMove<B>,
+                       p: Player,
+                       mover: Player,
+                       depth: usize,
+                       extension: usize,
+                       alpha: i64,
+                       beta: i64) -> i64
+{
+    if made.end_game.is_some() {
+        return end_value(state.value_for(p), depth);
+    }
+    let next = state.current_player();
+    if mover == CHANCE && next != CHANCE {
+        minimax_in_place(state, p, depth, extension, alpha, beta)
+    } else if next == mover && extension > 0 {
+        minimax_in_place(state, p, depth, extension - 1, alpha, beta)
+    } else {
+        minimax_in_place(state, p, depth - 1, extension, alpha, beta)
+    }
+}
+
+/// Like `minimax`, making and unmaking moves on `state`, which is left as it
+/// was found.
+fn minimax_in_place<B: Game>(state: &mut B,
+                             p: Player,
+                             depth: usize,
+                             extension: usize,
+                             mut alpha: i64,
+                             mut beta: i64) -> i64
+{
+    if depth == 0 {
+        return state.value_for(p);
+    }
+    let mover = state.current_player();
+    let ids = state.move_ids();
+    if ids.is_empty() {
+        return state.value_for(p);
+    }
+    if mover == CHANCE {
+        let total: i64 = ids.iter().map(|id| state.chance_weight(*id) as i64).sum();
+        let mut weighted = 0;
+        for id in ids {
+            let weight = state.chance_weight(id) as i64;
+            let made = state.make_move(id);
+            weighted += weight * made_value(state, &made, p, mover, depth, extension, i64::MIN, i64::MAX);
+            state.unmake_move(id, made.undo);
+        }
+        return weighted / total;
+    }
+    // Unlike `minimax`, this takes the moves in the order given, which saves
+    // making every one of them just to sort them (see `Game::move_ids`).
+    let maximizing = mover == p;
+    let mut best = if maximizing { i64::MIN } else { i64::MAX };
+    for id in ids {
+        let made = state.make_move(id);
+        let value = made_value(state, &made, p, mover, depth, extension, alpha, beta);
+        state.unmake_move(id, made.undo);
+        if maximizing {
+            best = best.max(value);
+            alpha = alpha.max(value);
+        } else {
+            best = best.min(value);
+            beta = beta.min(value);
+        }
+        if alpha >= beta {
+            break;
+        }
+    }
+    best
 }
 
 #[derive(Debug)]
@@ -125,6 +1025,10 @@ pub enum Command {
     List,
     RenderToText,
     Select,
+    Solve,
+    /// Makes a move, named after the game state; in games with hidden
+    /// information, the only way to learn where a move leads.
+    Play,
 }
 
 impl TryFrom<char> for Command {
@@ -136,7 +1040,110 @@ impl TryFrom<char> for Command {
             'l' => Command::List,
             'r' => Command::RenderToText,
             's' => Command::Select,
+            'a' => Command::Solve,
+            'p' => Command::Play,
             _ => return Err(UnknownCommand),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A race to the end of a track: A either steps along it, winning on
+    /// reaching the end, or (from the start only) jumps straight there. B can
+    /// only wait.
+    #[derive(Clone, Debug)]
+    struct Race {
+        left: u8,
+        player: Player,
+    }
+
+    impl Default for Race {
+        fn default() -> Self {
+            Race { left: 2, player: 'A' }
+        }
+    }
+
+    impl Game for Race {
+        const NAME: &'static str = "Race";
+
+        /// The player to move, then how far A has left to go, like "A2".
+        fn parse(input: &str) -> Result<Self, ParseError> {
+            let mut chars = input.chars();
+            let player = match chars.next() {
+                Some(p @ 'A') | Some(p @ 'B') => p,
+                _ => return Err(ParseError::unexpected(0, "the player to move, A or B")),
+            };
+            match chars.as_str().parse::<u8>() {
+                Ok(left) if left <= 2 => Ok(Race { left, player }),
+                _ => Err(ParseError::unexpected(1, "how far A has left to go, 0 to 2")),
+            }
+        }
+
+        fn unparse(&self) -> String {
+            format!("{}{}", self.player, self.left)
+        }
+
+        fn current_player(&self) -> Player {
+            self.player
+        }
+
+        /// Move 1 steps (or waits, for B); move 2 jumps to the end.
+        fn moves(&self) -> Vec<Move<Self>> {
+            if self.left == 0 {
+                return Vec::new();
+            }
+            let to = |id, left| Move {
+                id,
+                end_game: if left == 0 { Some(Some('A').into_iter().collect()) } else { None },
+                next_state: Race { left, player: if self.player == 'A' { 'B' } else { 'A' } },
+            };
+            match self.player {
+                'A' if self.left == 2 => vec![to(1, 1), to(2, 0)],
+                'A' => vec![to(1, self.left - 1)],
+                _ => vec![to(1, self.left)],
+            }
+        }
+
+        fn render_to_text(&self) -> String {
+            self.unparse()
+        }
+
+        fn value_for(&self, p: Player) -> i64 {
+            match (self.left, p) {
+                (0, 'A') => VICTORY,
+                (0, _) => -VICTORY,
+                _ => 0,
+            }
+        }
+
+        fn outcome(&self) -> Outcome {
+            if self.left == 0 { Outcome::win('A') } else { Outcome::Ongoing }
+        }
+    }
+
+    #[test]
+    fn races_round_trip() {
+        for state in ["A2", "B1", "A1", "B0"] {
+            assert_eq!(Race::parse(state).unwrap().unparse(), state);
+        }
+        for bad in ["", "C2", "A", "A3", "A-1", "A1x"] {
+            assert!(Race::parse(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn search_prefers_the_sooner_win() {
+        // Stepping wins in three moves (A, B, A) and jumping in one; both
+        // are within reach of the search, which must jump.
+        let start = Race::default();
+        let moves = start.moves();
+        for api in [MoveApi::Moves, MoveApi::InPlace] {
+            let (best, value) = alpha_beta(&start, &moves, 'A', SEARCH_DEPTH, api);
+            assert_eq!(moves[best].id, 2, "with {:?}", api);
+            assert!(value > VICTORY);
+        }
+    }
+}
@@ -0,0 +1,613 @@
+use crate::game_core::{Game, GameOptions, Move, MoveId, Outcome, ParseError, Player, VICTORY};
+use smallvec::SmallVec;
+
+/// The board sizes we support.
+pub const SIZES: [usize; 3] = [9, 13, 19];
+
+/// The digits used to pack the board (three cells per digit) and the position
+/// hashes into URL-safe strings.
+const DIGITS: &[u8; 62] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Each position hash is written as this many base-62 digits (enough for 32
+/// bits).
+const HASH_DIGITS: usize = 6;
+
+/// How many recent positions positional superko remembers (the current one
+/// included). Repetition cycles in real games are short (a triple ko comes
+/// round again after six moves), and remembering only these keeps the state
+/// from growing with every move.
+pub const SUPERKO_HISTORY: usize = 16;
+
+/// The most komi, in half points, that White may receive: far more than any
+/// board is worth, while keeping the scores well within a `u32`.
+pub const MAX_KOMI_HALVES: u32 = 1000;
+
+/// Go column labels skip 'I'.
+const COLUMNS: &[u8; 19] = b"ABCDEFGHJKLMNOPQRST";
+
+/// Which repetitions of earlier positions are forbidden.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KoRule {
+    /// A single stone may not immediately recapture a single stone; `ko` is
+    /// the point where that recapture would happen, if any.
+    Simple { ko: Option<usize> },
+    /// No move may recreate a recent board position; `history` holds a hash
+    /// of each of the last `SUPERKO_HISTORY` positions, including the current
+    /// one.
+    PositionalSuperko { history: Vec<u32> },
+}
+
+/// Go on a 9x9, 13x13 or 19x19 board, with area scoring. Black ('B') moves
+/// first; White ('W') receives `komi` (in half points) as compensation. The
+/// game ends after two consecutive passes.
+///
+/// Points are numbered row by row from 1, starting at the top left (A9 on
+/// 9x9); passing is the move just past the last point. Suicide is illegal, and
+/// all stones left on the board at the end are counted as alive.
+#[derive(Clone, Debug)]
+pub struct GoGame {
+    pub size: usize,
+    pub board: Vec<char>,
+    pub player: Player,
+    pub komi_halves: u32,
+    /// How many passes have been made in a row.
+    pub passes: u8,
+    pub ko_rule: KoRule,
+}
+
+impl Default for GoGame {
+    fn default() -> Self {
+        Self::new(9, 13, false)
+    }
+}
+
+fn opponent(p: Player) -> Player {
+    if p == 'B' { 'W' } else { 'B' }
+}
+
+/// A 32-bit FNV-1a hash of a board.
+fn board_hash(board: &[char]) -> u32 {
+    board.iter().fold(0x811c_9dc5, |h, c| (h ^ *c as u32).wrapping_mul(0x0100_0193))
+}
+
+impl GoGame {
+    pub fn new(size: usize, komi_halves: u32, superko: bool) -> Self {
+        let board = vec!['-'; size * size];
+        let ko_rule = if superko {
+            KoRule::PositionalSuperko { history: vec![board_hash(&board)] }
+        } else {
+            KoRule::Simple { ko: None }
+        };
+        GoGame { size, board, player: 'B', komi_halves, passes: 0, ko_rule }
+    }
+
+    pub fn pass_id(&self) -> MoveId {
+        (self.size * self.size) as MoveId + 1
+    }
+
+    fn is_over(&self) -> bool {
+        self.passes >= 2
+    }
+
+    fn neighbors(&self, i: usize) -> SmallVec<[usize; 4]> {
+        let n = self.size;
+        let (r, c) = (i / n, i % n);
+        let mut v = SmallVec::new();
+        if r > 0 { v.push(i - n); }
+        if r + 1 < n { v.push(i + n); }
+        if c > 0 { v.push(i - 1); }
+        if c + 1 < n { v.push(i + 1); }
+        v
+    }
+
+    /// The stones of the group containing `i` on `board`, and how many
+    /// liberties it has.
+    fn group(&self, board: &[char], i: usize) -> (Vec<usize>, usize) {
+        let color = board[i];
+        let mut stones = vec![i];
+        let mut seen = vec![false; board.len()];
+        seen[i] = true;
+        let mut liberties = 0;
+        let mut k = 0;
+        while k < stones.len() {
+            for j in self.neighbors(stones[k]) {
+                if seen[j] { continue; }
+                seen[j] = true;
+                if board[j] == color {
+                    stones.push(j);
+                } else if board[j] == '-' {
+                    liberties += 1;
+                }
+            }
+            k += 1;
+        }
+        (stones, liberties)
+    }
+
+    /// Plays a stone for the current player at point `i`, returning the next
+    /// state, or `None` if the move is illegal (occupied, suicide, or a
+    /// forbidden repetition).
+    fn play(&self, i: usize) -> Option<GoGame> {
+        if self.board[i] != '-' { return None; }
+        if let KoRule::Simple { ko: Some(k) } = self.ko_rule {
+            if k == i { return None; }
+        }
+        let mut board = self.board.clone();
+        board[i] = self.player;
+        let mut captured = Vec::new();
+        for j in self.neighbors(i) {
+            if board[j] == opponent(self.player) {
+                let (stones, liberties) = self.group(&board, j);
+                if liberties == 0 {
+                    for s in stones {
+                        board[s] = '-';
+                        captured.push(s);
+                    }
+                }
+            }
+        }
+        let (own_stones, own_liberties) = self.group(&board, i);
+        if own_liberties == 0 { return None; }
+
+        let ko_rule = match &self.ko_rule {
+            KoRule::Simple { .. } => {
+                let ko = if captured.len() == 1 && own_stones.len() == 1 && own_liberties == 1 {
+                    Some(captured[0])
+                } else {
+                    None
+                };
+                KoRule::Simple { ko }
+            }
+            KoRule::PositionalSuperko { history } => {
+                let hash = board_hash(&board);
+                if history.contains(&hash) { return None; }
+                let mut history = history.clone();
+                history.push(hash);
+                if history.len() > SUPERKO_HISTORY {
+                    history.remove(0);
+                }
+                KoRule::PositionalSuperko { history }
+            }
+        };
+        Some(GoGame {
+            size: self.size,
+            board,
+            player: opponent(self.player),
+            komi_halves: self.komi_halves,
+            passes: 0,
+            ko_rule,
+        })
+    }
+
+    /// Area scores in half points: each player's stones plus the empty regions
+    /// that touch only their stones, with komi added to White.
+    fn scores(&self) -> (u32, u32) {
+        let (mut black, mut white) = (0, self.komi_halves);
+        let mut seen = vec![false; self.board.len()];
+        for i in 0..self.board.len() {
+            match self.board[i] {
+                'B' => black += 2,
+                'W' => white += 2,
+                _ if !seen[i] => {
+                    // flood fill the empty region, noting which colors border it.
+                    let mut region = vec![i];
+                    seen[i] = true;
+                    let (mut touches_black, mut touches_white) = (false, false);
+                    let mut k = 0;
+                    while k < region.len() {
+                        for j in self.neighbors(region[k]) {
+                            match self.board[j] {
+                                'B' => touches_black = true,
+                                'W' => touches_white = true,
+                                _ if !seen[j] => {
+                                    seen[j] = true;
+                                    region.push(j);
+                                }
+                                _ => {}
+                            }
+                        }
+                        k += 1;
+                    }
+                    let points = 2 * region.len() as u32;
+                    match (touches_black, touches_white) {
+                        (true, false) => black += points,
+                        (false, true) => white += points,
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        (black, white)
+    }
+
+    fn victors(&self) -> SmallVec<[Player; 1]> {
+        let (black, white) = self.scores();
+        if black > white {
+            Some('B').into_iter().collect()
+        } else if white > black {
+            Some('W').into_iter().collect()
+        } else {
+            SmallVec::new()
+        }
+    }
+}
+
+impl Game for GoGame {
+    const NAME: &'static str = "Go";
+
+    fn current_player(&self) -> Player {
+        self.player
+    }
+
+    /// The serialized form is
+    /// `<size><rule><player><passes>-<komi>-<board>-<ko>`, where `<rule>` is
+    /// 's' for simple ko or 'p' for positional superko, `<komi>` is in half
+    /// points, and `<board>` packs three points (each empty, black or white)
+    /// into each base-62 digit. Under simple ko, `<ko>` is the forbidden point
+    /// (or nothing); under positional superko, it is the hashes of the
+    /// recent positions, up to `SUPERKO_HISTORY` of them, six base-62 digits
+    /// each. E.g. the default game is
+    /// "9sB0-13-000000000000000000000000000-".
+    fn unparse(&self) -> String {
+        let packed: String = self.board.chunks(3)
+            .map(|cells| {
+                let n = cells.iter().rev().fold(0, |acc, c| {
+                    3 * acc + match c { 'B' => 1, 'W' => 2, _ => 0 }
+                });
+                DIGITS[n] as char
+            })
+            .collect();
+        let (rule, ko) = match &self.ko_rule {
+            KoRule::Simple { ko } => ('s', ko.map(|k| k.to_string()).unwrap_or_default()),
+            KoRule::PositionalSuperko { history } => {
+                let hashes = history.iter()
+                    .map(|h| {
+                        let mut h = *h as usize;
+                        let mut digits = [b'0'; HASH_DIGITS];
+                        for d in digits.iter_mut().rev() {
+                            *d = DIGITS[h % 62];
+                            h /= 62;
+                        }
+                        String::from_utf8(digits.to_vec()).unwrap()
+                    })
+                    .collect();
+                ('p', hashes)
+            }
+        };
+        format!("{}{}{}{}-{}-{}-{}", self.size, rule, self.player, self.passes, self.komi_halves, packed, ko)
+    }
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let parts: Vec<&str> = input.split('-').collect();
+        let (header, komi, packed, ko) = match parts[..] {
+            [header, komi, packed, ko] => (header, komi, packed, ko),
+            _ => {
+                // The end of the input, or the '-' that starts a fifth part.
+                let at = parts.iter().take(4).map(|p| p.len() + 1).sum::<usize>() - 1;
+                return Err(ParseError::wrong_length("<size><rule><player><passes>-<komi>-<board>-<ko>").at(at));
+            }
+        };
+        // Where each part starts in the input.
+        let komi_at = header.len() + 1;
+        let packed_at = komi_at + komi.len() + 1;
+        let ko_at = packed_at + packed.len() + 1;
+
+        let digits = header.chars().take_while(|c| c.is_ascii_digit()).count();
+        let size = match header[..digits].parse() {
+            Ok(n) if SIZES.contains(&n) => n,
+            _ => return Err(ParseError::unexpected(0, "a board size of 9, 13 or 19")),
+        };
+        let rest = &header.as_bytes()[digits..];
+        let superko = match rest.first() {
+            Some(b's') => false,
+            Some(b'p') => true,
+            _ => return Err(ParseError::unexpected(digits, "the ko rule, 's' or 'p'")),
+        };
+        let player = match rest.get(1) {
+            Some(p @ b'B') | Some(p @ b'W') => *p as char,
+            _ => return Err(ParseError::unexpected(digits + 1, "the player to move, B or W")),
+        };
+        let passes = match rest.get(2) {
+            Some(n @ b'0'..=b'2') => n - b'0',
+            _ => return Err(ParseError::unexpected(digits + 2, "the passes in a row, from 0 to 2")),
+        };
+        if rest.len() > 3 {
+            return Err(ParseError::unexpected(digits + 3, "'-' and the komi"));
+        }
+        let komi_halves = match komi.parse() {
+            Ok(k) if k <= MAX_KOMI_HALVES => k,
+            _ => return Err(ParseError::unexpected(komi_at, "komi as a number of half points, at most 1000")),
+        };
+
+        let mut g = GoGame::new(size, komi_halves, superko);
+        g.player = player;
+        g.passes = passes;
+        let num_digits = (size * size).div_ceil(3);
+        if packed.len() != num_digits {
+            let at = packed_at + packed.len().min(num_digits);
+            return Err(ParseError::wrong_length(format!("{} board digits for the board size", num_digits)).at(at));
+        }
+        for (chunk, d) in packed.bytes().enumerate() {
+            let mut n = match DIGITS.iter().position(|x| *x == d) {
+                Some(n) if n < 27 => n,
+                _ => return Err(ParseError::unexpected(packed_at + chunk, "a base-27 digit for three points")),
+            };
+            for cell in 3 * chunk..3 * chunk + 3 {
+                match (n % 3, cell < g.board.len()) {
+                    (1, true) => g.board[cell] = 'B',
+                    (2, true) => g.board[cell] = 'W',
+                    (0, _) => {}
+                    _ => return Err(ParseError::unexpected(packed_at + chunk, "no stones past the last point")),
+                }
+                n /= 3;
+            }
+        }
+
+        g.ko_rule = if superko {
+            if ko.len() % HASH_DIGITS != 0 {
+                let at = ko_at + ko.len() - ko.len() % HASH_DIGITS;
+                return Err(ParseError::wrong_length("position hashes of six digits each").at(at));
+            }
+            if ko.len() > SUPERKO_HISTORY * HASH_DIGITS {
+                return Err(ParseError::wrong_length(format!("at most {} position hashes", SUPERKO_HISTORY)).at(ko_at + SUPERKO_HISTORY * HASH_DIGITS));
+            }
+            let mut history = Vec::new();
+            for (k, hash) in ko.as_bytes().chunks(HASH_DIGITS).enumerate() {
+                let at = ko_at + k * HASH_DIGITS;
+                let mut h: u64 = 0;
+                for (j, d) in hash.iter().enumerate() {
+                    match DIGITS.iter().position(|x| x == d) {
+                        Some(n) => h = 62 * h + n as u64,
+                        None => return Err(ParseError::unexpected(at + j, "a position hash digit, in base 62")),
+                    }
+                }
+                if h > u32::MAX as u64 { return Err(ParseError::invalid("a position hash of at most 32 bits").at(at)); }
+                history.push(h as u32);
+            }
+            if history.last() != Some(&board_hash(&g.board)) {
+                return Err(ParseError::invalid("a position history ending with the current position"));
+            }
+            KoRule::PositionalSuperko { history }
+        } else if ko.is_empty() {
+            KoRule::Simple { ko: None }
+        } else {
+            match ko.parse() {
+                Ok(k) if k < g.board.len() => KoRule::Simple { ko: Some(k) },
+                _ => return Err(ParseError::unexpected(ko_at, "the ko, a point on the board")),
+            }
+        };
+        Ok(g)
+    }
+
+    /// `size` (9, 13 or 19), `komi` (in points, which may end in ".5") and
+    /// `superko` (whether positional superko replaces simple ko).
+    fn with_options(mut options: GameOptions) -> Result<Self, ParseError> {
+        let size = options.number_of("size", &SIZES)?.unwrap_or(9);
+        let komi_halves = match options.text("komi") {
+            None => 13,
+            Some((komi, at)) => {
+                let (points, half) = komi.strip_suffix(".5").map_or((komi, 0), |points| (points, 1));
+                let halves = points.parse::<u32>().ok()
+                    .and_then(|points| points.checked_mul(2))
+                    .and_then(|halves| halves.checked_add(half));
+                match halves {
+                    Some(halves) if halves <= MAX_KOMI_HALVES => halves,
+                    _ => return Err(ParseError::invalid_option(at, "`komi` to be a number of points up to 500, like 6.5")),
+                }
+            }
+        };
+        let superko = options.flag("superko")?.unwrap_or(false);
+        options.finish(&["size", "komi", "superko"])?;
+        Ok(GoGame::new(size, komi_halves, superko))
+    }
+
+    fn options(&self) -> String {
+        let superko = matches!(self.ko_rule, KoRule::PositionalSuperko { .. });
+        let half = if self.komi_halves % 2 == 1 { ".5" } else { "" };
+        format!("size={},komi={}{},superko={}", self.size, self.komi_halves / 2, half, if superko { "yes" } else { "no" })
+    }
+
+    fn moves(&self) -> Vec<Move<Self>> {
+        if self.is_over() {
+            return Vec::new();
+        }
+        let mut v: Vec<Move<Self>> = (0..self.board.len())
+            .filter_map(|i| self.play(i).map(|next_state| Move {
+                id: i as MoveId + 1,
+                next_state,
+                end_game: None,
+            }))
+            .collect();
+
+        let mut next_state = self.clone();
+        next_state.player = opponent(self.player);
+        next_state.passes += 1;
+        if let KoRule::Simple { ko } = &mut next_state.ko_rule {
+            *ko = None;
+        }
+        let end_game = if next_state.is_over() { Some(next_state.victors()) } else { None };
+        v.push(Move { id: self.pass_id(), next_state, end_game });
+        v
+    }
+
+    /// Points are named by column letter (skipping 'I') and row number,
+    /// counting rows from the bottom, e.g. "D4".
+    fn move_name(&self, id: MoveId) -> String {
+        if id == self.pass_id() {
+            return "pass".to_string();
+        }
+        let i = id as usize - 1;
+        format!("{}{}", COLUMNS[i % self.size] as char, self.size - i / self.size)
+    }
+
+    fn move_long_name(&self, id: MoveId) -> String {
+        if id == self.pass_id() {
+            "pass".to_string()
+        } else {
+            format!("play on {}", self.move_name(id))
+        }
+    }
+
+    fn render_to_text(&self) -> String {
+        let columns: String = COLUMNS[..self.size].iter().map(|c| format!(" {}", *c as char)).collect();
+        let mut s = format!("   {}\n", columns);
+        for r in 0..self.size {
+            let row = self.size - r;
+            s.push_str(&format!("{:>2} ", row));
+            for c in 0..self.size {
+                let i = r * self.size + c;
+                let ko = self.ko_rule == KoRule::Simple { ko: Some(i) };
+                s.push(' ');
+                s.push(match self.board[i] {
+                    '-' if ko => '*',
+                    '-' => '.',
+                    p => p,
+                });
+            }
+            s.push_str(&format!("  {}\n", row));
+        }
+        s.push_str(&format!("   {}\n", columns));
+        let (black, white) = self.scores();
+        s.push_str(&format!("B: {}  W: {} (komi {})  ",
+                            black as f64 / 2.0, white as f64 / 2.0, self.komi_halves as f64 / 2.0));
+        if self.is_over() {
+            s.push_str("game over\n");
+        } else {
+            s.push_str(&format!("({} to move)\n", self.player));
+        }
+        s
+    }
+
+    /// The difference in area score, counting komi.
+    fn value_for(&self, p: Player) -> i64 {
+        let (black, white) = self.scores();
+        let diff = if p == 'B' { black as i64 - white as i64 } else { white as i64 - black as i64 };
+        if self.is_over() {
+            diff.signum() * VICTORY
+        } else {
+            diff
+        }
+    }
+
+    /// The game ends after two passes in a row.
+    fn outcome(&self) -> Outcome {
+        if self.is_over() { Outcome::from_end_game(Some(self.victors())) } else { Outcome::Ongoing }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_core::play;
+
+    /// The point named like "E5" on a 9x9 board.
+    fn point(name: &str) -> usize {
+        let col = COLUMNS.iter().position(|c| *c == name.as_bytes()[0]).unwrap();
+        let row: usize = name[1..].parse().unwrap();
+        (9 - row) * 9 + col
+    }
+
+    fn game(superko: bool, black: &[&str], white: &[&str]) -> GoGame {
+        let mut g = GoGame::new(9, 13, superko);
+        for (stones, color) in [(black, 'B'), (white, 'W')] {
+            for name in stones {
+                g.board[point(name)] = color;
+            }
+        }
+        if let KoRule::PositionalSuperko { history } = &mut g.ko_rule {
+            *history = vec![board_hash(&g.board)];
+        }
+        g
+    }
+
+    /// Black has just taken the ko at E5 by playing F5.
+    fn ko(superko: bool) -> GoGame {
+        let g = game(superko, &["E6", "D5", "E4"], &["F6", "E5", "G5", "F4"]);
+        play(&g, "F5").next_state
+    }
+
+    #[test]
+    fn a_group_without_liberties_is_captured() {
+        let g = game(false, &["D5", "F5", "E6"], &["E5"]);
+        let next = play(&g, "E4").next_state;
+        assert_eq!(next.board[point("E5")], '-');
+        assert_eq!(next.board[point("E4")], 'B');
+    }
+
+    #[test]
+    fn suicide_is_illegal_unless_it_captures() {
+        let g = game(false, &[], &["D5", "F5", "E6", "E4"]);
+        assert!(g.find_move("E5").is_none());
+        // With E4 in atari, playing E5 takes its last liberty first.
+        let g = game(false, &["D4", "F4", "E3"], &["D5", "F5", "E6", "E4"]);
+        let next = play(&g, "E5").next_state;
+        assert_eq!(next.board[point("E4")], '-');
+    }
+
+    #[test]
+    fn simple_ko_forbids_retaking_at_once() {
+        let g = ko(false);
+        assert_eq!(g.board[point("E5")], '-');
+        assert_eq!(g.ko_rule, KoRule::Simple { ko: Some(point("E5")) });
+        assert!(g.find_move("E5").is_none());
+        // After a move elsewhere from each player, it may be retaken.
+        let later = play(&play(&g, "J9").next_state, "J1").next_state;
+        let retaken = play(&later, "E5").next_state;
+        assert_eq!(retaken.board[point("F5")], '-');
+    }
+
+    #[test]
+    fn superko_forbids_repeating_any_position() {
+        let g = ko(true);
+        assert!(g.find_move("E5").is_none());
+        let later = play(&play(&g, "J9").next_state, "J1").next_state;
+        assert!(later.find_move("E5").is_some());
+    }
+
+    #[test]
+    fn superko_remembers_a_bounded_history() {
+        let mut g = game(true, &[], &[]);
+        for _ in 0..40 {
+            g = g.moves().swap_remove(0).next_state;
+        }
+        let KoRule::PositionalSuperko { history } = &g.ko_rule else { panic!("superko is kept") };
+        assert_eq!(history.len(), SUPERKO_HISTORY);
+        let state = g.unparse();
+        let (rest, hashes) = state.rsplit_once('-').unwrap();
+        assert_eq!(hashes.len(), SUPERKO_HISTORY * HASH_DIGITS);
+        assert_eq!(GoGame::parse(&state).unwrap().unparse(), state);
+
+        let longer = format!("{}-{}{}", rest, &hashes[..HASH_DIGITS], hashes);
+        let e = GoGame::parse(&longer).unwrap_err();
+        assert!(e.expected.contains("at most 16"), "{}", e);
+    }
+
+    #[test]
+    fn area_scoring_counts_stones_territory_and_komi() {
+        // Black walls off the first four columns, White the last five.
+        let g = game(false,
+                     &["D1", "D2", "D3", "D4", "D5", "D6", "D7", "D8", "D9"],
+                     &["E1", "E2", "E3", "E4", "E5", "E6", "E7", "E8", "E9"]);
+        assert_eq!(g.scores(), (2 * 36, 2 * 45 + 13));
+        let over = play(&play(&g, "pass").next_state, "pass").next_state;
+        assert_eq!(over.outcome(), Outcome::win('W'));
+        assert_eq!(over.value_for('B'), -VICTORY);
+
+        // A region touching both colors counts for neither.
+        let g = game(false, &["A1"], &["J9"]);
+        assert_eq!(g.scores(), (2, 2 + 13));
+    }
+
+    #[test]
+    fn komi_is_bounded() {
+        let empty = "0".repeat(27);
+        assert!(GoGame::parse(&format!("9sB0-1000-{}-", empty)).is_ok());
+        assert!(GoGame::parse(&format!("9sB0-1001-{}-", empty)).is_err());
+        assert!(GoGame::parse(&format!("9sB0-4294967295-{}-", empty)).is_err());
+        let options = |komi: &str| GameOptions::parse(&format!("komi={}", komi)).and_then(GoGame::with_options);
+        assert_eq!(options("6.5").unwrap().komi_halves, 13);
+        assert!(options("500.5").is_err());
+        assert!(options("2147483648").is_err());
+    }
+}
@@ -0,0 +1,342 @@
+//! Rectangular boards, for games played on a grid of cells (like tictactoe,
+//! Othello or Go).
+//!
+//! A `Grid` is stored row by row, so a cell has both a coordinate and an
+//! index into the flat board; games that number their moves by cell can use
+//! the index. Rows are numbered from the top and columns from the left, and
+//! cells are named like "b3" (the second column of the third row).
+
+// Not every game on a grid uses every part of it (yet).
+#![allow(dead_code)]
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+use crate::game_core::ParseError;
+use crate::lines;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Coord {
+    pub row: usize,
+    pub col: usize,
+}
+
+impl Coord {
+    pub fn new(row: usize, col: usize) -> Self {
+        Coord { row, col }
+    }
+
+    /// The coordinate `steps` steps away in `direction`, if it is not off the
+    /// top or left edge. (Whether it is off the board is for the grid to say.)
+    fn step(self, (dr, dc): (isize, isize), steps: isize) -> Option<Coord> {
+        let row = self.row as isize + dr * steps;
+        let col = self.col as isize + dc * steps;
+        if row < 0 || col < 0 { None } else { Some(Coord::new(row as usize, col as usize)) }
+    }
+}
+
+/// Names the cell by column letter and row number, like "b3". (So grids with
+/// named cells are at most 26 columns wide.)
+impl fmt::Display for Coord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.col as u8) as char, self.row + 1)
+    }
+}
+
+/// Which cells count as next to each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Adjacency {
+    /// Up, down, left and right (as in Go).
+    Orthogonal,
+    /// The orthogonal neighbors and the diagonal ones (as in Othello).
+    All,
+    /// The six neighbors of a cell when the grid is drawn as a rhombus of
+    /// hexagons (as in Hex): the orthogonal ones, plus up-right and
+    /// down-left.
+    Hex,
+}
+
+impl Adjacency {
+    fn directions(self) -> &'static [(isize, isize)] {
+        match self {
+            Adjacency::Orthogonal => &[(-1, 0), (0, -1), (0, 1), (1, 0)],
+            Adjacency::All => &[(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)],
+            Adjacency::Hex => &[(-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0)],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    rows: usize,
+    cols: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+    /// A grid with every cell set to `fill`.
+    pub fn new(rows: usize, cols: usize, fill: T) -> Self {
+        Grid { rows, cols, cells: vec![fill; rows * cols] }
+    }
+}
+
+impl<T> Grid<T> {
+    /// A grid of the given cells, row by row; there must be `rows * cols`
+    /// of them.
+    pub fn from_cells(rows: usize, cols: usize, cells: Vec<T>) -> Self {
+        assert_eq!(cells.len(), rows * cols, "a {}x{} grid needs {} cells", rows, cols, rows * cols);
+        Grid { rows, cols, cells }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The cells, row by row.
+    pub fn cells(&self) -> &[T] {
+        &self.cells
+    }
+
+    pub fn contains(&self, c: Coord) -> bool {
+        c.row < self.rows && c.col < self.cols
+    }
+
+    /// The index of `c` into `cells`.
+    pub fn index_of(&self, c: Coord) -> usize {
+        debug_assert!(self.contains(c));
+        c.row * self.cols + c.col
+    }
+
+    /// The coordinate of the cell at index `i` of `cells`.
+    pub fn coord(&self, i: usize) -> Coord {
+        Coord::new(i / self.cols, i % self.cols)
+    }
+
+    pub fn get(&self, c: Coord) -> Option<&T> {
+        if self.contains(c) { Some(&self.cells[self.index_of(c)]) } else { None }
+    }
+
+    /// Every coordinate, row by row.
+    pub fn coords(&self) -> impl Iterator<Item = Coord> {
+        let cols = self.cols;
+        (0..self.rows * self.cols).map(move |i| Coord::new(i / cols, i % cols))
+    }
+
+    /// The cells next to `c` on the grid.
+    pub fn neighbors(&self, c: Coord, adjacency: Adjacency) -> impl Iterator<Item = Coord> + '_ {
+        adjacency.directions()
+            .iter()
+            .filter_map(move |d| c.step(*d, 1))
+            .filter(move |n| self.contains(*n))
+    }
+
+    /// The cells connected to `start` through neighbors that `within` accepts,
+    /// including `start` itself (if `within` accepts it), nearest first.
+    pub fn flood_fill(&self, start: Coord, adjacency: Adjacency, within: impl Fn(&T) -> bool) -> Vec<Coord> {
+        let mut seen = vec![false; self.cells.len()];
+        let mut region = Vec::new();
+        let mut queue = VecDeque::new();
+        if self.contains(start) && within(&self[start]) {
+            seen[self.index_of(start)] = true;
+            queue.push_back(start);
+        }
+        while let Some(c) = queue.pop_front() {
+            region.push(c);
+            for n in self.neighbors(c, adjacency) {
+                let i = self.index_of(n);
+                if !seen[i] && within(&self.cells[i]) {
+                    seen[i] = true;
+                    queue.push_back(n);
+                }
+            }
+        }
+        region
+    }
+}
+
+impl<T: PartialEq> Grid<T> {
+    /// Whether the cell at `c` is part of a line of at least `n` cells in a
+    /// row holding `p`, across, down or diagonally. Checking only the cell
+    /// just played is much cheaper than checking the whole board.
+    pub fn in_a_row_through(&self, c: Coord, n: usize, p: &T) -> bool {
+        let lines = lines::cached(&[self.rows, self.cols], n);
+        lines::has_line(&self.cells, lines.through(self.index_of(c)), p)
+    }
+
+    /// Whether `p` holds a line of at least `n` cells in a row anywhere.
+    pub fn in_a_row(&self, n: usize, p: &T) -> bool {
+        lines::has_line(&self.cells, &lines::cached(&[self.rows, self.cols], n).all, p)
+    }
+}
+
+impl<T> Grid<T> {
+    /// Draws the grid with `cell` giving the text for each cell, padding it to
+    /// the widest. With `labels`, columns are lettered along the top and rows
+    /// numbered down the left, as cells are named; with `ruled`, cells are
+    /// boxed in by lines.
+    pub fn render(&self, labels: bool, ruled: bool, cell: impl Fn(&T) -> String) -> String {
+        let texts: Vec<String> = self.cells.iter().map(cell).collect();
+        let width = texts.iter().map(|t| t.chars().count()).max().unwrap_or(1);
+        // Rules leave a space on either side of a cell.
+        let width = if ruled { width + 2 } else { width };
+        let margin = if labels { format!("{:>w$} ", "", w = self.rows.to_string().len()) } else { String::new() };
+        let gap = if ruled { "|" } else { " " };
+
+        let mut s = String::new();
+        if labels {
+            let letters: Vec<String> = (0..self.cols)
+                .map(|c| format!("{:^width$}", (b'a' + c as u8) as char, width = width))
+                .collect();
+            s.push_str(format!("{}{}", margin, letters.join(" ")).trim_end());
+            s.push('\n');
+        }
+        for r in 0..self.rows {
+            if ruled && r > 0 {
+                let rule = vec!["-".repeat(width); self.cols].join("|");
+                s.push_str(&format!("{}{}\n", margin, rule));
+            }
+            if labels {
+                s.push_str(&format!("{:>w$} ", r + 1, w = margin.len() - 1));
+            }
+            let row: Vec<String> = texts[r * self.cols..(r + 1) * self.cols]
+                .iter()
+                .map(|t| format!("{:^width$}", t, width = width))
+                .collect();
+            s.push_str(&row.join(gap));
+            s.push('\n');
+        }
+        s
+    }
+
+    /// The cells as text, one character each, row by row. With `compact`, a
+    /// run of three or more of the same character is written as its length
+    /// and the character, like "7-" for seven empty cells. The characters
+    /// should be URL-safe, and must not be digits.
+    pub fn encode(&self, compact: bool, symbol: impl Fn(&T) -> char) -> String {
+        let symbols: Vec<char> = self.cells.iter().map(symbol).collect();
+        if !compact {
+            return symbols.into_iter().collect();
+        }
+        let mut s = String::new();
+        let mut i = 0;
+        while i < symbols.len() {
+            let run = symbols[i..].iter().take_while(|c| **c == symbols[i]).count();
+            if run >= 3 {
+                s.push_str(&run.to_string());
+                s.push(symbols[i]);
+            } else {
+                s.extend(std::iter::repeat_n(symbols[i], run));
+            }
+            i += run;
+        }
+        s
+    }
+
+    /// Reads cells written by `encode`, compact or not, for a grid of the
+    /// given size. `symbol` reads one cell's character, or says what it
+    /// expected instead. Error positions are offsets into `input`.
+    pub fn decode(
+        rows: usize,
+        cols: usize,
+        input: &str,
+        symbol: impl Fn(char) -> Result<T, Cow<'static, str>>,
+    ) -> Result<Self, ParseError>
+    where
+        T: Clone,
+    {
+        let size = rows * cols;
+        let mut cells = Vec::with_capacity(size);
+        let mut run: Option<(usize, usize)> = None;
+        for (i, c) in input.chars().enumerate() {
+            if let Some(d) = c.to_digit(10) {
+                let (start, n) = run.unwrap_or((i, 0));
+                run = Some((start, n * 10 + d as usize));
+                continue;
+            }
+            let cell = symbol(c).map_err(|expected| ParseError::unexpected(i, expected))?;
+            let (start, n) = run.take().unwrap_or((i, 1));
+            if cells.len() + n > size {
+                return Err(ParseError::wrong_length(format!("{} cells", size)).at(start));
+            }
+            cells.extend(std::iter::repeat_n(cell, n));
+        }
+        if let Some((start, _)) = run {
+            return Err(ParseError::unexpected(start, "a cell after the length of a run"));
+        }
+        if cells.len() != size {
+            return Err(ParseError::wrong_length(format!("{} cells", size)).at(input.chars().count()));
+        }
+        Ok(Grid { rows, cols, cells })
+    }
+}
+
+impl<T> Index<Coord> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, c: Coord) -> &T {
+        &self.cells[self.index_of(c)]
+    }
+}
+
+impl<T> IndexMut<Coord> for Grid<T> {
+    fn index_mut(&mut self, c: Coord) -> &mut T {
+        let i = self.index_of(c);
+        &mut self.cells[i]
+    }
+}
+
+/// Cells can also be indexed by their place in `cells`.
+impl<T> Index<usize> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, i: usize) -> &T {
+        &self.cells[i]
+    }
+}
+
+impl<T> IndexMut<usize> for Grid<T> {
+    fn index_mut(&mut self, i: usize) -> &mut T {
+        &mut self.cells[i]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codec_round_trips() {
+        let mut grid = Grid::new(3, 4, '-');
+        grid[Coord::new(1, 2)] = 'X';
+        grid[11] = 'O';
+        let compact = grid.encode(true, |c| *c);
+        assert_eq!(compact, "6-X4-O");
+        assert_eq!(Grid::decode(3, 4, &compact, Ok).unwrap(), grid);
+        assert_eq!(Grid::decode(3, 4, &grid.encode(false, |c| *c), Ok).unwrap(), grid);
+
+        let err = Grid::decode(3, 4, "6-X6-", Ok).unwrap_err();
+        assert_eq!(err.position, Some(3));
+        let err = Grid::decode(3, 4, "6-X4", Ok).unwrap_err();
+        assert_eq!(err.position, Some(3));
+    }
+
+    #[test]
+    fn finds_lines_and_regions() {
+        let grid = Grid::decode(4, 4, concat!("X---", "-X-O", "OOX-", "O--X"), Ok).unwrap();
+        assert!(grid.in_a_row(4, &'X'));
+        assert!(grid.in_a_row_through(Coord::new(1, 1), 4, &'X'));
+        assert!(!grid.in_a_row(3, &'O'));
+        assert!(grid.in_a_row(2, &'O'));
+
+        let empty = grid.flood_fill(Coord::new(0, 1), Adjacency::Orthogonal, |c| *c == '-');
+        assert_eq!(empty.len(), 4);
+        assert_eq!(grid.flood_fill(Coord::new(0, 0), Adjacency::All, |c| *c == 'X').len(), 4);
+        assert_eq!(grid.neighbors(Coord::new(0, 0), Adjacency::Hex).count(), 2);
+    }
+}
@@ -0,0 +1,346 @@
+use crate::game_core::{Game, GameOptions, Move, MoveId, Outcome, ParseError, Player, Rng, VICTORY};
+use std::collections::VecDeque;
+
+/// Boards range from 2x2 to 19x19, so that columns can be named by letters.
+pub const MAX_SIZE: usize = 19;
+
+/// Hex on an `n` by `n` rhombus. Red ('R') moves first and tries to connect
+/// the top and bottom edges; Blue ('B') tries to connect the left and right
+/// edges. Cells are numbered row by row from 1, like the tictactoe board.
+///
+/// With the swap rule enabled, Blue's first move may instead be to take over
+/// Red's opening stone (the "pie rule"), which keeps Red from simply opening in
+/// the strongest cell.
+#[derive(Clone, Debug)]
+pub struct HexGame {
+    pub size: usize,
+    pub swap_rule: bool,
+    pub cells: Vec<char>,
+    pub player: Player,
+}
+
+impl Default for HexGame {
+    fn default() -> Self {
+        Self::new(11, true)
+    }
+}
+
+fn opponent(p: Player) -> Player {
+    if p == 'R' { 'B' } else { 'R' }
+}
+
+/// The six neighbors of a cell on the rhombus, as row and column offsets.
+const NEIGHBORS: [(i32, i32); 6] = [(-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0)];
+
+impl HexGame {
+    pub fn new(size: usize, swap_rule: bool) -> Self {
+        HexGame { size, swap_rule, cells: vec!['-'; size * size], player: 'R' }
+    }
+
+    /// The id of the swap move, just past the ids of the cells.
+    pub fn swap_id(&self) -> MoveId {
+        (self.size * self.size) as MoveId + 1
+    }
+
+    fn swap_available(&self) -> bool {
+        self.swap_rule
+            && self.player == 'B'
+            && self.cells.iter().filter(|c| **c != '-').count() == 1
+            && self.cells.contains(&'R')
+    }
+
+    /// The move that places a stone on the empty cell `i`.
+    fn placement(&self, i: usize) -> Move<Self> {
+        let mut next_state = self.clone();
+        next_state.cells[i] = self.player;
+        next_state.player = opponent(self.player);
+        let end_game = if next_state.connects(self.player) {
+            Some(Some(self.player).into_iter().collect())
+        } else {
+            None
+        };
+        Move { id: i as MoveId + 1, end_game, next_state }
+    }
+
+    /// The swap move, if Blue may make it.
+    fn swap(&self) -> Option<Move<Self>> {
+        if !self.swap_available() {
+            return None;
+        }
+        // Red's stone becomes Blue's, reflected across the long diagonal so
+        // that it relates to Blue's edges as it did to Red's.
+        let i = self.cells.iter().position(|c| *c == 'R').unwrap();
+        let (r, c) = (i / self.size, i % self.size);
+        let mut next_state = self.clone();
+        next_state.cells[i] = '-';
+        next_state.cells[c * self.size + r] = 'B';
+        next_state.player = 'R';
+        Some(Move { id: self.swap_id(), end_game: None, next_state })
+    }
+
+    fn neighbors(&self, i: usize) -> impl Iterator<Item = usize> {
+        let n = self.size as i32;
+        let (r, c) = ((i / self.size) as i32, (i % self.size) as i32);
+        NEIGHBORS.iter()
+            .map(move |(dr, dc)| (r + dr, c + dc))
+            .filter(move |(r, c)| (0..n).contains(r) && (0..n).contains(c))
+            .map(move |(r, c)| (r * n + c) as usize)
+    }
+
+    /// Whether cell `i` lies on the first (`far == false`) or last edge that
+    /// `p` is trying to connect.
+    fn on_edge(&self, i: usize, p: Player, far: bool) -> bool {
+        let line = if p == 'R' { i / self.size } else { i % self.size };
+        line == if far { self.size - 1 } else { 0 }
+    }
+
+    /// Whether `p`'s stones connect their two edges, found by flood-filling
+    /// from the stones on their first edge.
+    fn connects(&self, p: Player) -> bool {
+        let mut seen = vec![false; self.cells.len()];
+        let mut queue: VecDeque<usize> = (0..self.cells.len())
+            .filter(|i| self.cells[*i] == p && self.on_edge(*i, p, false))
+            .collect();
+        for i in &queue { seen[*i] = true; }
+        while let Some(i) = queue.pop_front() {
+            if self.on_edge(i, p, true) { return true; }
+            for j in self.neighbors(i) {
+                if !seen[j] && self.cells[j] == p {
+                    seen[j] = true;
+                    queue.push_back(j);
+                }
+            }
+        }
+        false
+    }
+
+    /// The fewest empty cells `p` must still fill to connect their edges, or
+    /// `None` if the opponent has cut them off. (A 0-1 breadth-first search:
+    /// `p`'s own stones cost nothing to cross, empty cells cost one.)
+    fn distance_to_connect(&self, p: Player) -> Option<usize> {
+        let cost = |i: usize| if self.cells[i] == p { 0 } else { 1 };
+        let mut dist = vec![usize::MAX; self.cells.len()];
+        let mut queue = VecDeque::new();
+        let starts = (0..self.cells.len())
+            .filter(|i| self.cells[*i] != opponent(p) && self.on_edge(*i, p, false));
+        for i in starts {
+            dist[i] = cost(i);
+            if cost(i) == 0 { queue.push_front(i) } else { queue.push_back(i) }
+        }
+        while let Some(i) = queue.pop_front() {
+            for j in self.neighbors(i) {
+                if self.cells[j] == opponent(p) { continue; }
+                let d = dist[i] + cost(j);
+                if d < dist[j] {
+                    dist[j] = d;
+                    if cost(j) == 0 { queue.push_front(j) } else { queue.push_back(j) }
+                }
+            }
+        }
+        (0..self.cells.len())
+            .filter(|i| self.on_edge(*i, p, true))
+            .map(|i| dist[i])
+            .min()
+            .filter(|d| *d != usize::MAX)
+    }
+
+    /// The coordinate name of cell `i`, e.g. "c4" for the third column of the
+    /// fourth row.
+    fn cell_name(&self, i: usize) -> String {
+        format!("{}{}", (b'a' + (i % self.size) as u8) as char, i / self.size + 1)
+    }
+}
+
+impl Game for HexGame {
+    const NAME: &'static str = "Hex";
+
+    fn current_player(&self) -> Player {
+        self.player
+    }
+
+    /// The serialized form is the board size in decimal, 's' or 'n' for
+    /// whether the swap rule is in use, the player to move, and then the cells
+    /// row by row, each 'R', 'B' or '-'.
+    fn unparse(&self) -> String {
+        let mut s = self.size.to_string();
+        s.push(if self.swap_rule { 's' } else { 'n' });
+        s.push(self.player);
+        s.extend(self.cells.iter());
+        s
+    }
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let digits = input.chars().take_while(|c| c.is_ascii_digit()).count();
+        let size: usize = match input[..digits].parse() {
+            Ok(n) if (2..=MAX_SIZE).contains(&n) => n,
+            _ => return Err(ParseError::unexpected(0, "a board size from 2 to 19")),
+        };
+        let mut rest = input[digits..].chars();
+        let swap_rule = match rest.next() {
+            Some('s') => true,
+            Some('n') => false,
+            _ => return Err(ParseError::unexpected(digits, "'s' (swap rule) or 'n' (no swap rule)")),
+        };
+        let player = match rest.next() {
+            Some(p @ 'R') | Some(p @ 'B') => p,
+            _ => return Err(ParseError::unexpected(digits + 1, "the player to move, R or B")),
+        };
+        let cells: Vec<char> = rest.collect();
+        if cells.len() != size * size {
+            let at = digits + 2 + cells.len().min(size * size);
+            return Err(ParseError::wrong_length(format!("{} cells for the board size", size * size)).at(at));
+        }
+        if let Some(i) = cells.iter().position(|c| !matches!(c, 'R' | 'B' | '-')) {
+            return Err(ParseError::unexpected(digits + 2 + i, "'R', 'B' or '-'"));
+        }
+        Ok(HexGame { size, swap_rule, cells, player })
+    }
+
+    /// `size` (from 2 to 19) and `swap` (whether the swap rule is in use).
+    fn with_options(mut options: GameOptions) -> Result<Self, ParseError> {
+        let size = options.number("size", 2..=MAX_SIZE)?.unwrap_or(11);
+        let swap_rule = options.flag("swap")?.unwrap_or(true);
+        options.finish(&["size", "swap"])?;
+        Ok(HexGame::new(size, swap_rule))
+    }
+
+    fn options(&self) -> String {
+        format!("size={},swap={}", self.size, if self.swap_rule { "yes" } else { "no" })
+    }
+
+    const LAZY_MOVES: bool = true;
+
+    fn moves(&self) -> Vec<Move<Self>> {
+        self.lazy_moves().collect()
+    }
+
+    fn lazy_moves(&self) -> Box<dyn Iterator<Item = Move<Self>> + '_> {
+        if self.outcome().is_over() {
+            return Box::new(std::iter::empty());
+        }
+        let placements = (0..self.cells.len())
+            .filter(move |i| self.cells[*i] == '-')
+            .map(move |i| self.placement(i));
+        Box::new(placements.chain(std::iter::once_with(move || self.swap()).flatten()))
+    }
+
+    /// Picks an empty cell (or the swap) without building the other moves.
+    fn random_move(&self, rng: &mut Rng) -> Option<Move<Self>> {
+        let empty = self.cells.iter().filter(|c| **c == '-').count();
+        let swap = self.swap_available() as usize;
+        if empty + swap == 0 || self.outcome().is_over() {
+            return None;
+        }
+        match rng.below(empty + swap) {
+            k if k == empty => self.swap(),
+            k => {
+                let i = self.cells.iter().enumerate().filter(|(_, c)| **c == '-').nth(k).unwrap().0;
+                Some(self.placement(i))
+            }
+        }
+    }
+
+    fn move_name(&self, id: MoveId) -> String {
+        if id == self.swap_id() {
+            "swap".to_string()
+        } else {
+            self.cell_name(id as usize - 1)
+        }
+    }
+
+    fn move_long_name(&self, id: MoveId) -> String {
+        if id == self.swap_id() {
+            "swap, taking over the opening stone".to_string()
+        } else {
+            format!("place on {}", self.move_name(id))
+        }
+    }
+
+    fn render_to_text(&self) -> String {
+        let mut s = String::from("   ");
+        for c in 0..self.size {
+            s.push(' ');
+            s.push((b'a' + c as u8) as char);
+        }
+        s.push_str("     (R: top-bottom, B: left-right)\n");
+        for r in 0..self.size {
+            s.push_str(&" ".repeat(r));
+            s.push_str(&format!("{:>2} ", r + 1));
+            for c in 0..self.size {
+                s.push(' ');
+                s.push(match self.cells[r * self.size + c] { '-' => '.', p => p });
+            }
+            s.push('\n');
+        }
+        s.push_str(&format!("{} to move\n", self.player));
+        s
+    }
+
+    /// Compares how many more stones each player needs to connect their edges.
+    fn value_for(&self, p: Player) -> i64 {
+        if self.connects(p) {
+            return VICTORY;
+        }
+        if self.connects(opponent(p)) {
+            return -VICTORY;
+        }
+        let most = self.cells.len() as i64;
+        let mine = self.distance_to_connect(p).map_or(most, |d| d as i64);
+        let theirs = self.distance_to_connect(opponent(p)).map_or(most, |d| d as i64);
+        theirs - mine
+    }
+
+    /// Hex cannot end in a draw: once the board is full, one player or the
+    /// other has connected their edges.
+    fn outcome(&self) -> Outcome {
+        match ['R', 'B'].iter().find(|p| self.connects(**p)) {
+            Some(p) => Outcome::win(*p),
+            None => Outcome::Ongoing,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_core::play;
+
+    #[test]
+    fn the_swap_takes_over_the_opening_stone_reflected() {
+        let g = HexGame::new(5, true);
+        let opened = play(&g, "b1").next_state;
+        let swapped = play(&opened, "swap");
+        assert_eq!(swapped.id, opened.swap_id());
+        // b1 (row 1, column b) becomes a2 (row 2, column a), now Blue's.
+        assert_eq!(swapped.next_state.cells[5], 'B');
+        assert_eq!(swapped.next_state.cells.iter().filter(|c| **c != '-').count(), 1);
+        assert_eq!(swapped.next_state.current_player(), 'R');
+
+        // Only as Blue's first move, and only with the swap rule.
+        let swap_id = opened.swap_id();
+        assert!(swapped.next_state.moves().iter().all(|m| m.id != swap_id));
+        let placed = play(&play(&opened, "c3").next_state, "d4").next_state;
+        assert!(placed.moves().iter().all(|m| m.id != swap_id));
+        let no_swap = play(&HexGame::new(5, false), "b1").next_state;
+        assert!(no_swap.moves().iter().all(|m| m.id != swap_id));
+    }
+
+    #[test]
+    fn stones_connect_along_the_short_diagonal_only() {
+        // a3, b2 and c1 touch one another, and join Red's top and bottom.
+        let g = HexGame::parse("3nBB-R-RBR--").unwrap();
+        assert_eq!(g.outcome(), Outcome::win('R'));
+        assert_eq!(g.value_for('B'), -VICTORY);
+        // a1, b2 and c3 do not.
+        let g = HexGame::parse("3nRR-B-R-B-R").unwrap();
+        assert_eq!(g.outcome(), Outcome::Ongoing);
+    }
+
+    #[test]
+    fn completing_a_connection_ends_the_game() {
+        let g = HexGame::parse("3nRRB-RB----").unwrap();
+        let m = play(&g, "a3");
+        assert_eq!(m.end_game, Some(Some('R').into_iter().collect()));
+        assert!(m.next_state.moves().is_empty());
+    }
+}
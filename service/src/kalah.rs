@@ -0,0 +1,295 @@
+use crate::game_core::{Game, GameOptions, Move, MoveId, Outcome, ParseError, Player, VICTORY};
+use smallvec::SmallVec;
+
+/// The pits of a Kalah board, counter-clockwise: South's six pits (left to
+/// right), South's store, North's six pits (right to left from South's point
+/// of view), then North's store.
+pub type KalahBoard = [u8; 14];
+
+const PITS: usize = 6;
+const SEEDS: u8 = 4;
+/// With more seeds to a pit, a store could hold more than a digit can say.
+const MAX_SEEDS: u8 = 5;
+const SOUTH_STORE: usize = PITS;
+const NORTH_STORE: usize = 2 * PITS + 1;
+
+/// Seed counts are serialized as single base-62 digits; Kalah(6,5) has only
+/// 60 seeds, so any pit's count fits.
+const DIGITS: &str = "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Kalah(6,4): six pits per side, four seeds per pit. South ('S') moves first.
+/// As options, there can be from one to five seeds per pit (`seeds`), and
+/// North can move first (`first`).
+#[derive(Clone, Debug)]
+pub struct KalahGame {
+    pub board: KalahBoard,
+    pub player: Player,
+    /// Who moved first, which only the options need.
+    pub first: Player,
+}
+
+impl Default for KalahGame {
+    fn default() -> Self {
+        Self::new(SEEDS, 'S')
+    }
+}
+
+fn opponent(p: Player) -> Player {
+    if p == 'S' { 'N' } else { 'S' }
+}
+
+/// The index of the first pit of `p`'s row, and of `p`'s store.
+fn row_and_store(p: Player) -> (usize, usize) {
+    if p == 'S' { (0, SOUTH_STORE) } else { (SOUTH_STORE + 1, NORTH_STORE) }
+}
+
+impl Game for KalahGame {
+    const NAME: &'static str = "Kalah";
+
+    fn current_player(&self) -> Player {
+        self.player
+    }
+
+    /// The serialized form is the player to move ('S' or 'N') followed by the
+    /// seed count of each of the 14 pits, in the order of `KalahBoard`, as
+    /// base-62 digits, and then "-N" if North moved first.
+    fn unparse(&self) -> String {
+        let digits: Vec<char> = DIGITS.chars().collect();
+        let mut s: String = Some(self.player).into_iter()
+            .chain(self.board.iter().map(|n| digits[*n as usize]))
+            .collect();
+        if self.first == 'N' {
+            s.push_str("-N");
+        }
+        s
+    }
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let (input, first) = match input.strip_suffix("-N") {
+            Some(input) => (input, 'N'),
+            None => (input, 'S'),
+        };
+        let len = input.chars().count();
+        if len != 15 { return Err(ParseError::wrong_length("the player to move and 14 pits").at(len.min(15))); }
+        let mut chars = input.chars();
+        let player = match chars.next() {
+            Some(p @ 'S') | Some(p @ 'N') => p,
+            _ => return Err(ParseError::unexpected(0, "the player to move, S or N")),
+        };
+        let mut board = [0; 14];
+        for (i, c) in chars.enumerate() {
+            match DIGITS.find(c) {
+                Some(n) => board[i] = n as u8,
+                None => return Err(ParseError::unexpected(i + 1, "a seed count as a base-62 digit")),
+            }
+        }
+        let total: u32 = board.iter().map(|n| *n as u32).sum();
+        if !total.is_multiple_of(2 * PITS as u32) || !(1..=MAX_SEEDS as u32).contains(&(total / (2 * PITS as u32))) {
+            return Err(ParseError::invalid("from one to five seeds for each pit on the board, like 48 for four"));
+        }
+        Ok(KalahGame { board, player, first })
+    }
+
+    /// `seeds` (how many each pit starts with) and `first` (S or N).
+    fn with_options(mut options: GameOptions) -> Result<Self, ParseError> {
+        let seeds = options.number("seeds", 1..=MAX_SEEDS as usize)?.unwrap_or(SEEDS as usize);
+        let first = options.player("first", &['S', 'N'])?.unwrap_or('S');
+        options.finish(&["seeds", "first"])?;
+        Ok(KalahGame::new(seeds as u8, first))
+    }
+
+    fn options(&self) -> String {
+        let total: usize = self.board.iter().map(|n| *n as usize).sum();
+        format!("seeds={},first={}", total / (2 * PITS), self.first)
+    }
+
+    /// Move `i` sows the seeds of the mover's `i`th pit, counting from 1 at
+    /// the mover's left.
+    fn moves(&self) -> Vec<Move<Self>> {
+        if self.is_over() {
+            return Vec::new();
+        }
+        let (row, _) = row_and_store(self.player);
+        (1..=PITS)
+            .filter(|i| self.board[row + i - 1] > 0)
+            .map(|i| self.sow(i as MoveId))
+            .collect()
+    }
+
+    fn move_long_name(&self, id: MoveId) -> String {
+        let (row, _) = row_and_store(self.player);
+        format!("sow the {} seeds of pit {}", self.board[row + id as usize - 1], id)
+    }
+
+    fn render_to_text(&self) -> String {
+        let pit = |i: usize| format!("[{:>2}]", self.board[i]);
+        let mut s = String::from("       6   5   4   3   2   1    (N)\n     ");
+        for i in (SOUTH_STORE + 1..NORTH_STORE).rev() {
+            s.push_str(&pit(i));
+        }
+        s.push_str(&format!("\n {:>2}  {:24} {:>2}\n     ",
+                            self.board[NORTH_STORE], "", self.board[SOUTH_STORE]));
+        for i in 0..SOUTH_STORE {
+            s.push_str(&pit(i));
+        }
+        s.push_str("\n       1   2   3   4   5   6    (S)\n");
+        s.push_str(&format!("{} to move\n", self.player));
+        s
+    }
+
+    /// The difference between the two stores; once the game is over, a
+    /// decisive victory or loss.
+    fn value_for(&self, p: Player) -> i64 {
+        let (_, mine) = row_and_store(p);
+        let (_, theirs) = row_and_store(opponent(p));
+        if self.is_over() {
+            let board = self.banked();
+            (board[mine] as i64 - board[theirs] as i64).signum() * VICTORY
+        } else {
+            self.board[mine] as i64 - self.board[theirs] as i64
+        }
+    }
+
+    fn outcome(&self) -> Outcome {
+        if self.is_over() {
+            Outcome::from_end_game(Some(victors(&self.banked())))
+        } else {
+            Outcome::Ongoing
+        }
+    }
+}
+
+impl KalahGame {
+    /// A board with `seeds` in each pit, and `first` to move.
+    pub fn new(seeds: u8, first: Player) -> Self {
+        let mut board = [seeds; 14];
+        board[SOUTH_STORE] = 0;
+        board[NORTH_STORE] = 0;
+        KalahGame { board, player: first, first }
+    }
+
+    /// Whether either row has run out of seeds, which ends the game.
+    fn is_over(&self) -> bool {
+        self.board[..SOUTH_STORE].iter().all(|n| *n == 0)
+            || self.board[SOUTH_STORE + 1..NORTH_STORE].iter().all(|n| *n == 0)
+    }
+
+    /// The board once each player has banked whatever remains in their own
+    /// row, as they do when the game ends.
+    fn banked(&self) -> KalahBoard {
+        let mut board = self.board;
+        for p in &['S', 'N'] {
+            let (row, store) = row_and_store(*p);
+            let remaining: u8 = board[row..row + PITS].iter().sum();
+            board[row..row + PITS].iter_mut().for_each(|n| *n = 0);
+            board[store] += remaining;
+        }
+        board
+    }
+
+    /// Sows the seeds from the mover's `id`th pit counter-clockwise, skipping
+    /// the opponent's store. A last seed landing in the mover's store earns
+    /// another turn; one landing in an empty pit of the mover's own row
+    /// captures the seeds opposite it. When either row runs out of seeds, each
+    /// player banks whatever remains in their own row and the game ends.
+    fn sow(&self, id: MoveId) -> Move<Self> {
+        let (row, store) = row_and_store(self.player);
+        let (_, opponent_store) = row_and_store(opponent(self.player));
+        let mut board = self.board;
+        let mut i = row + id as usize - 1;
+        let mut seeds = board[i];
+        board[i] = 0;
+        while seeds > 0 {
+            i = (i + 1) % board.len();
+            if i == opponent_store { continue; }
+            board[i] += 1;
+            seeds -= 1;
+        }
+
+        if (row..row + PITS).contains(&i) && board[i] == 1 {
+            let opposite = 2 * PITS - i;
+            if board[opposite] > 0 {
+                board[store] += board[opposite] + 1;
+                board[i] = 0;
+                board[opposite] = 0;
+            }
+        }
+
+        let player = if i == store { self.player } else { opponent(self.player) };
+        let mut next_state = KalahGame { board, player, first: self.first };
+        let end_game = if next_state.is_over() {
+            next_state.board = next_state.banked();
+            Some(victors(&next_state.board))
+        } else {
+            None
+        };
+
+        Move { id, end_game, next_state }
+    }
+}
+
+/// The player with more seeds in their store wins; a tie has no victors.
+fn victors(board: &KalahBoard) -> SmallVec<[Player; 1]> {
+    if board[SOUTH_STORE] > board[NORTH_STORE] {
+        Some('S').into_iter().collect()
+    } else if board[NORTH_STORE] > board[SOUTH_STORE] {
+        Some('N').into_iter().collect()
+    } else {
+        SmallVec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_core::play;
+
+    #[test]
+    fn ending_in_the_store_earns_another_turn() {
+        let m = play(&KalahGame::default(), "3");
+        assert_eq!(m.next_state.unparse(), "S44055514444440");
+        assert_eq!(m.next_state.current_player(), 'S');
+        let m = play(&KalahGame::default(), "4");
+        assert_eq!(m.next_state.current_player(), 'N');
+    }
+
+    #[test]
+    fn ending_in_an_empty_pit_captures_the_seeds_opposite() {
+        let mut board = [0; 14];
+        board[0] = 1;
+        board[5] = 2;
+        board[7] = 2;
+        board[11] = 3;
+        let g = KalahGame { board, player: 'S', first: 'S' };
+        let m = play(&g, "1");
+        assert_eq!(m.next_state.board, [0, 0, 0, 0, 0, 2, 4, 2, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(m.next_state.current_player(), 'N');
+        assert_eq!(m.end_game, None);
+    }
+
+    #[test]
+    fn an_empty_row_ends_the_game_and_each_player_banks_their_own() {
+        let mut board = [0; 14];
+        board[5] = 1;
+        board[SOUTH_STORE] = 20;
+        board[7] = 3;
+        board[NORTH_STORE] = 24;
+        let g = KalahGame { board, player: 'S', first: 'S' };
+        let m = play(&g, "6");
+        assert_eq!(m.end_game, Some(Some('N').into_iter().collect()));
+        assert_eq!(m.next_state.board[SOUTH_STORE], 21);
+        assert_eq!(m.next_state.board[NORTH_STORE], 27);
+        assert_eq!(m.next_state.outcome(), Outcome::win('N'));
+        assert!(m.next_state.moves().is_empty());
+    }
+
+    #[test]
+    fn the_state_remembers_who_moved_first() {
+        let g = KalahGame::with_options(GameOptions::parse("seeds=3,first=N").unwrap()).unwrap();
+        assert_eq!(g.unparse(), "N33333303333330-N");
+        let after = &g.moves()[0].next_state;
+        let parsed = KalahGame::parse(&after.unparse()).unwrap();
+        assert_eq!(parsed.options(), "seeds=3,first=N");
+        assert_eq!(KalahGame::default().options(), "seeds=4,first=S");
+    }
+}
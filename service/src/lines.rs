@@ -0,0 +1,121 @@
+//! Finding N in a row, for games won by lining up pieces (like tictactoe,
+//! Pentago or Qubic).
+//!
+//! A board is a grid of any number of dimensions, stored as a flat slice with
+//! the last coordinate varying fastest (so a 2-dimensional board is stored
+//! row by row).
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Every run of `n` cells in a straight line (along an axis or any diagonal)
+/// on a board with the given dimensions, as indexes into the flat board.
+///
+/// For example, `lines(&[3, 3], 3)` gives the 8 lines of tictactoe and
+/// `lines(&[4, 4, 4], 4)` the 76 lines of Qubic.
+pub fn lines(dims: &[usize], n: usize) -> Vec<Vec<usize>> {
+    let cells: usize = dims.iter().product();
+    let coords = |mut i: usize| -> Vec<isize> {
+        let mut c = vec![0; dims.len()];
+        for (k, d) in dims.iter().enumerate().rev() {
+            c[k] = (i % d) as isize;
+            i /= d;
+        }
+        c
+    };
+    let index = |c: &[isize]| -> Option<usize> {
+        c.iter().zip(dims).try_fold(0, |i, (x, d)| {
+            if *x >= 0 && (*x as usize) < *d { Some(i * d + *x as usize) } else { None }
+        })
+    };
+
+    // Each direction is a step of -1, 0 or 1 along every axis. A direction
+    // and its reverse give the same lines, so only those whose first nonzero
+    // step is 1 are kept.
+    let directions: Vec<Vec<isize>> = (0..3usize.pow(dims.len() as u32))
+        .map(|mut k| {
+            let mut step = vec![0; dims.len()];
+            for s in step.iter_mut().rev() {
+                *s = (k % 3) as isize - 1;
+                k /= 3;
+            }
+            step
+        })
+        .filter(|step| step.iter().find(|s| **s != 0) == Some(&1))
+        .collect();
+
+    let mut found = Vec::new();
+    for start in 0..cells {
+        let start = coords(start);
+        for step in &directions {
+            let line: Option<Vec<usize>> = (0..n as isize)
+                .map(|t| {
+                    let c: Vec<isize> = start.iter().zip(step).map(|(x, s)| x + t * s).collect();
+                    index(&c)
+                })
+                .collect();
+            if let Some(line) = line {
+                found.push(line);
+            }
+        }
+    }
+    found
+}
+
+/// The lines of a board, and which of them pass through each cell: a move
+/// can only complete a line through the cell it was played in.
+pub struct Lines {
+    pub all: Vec<Vec<usize>>,
+    through: Vec<Vec<Vec<usize>>>,
+}
+
+impl Lines {
+    /// The lines of `n` cells on a board with the given dimensions, as
+    /// `lines` finds them.
+    pub fn new(dims: &[usize], n: usize) -> Self {
+        let all = lines(dims, n);
+        let through = (0..dims.iter().product())
+            .map(|i| all.iter().filter(|line| line.contains(&i)).cloned().collect())
+            .collect();
+        Lines { all, through }
+    }
+
+    /// The lines through cell `i`.
+    pub fn through(&self, i: usize) -> &[Vec<usize>] {
+        &self.through[i]
+    }
+}
+
+/// `Lines` already worked out, by the board's dimensions and the line length.
+type LinesCache = Mutex<HashMap<(Vec<usize>, usize), &'static Lines>>;
+
+/// The `Lines` of `n` cells on a board with the given dimensions, worked out
+/// the first time they are asked for and kept from then on, for games whose
+/// board and line length are options.
+pub fn cached(dims: &[usize], n: usize) -> &'static Lines {
+    static CACHE: OnceLock<LinesCache> = OnceLock::new();
+    let mut cache = CACHE.get_or_init(Default::default).lock().expect("no thread panics holding the lines");
+    cache.entry((dims.to_vec(), n)).or_insert_with(|| Box::leak(Box::new(Lines::new(dims, n))))
+}
+
+/// Whether `p` fills any of `lines` on `board`.
+pub fn has_line<T: PartialEq>(board: &[T], lines: &[Vec<usize>], p: &T) -> bool {
+    lines.iter().any(|line| line.iter().all(|i| board[*i] == *p))
+}
+
+/// For each line that `p` could still complete (one holding none of anyone
+/// else's pieces), how many of its cells `p` already holds.
+pub fn open_lines<'a, T: PartialEq>(
+    board: &'a [T],
+    lines: &'a [Vec<usize>],
+    p: &'a T,
+    empty: &'a T,
+) -> impl Iterator<Item = usize> + 'a {
+    lines.iter().filter_map(move |line| {
+        if line.iter().all(|i| board[*i] == *p || board[*i] == *empty) {
+            Some(line.iter().filter(|i| board[**i] == *p).count())
+        } else {
+            None
+        }
+    })
+}
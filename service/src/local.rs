@@ -3,26 +3,77 @@ use tracing::debug;
 use std::io::{self, BufRead, Write};
 use std::convert::TryInto;
 
-use crate::game_core::{Command, Game, Move};
+use crate::dyn_game::{DynGame, DynMove, GameType};
+use crate::game_core::{Command, Outcome, Player, Rng, CHANCE};
 
+mod backgammon;
+mod battleship;
+mod bench;
+mod cfr;
+mod chinese_checkers;
+mod dots_and_boxes;
+mod dyn_game;
 mod game_core;
+mod go;
+mod grid;
+mod hex;
+mod kalah;
+mod lines;
+mod nim;
+mod nine_mens_morris;
+mod othello;
+mod pentago;
+mod perft;
+mod poker;
+mod quarto;
+mod qubic;
+mod registry;
+mod sliding_puzzle;
+mod solver;
+mod sprague_grundy;
 mod tictactoe;
-
-type TheGame = crate::tictactoe::TicTacToeGame;
+mod train;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt::init();
 
+    // The game to play can be named as the first argument; tictactoe is the
+    // default.
+    let name = std::env::args().nth(1).unwrap_or_else(|| registry::default_game().name.to_string());
+    if name == "train" { return train::run(std::env::args().skip(2)); }
+    if name == "bench" { return bench::run(std::env::args().skip(2)); }
+    if name == "perft" { return perft::run(std::env::args().skip(2)); }
+    match registry::find(&name) {
+        Some(game_type) => repl(game_type).await,
+        None => Err(format!("unknown game `{}`; the games are {}", name, registry::names().join(", ")).into()),
+    }
+}
+
+/// The game state as the REPL shows it to `viewer`: in games with hidden
+/// information, only what `viewer` can see.
+fn shown(game: &dyn DynGame, viewer: Player) -> String {
+    if game.hidden_information() { game.observation(viewer) } else { game.unparse() }
+}
+
+async fn repl(game_type: &'static GameType) -> Result<(), Box<dyn std::error::Error>> {
     let stdin = io::stdin();
     let mut lines = stdin.lock().lines();
 
-    let mut game: TheGame = Default::default();
+    let mut rng = Rng::from_entropy();
+    let mut game = settle_chance(game_type, game_type.new_game_with("")?, &mut rng);
 
-    println!("{}", TheGame::NAME);
-    println!("{}", game.render_to_text());
+    // In games with hidden information, the REPL only ever shows the game to
+    // the player who moves first; the AI (`s`) plays the other side.
+    let viewer = game.current_player();
+
+    println!("{}", game_type.name);
+    if game_type.hidden_information {
+        println!("you are playing {}", viewer);
+    }
+    println!("{}", game.render_for(viewer));
     let prompt = || {
-        println!("next command: [n, l, r, s] (with optional /<game>)");
+        println!("next command: [n, l, p, r, s, a] (with optional /<game>, or n/<options>)");
         print!("? ");
         std::io::stdout().flush().unwrap();
     };
@@ -42,7 +93,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let (cmd, slash_state) = line.split_at(1);
         if slash_state == "" {
             // no overriding state provided; reuse the current game.
-            println!("game: {:?}", game.unparse());
+            println!("game: {:?}", shown(&*game, viewer));
         } else {
             let (slash, state) = slash_state.split_at(1);
             if slash != "/" {
@@ -51,10 +102,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 continue;
             }
 
-            game = match TheGame::parse(state) {
+            // `n/<options>` starts a game of the variant the options pick;
+            // the other commands take a game.
+            let (what, parsed) = if cmd == "n" {
+                ("create game", game_type.new_game_with(state))
+            } else {
+                ("parse game", game_type.parse(state))
+            };
+            game = match parsed {
                 Ok(game) => game,
-                Err(msg) => {
-                    println!("failed to parse game due to {}", msg);
+                Err(err) => {
+                    println!("failed to {}: {}", what, err);
+                    // Point out where the trouble is.
+                    if let Some(position) = err.position {
+                        println!("  {}", state);
+                        println!("  {}^", " ".repeat(position));
+                    }
                     println!("provide either <C> or <C>/<game> for command");
                     prompt();
                     continue;
@@ -72,39 +135,58 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         };
 
-        let unparsed = game.unparse();
+        let unparsed = shown(&*game, viewer);
         debug!("c: {:?} unparsed: {:?}", c, unparsed);
 
         match c {
             Command::NewGame => {
-                game = Default::default();
-                println!("new-game: {:?}", game.unparse());
+                if slash_state.is_empty() {
+                    game = same_variant(game_type, &*game);
+                }
+                game = settle_chance(game_type, game, &mut rng);
+                println!("new-game: {:?}", shown(&*game, viewer));
+                if !game.options().is_empty() {
+                    println!("options: {}", game.options());
+                }
             }
             Command::List => {
-                let moves = game.moves();
+                let visible = game.moves_for(viewer);
+                let moves = game.moves().into_iter()
+                    .filter(|m| !game.hidden_information() || visible.contains(&m.id))
+                    .collect::<Vec<_>>();
+                if moves.is_empty() {
+                    match game.outcome() {
+                        Outcome::Ongoing => println!("there are no moves for you to choose from"),
+                        outcome => println!("there are no moves: the game is over, and {}", outcome),
+                    }
+                    prompt();
+                    continue;
+                }
+                // Where there is hidden information, a move's outcome (say,
+                // whether a shot hits) must stay hidden until it is made.
                 let moves_unparsed = moves.iter()
-                    .map(|m|(m.id, m.next_state.unparse()))
+                    .map(|m| if game.hidden_information() {
+                        (game.move_name(m.id), String::new())
+                    } else {
+                        (game.move_name(m.id), shown(&*m.next_state, viewer))
+                    })
                     .collect::<Vec<_>>();
 
-                let chosen_move: &Move<TheGame>;
+                let chosen_move: &DynMove;
                 'choose: loop {
                     println!("list {:?} : {:?}", unparsed, moves_unparsed);
 
                     println!("choose a move from list above");
                     println!("(you will see preview of it before you commit to it.)");
-                    let (num, m) = if let Some(Ok(line)) = lines.next() {
-                        let num: u32 = match line.parse() {
-                            Ok(num) => num,
-                            Err(msg) => {
-                                println!("{} is not a number, due to {}", line, msg);
-                                println!("Please try again.");
-                                continue 'choose;
-                            }
-                        };
-                        match moves.iter().filter(|m| m.id == num).next() {
-                            Some(m) => (num, m),
+                    let m = if let Some(Ok(line)) = lines.next() {
+                        // moves can be chosen by their number or any of
+                        // their notations.
+                        let line = line.trim();
+                        let found = game.find_move(line).and_then(|id| moves.iter().find(|m| m.id == id));
+                        match found {
+                            Some(m) => m,
                             None => {
-                                println!("The number {} is not in the list", num);
+                                println!("{} is not in the list", line);
                                 println!("Please try again.");
                                 continue 'choose;
                             }
@@ -114,9 +196,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     };
 
                     'confirm: loop {
-                        println!("Move {} yields\n{}",
-                                 num,
-                                 m.next_state.render_to_text());
+                        if game.hidden_information() {
+                            println!("{} (its outcome stays hidden until you make it)",
+                                     game.notation(m.id).descriptive);
+                        } else {
+                            println!("{} yields\n{}",
+                                     game.notation(m.id).descriptive,
+                                     m.next_state.render_to_text());
+                        }
                         println!("Is this what you want (Y/n)?");
                         if let Some(Ok(line)) = lines.next() {
                             match &line.to_lowercase()[..] {
@@ -135,18 +222,71 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     }
                 }
 
-                game = end_game_check(chosen_move);
+                game = end_game_check(game_type, chosen_move, game.current_player());
+                game = settle_chance(game_type, game, &mut rng);
+            }
+            Command::Play => {
+                // Like `l`, without the list or the preview: the move named
+                // is made at once.
+                println!("which move?");
+                let line = match lines.next() {
+                    Some(Ok(line)) => line,
+                    _ => break,
+                };
+                let visible = game.moves_for(viewer);
+                let chosen = game.find_move(&line)
+                    .filter(|id| !game.hidden_information() || visible.contains(id))
+                    .and_then(|id| game.moves().into_iter().find(|m| m.id == id));
+                match chosen {
+                    Some(m) => {
+                        println!("{}", game.notation(m.id).descriptive);
+                        game = end_game_check(game_type, &m, game.current_player());
+                        game = settle_chance(game_type, game, &mut rng);
+                        println!("{}", game.render_for(viewer));
+                    }
+                    None => println!("{} is not one of the moves; try `l` to list them", line.trim()),
+                }
             }
             Command::RenderToText => {
-                println!("render {:?} :\n{}", unparsed, game.render_to_text());
+                println!("render {:?} :\n{}", unparsed, game.render_for(viewer));
+                if game.outcome().is_over() {
+                    match game.points() {
+                        points if points > 1 => println!("game over: {}, for {} points", game.outcome(), points),
+                        _ => println!("game over: {}", game.outcome()),
+                    }
+                }
             }
             Command::Select => {
-                let next_moves = game.moves();
-                let choice = game_core::search(&next_moves, game.current_player()).await;
-                println!("select {:?} : {:?}", unparsed, choice);
-                println!("AI chose\n{}", choice.next_state.render_to_text());
+                if game.outcome().is_over() {
+                    println!("the game is over, and {}; try `n` for a new game", game.outcome());
+                    prompt();
+                    continue;
+                }
+                let choice = game.select().await;
+                println!("select {:?} : {} -> {:?}",
+                         unparsed, game.move_name(choice.id), shown(&*choice.next_state, viewer));
+                println!("AI chose: {}\n{}", game.notation(choice.id).descriptive, choice.next_state.render_for(viewer));
 
-                game = end_game_check(choice);
+                game = end_game_check(game_type, &choice, game.current_player());
+                game = settle_chance(game_type, game, &mut rng);
+            }
+            Command::Solve => {
+                if !game.puzzle() {
+                    println!("only puzzles can be solved; try `s` to have the AI choose a move");
+                    prompt();
+                    continue;
+                }
+                match game.solution() {
+                    Ok(Some(solution)) => {
+                        let names = solution
+                            .iter()
+                            .map(|(state, m)| state.move_name(m.id))
+                            .collect::<Vec<_>>();
+                        println!("solve {:?} : {} moves {:?}", unparsed, names.len(), names);
+                    }
+                    Ok(None) => println!("solve {:?} : there is no solution", unparsed),
+                    Err(_) => println!("solve {:?} : too hard; the solver gave up", unparsed),
+                }
             }
         }
 
@@ -155,12 +295,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn end_game_check<B: Game>(the_move: &Move<B>) -> B {
-    if let Some(victors) = &the_move.end_game {
-        println!("game over! Victory goes to {:?}", victors);
+/// Makes the moves that are left to chance, like rolling the dice, so that the
+/// REPL always leaves a player with a decision to make.
+fn settle_chance(game_type: &GameType, mut game: Box<dyn DynGame>, rng: &mut Rng) -> Box<dyn DynGame> {
+    while game.current_player() == CHANCE {
+        if game.moves().is_empty() { break; }
+        let m = game.sample_chance(rng);
+        println!("chance: {}", game.move_name(m.id));
+        game = if m.end_game.is_some() { end_game_check(game_type, &m, CHANCE) } else { m.next_state };
+    }
+    game
+}
+
+fn end_game_check(game_type: &GameType, the_move: &DynMove, mover: Player) -> Box<dyn DynGame> {
+    if the_move.end_game.is_some() {
+        println!("game over: {}", Outcome::from_end_game(the_move.end_game.clone()));
         println!("starting new game.");
-        Default::default()
+        same_variant(game_type, &*the_move.next_state)
     } else {
+        // Some games (e.g. Kalah) let a player move several times in a row.
+        if the_move.next_state.current_player() == mover {
+            println!("{} moves again.", mover);
+        }
         the_move.next_state.clone()
     }
 }
+
+/// A new game of the same variant as `game`.
+fn same_variant(game_type: &GameType, game: &dyn DynGame) -> Box<dyn DynGame> {
+    game_type.new_game_with(&game.options()).expect("a game's own options are valid")
+}
@@ -3,9 +3,9 @@ use serde::{Deserialize, Serialize, Serializer};
 use serde::ser::Error as _;
 
 use std::convert::TryInto;
-use std::default::Default;
 
-use crate::game_core::{Game, Command};
+use crate::dyn_game::{DynGame, DynMove};
+use crate::game_core::{Command, Outcome, ParseError, Player};
 
 pub type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
 
@@ -25,7 +25,7 @@ struct Request {
 // #[allow(non_snake_case)]
 #[derive(Serialize)]
 struct Response {
-    body: DoublyEncode<ResponseBody>,
+    body: DoublyEncode<Body>,
     // This should be a u32, but API Gateway actually expects a String that looking like an int for some reason.
     #[serde(rename="statusCode")]
     status_code: String,
@@ -42,26 +42,165 @@ impl<T:Serialize> Serialize for DoublyEncode<T> {
     }
 }
 
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Body {
+    Game(Box<ResponseBody>),
+    Error(ErrorBody),
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: ErrorDescription,
+}
+
+#[derive(Serialize)]
+struct ErrorDescription {
+    // Machine-readable: "wrong_length", "unexpected_character",
+    // "invalid_state", "unknown_option" or "invalid_option" for input that
+    // does not parse, "game_over" for a command that needs a game still in
+    // play, "unknown_move" for a move to play that is not to be had,
+    // "not_a_puzzle" for solving a game that is not a puzzle, or "too_hard"
+    // for a puzzle the solver gave up on.
+    code: String,
+    message: String,
+    // The index of the offending character in the game state (or the
+    // options), if there is one.
+    position: Option<usize>,
+    expected: String,
+}
+
+impl Response {
+    /// A request that is the client's mistake: a 400.
+    fn bad_request(error: ErrorDescription) -> Self {
+        Response {
+            body: DoublyEncode(Body::Error(ErrorBody { error })),
+            status_code: String::from("400"),
+        }
+    }
+}
+
+impl From<ParseError> for Response {
+    /// A game state (or options) that does not parse is the client's mistake.
+    fn from(err: ParseError) -> Self {
+        Response::bad_request(ErrorDescription {
+            code: err.kind.code().to_string(),
+            message: err.to_string(),
+            position: err.position,
+            expected: err.expected.to_string(),
+        })
+    }
+}
+
 #[derive(Serialize)]
 struct ResponseBody {
     // request: String,
     // ctx: String,
+    game: String,
     command: String,
     parsed_game_state: String,
     player: String,
     next_game_states: Option<Vec<MoveDescription>>,
+    // The move chosen by `select` (or made by `play`), as its id and the
+    // state it leads to. This pair is what clients have always been sent; the
+    // same move is described in full by `selected_move_description`.
     selected_move: Option<(String, String)>,
+    selected_move_description: Option<MoveDescription>,
     text: Option<String>,
     victory: Option<Vec<String>>,
+    // Only for games with hidden information, whose states are sealed: what
+    // the player the state was sealed for can see.
+    observation: Option<String>,
+    // Only for puzzles, with the solve command: the shortest way to solve
+    // the puzzle, one move after the other (or nothing, if it cannot be
+    // solved).
+    solution: Option<Vec<MoveDescription>>,
+    // Whether the game in `parsed_game_state` is over, and how it ended.
+    outcome: OutcomeDescription,
+    // The options the game was created with, including the defaults, like
+    // "size=11,swap=yes"; empty for games without options.
+    options: String,
+}
+
+#[derive(Serialize)]
+struct OutcomeDescription {
+    // "ongoing", "win" or "draw".
+    result: String,
+    // The winners, if `result` is "win".
+    victors: Vec<String>,
+    // What the win is worth to the winners: 1, or more in games that score
+    // some wins higher (like 2 for a gammon in Backgammon); 0 unless `result`
+    // is "win".
+    points: u32,
+}
+
+impl OutcomeDescription {
+    fn of(game: &dyn DynGame) -> Self {
+        let (result, victors) = match game.outcome() {
+            Outcome::Ongoing => ("ongoing", Vec::new()),
+            Outcome::Win(victors) => ("win", victors.iter().map(|p| p.to_string()).collect()),
+            Outcome::Draw => ("draw", Vec::new()),
+        };
+        OutcomeDescription { result: result.to_string(), victors, points: game.points() }
+    }
 }
 
 #[derive(Serialize)]
 struct MoveDescription {
     move_id: String,
-    next_board: String,
+    // The move's short notation, like "c4"; a client may offer it, or
+    // `move_long_name`, as well as the id.
+    move_name: String,
+    move_long_name: String,
+    // Who does what, like "R: place on c4".
+    move_description: String,
+    // Left out of `list` in games with hidden information, where it would
+    // give away what the move uncovers (like whether a shot hits); the play
+    // command tells it once the move is made.
+    next_board: Option<String>,
+    // Not necessarily the other player: some games grant extra turns.
     next_player: String,
 }
 
+impl MoveDescription {
+    /// A move that has been made (or chosen, by `select`).
+    fn new(game: &dyn DynGame, m: &DynMove, viewer: Player) -> Result<Self, Error> {
+        Ok(MoveDescription::describing(game, m, Some(state_for(&*m.next_state, viewer)?)))
+    }
+
+    /// A move for `list` to offer, which does not say where it leads in
+    /// games with hidden information.
+    fn offered(game: &dyn DynGame, m: &DynMove, viewer: Player) -> Result<Self, Error> {
+        if game.hidden_information() {
+            Ok(MoveDescription::describing(game, m, None))
+        } else {
+            MoveDescription::new(game, m, viewer)
+        }
+    }
+
+    fn describing(game: &dyn DynGame, m: &DynMove, next_board: Option<String>) -> Self {
+        let notation = game.notation(m.id);
+        MoveDescription {
+            move_id: m.id.to_string(),
+            move_name: notation.short,
+            move_long_name: notation.long,
+            move_description: notation.descriptive,
+            next_board,
+            next_player: m.next_state.current_player().to_string(),
+        }
+    }
+}
+
+/// The game state as handed to `viewer`: sealed, if the game has hidden
+/// information, and otherwise just serialized.
+fn state_for(game: &dyn DynGame, viewer: Player) -> Result<String, Error> {
+    if game.hidden_information() {
+        Ok(seal::seal(viewer, &game.unparse())?)
+    } else {
+        Ok(game.unparse())
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     let func = handler_fn(my_handler);
@@ -70,35 +209,83 @@ async fn main() -> Result<(), Error> {
 }
 
 pub(crate) async fn my_handler(event: Request, _ctx: Context) -> Result<Response, Error> {
-    // a correct input path will tend to be of form `/C/GAME` where C is a
-    // single character command code and GAME is a multiple-character string
-    // describing the game state.
+    respond(&event.path).await
+}
+
+async fn respond(path: &str) -> Result<Response, Error> {
+    // a correct input path will tend to be of form `/G/C/GAME` where G names
+    // the game (see `registry`), C is a single character command code and
+    // GAME is a multiple-character string describing the game state. Without
+    // the `/G`, the game is tic-tac-toe.
     //
-    // The main exception is creating a fresh game, which just takes the form `/n/`, with no need
-    // for a further string.
+    // The main exception is creating a fresh game, which just takes the form `/G/n/`, with no need
+    // for a further string; or `/G/n/OPTIONS` to pick a variant, where OPTIONS
+    // is like `size=9,misere` (see `GameOptions`). Playing a move also names
+    // the move, by its id or notation, after the state: `/G/p/GAME/MOVE`.
 
     // drop the leading `/`
-    let (slash, input) = event.path.split_at(1);
+    let (slash, input) = path.split_at(1);
     assert_eq!(slash, "/");
+    let (game_type, input) = match input.find('/') {
+        Some(end) if end > 1 => {
+            let name = &input[..end];
+            let game_type = registry::find(name).ok_or_else(|| {
+                format!("unknown game `{}`; the games are {}", name, registry::names().join(", "))
+            })?;
+            (game_type, &input[end + 1..])
+        }
+        _ => (registry::default_game(), input),
+    };
     let (cmd, slash_state) = input.split_at(1);
     let (slash, state) = slash_state.split_at(1);
     assert_eq!(slash, "/");
 
 
     let c: Command = cmd.chars().next().unwrap().try_into()?;
+    // Game states never hold a '/', though moves (like "13/7*") may.
+    let (state, played) = match state.split_once('/') {
+        Some((state, played)) if c == Command::Play => (state, played),
+        _ => (state, ""),
+    };
 
-    let game = if c == Command::NewGame {
-        Default::default()
+    // The viewer is whoever the state is being shown to. Sealed states carry
+    // their viewer; a new game is shown to the player who moves first.
+    let (game, viewer) = if c == Command::NewGame {
+        let game = match game_type.new_game_with(state) {
+            Ok(game) => game,
+            Err(err) => return Ok(err.into()),
+        };
+        let viewer = game.current_player();
+        (game, viewer)
+    } else if game_type.hidden_information {
+        // A state the service did not seal is as much the client's mistake
+        // as one that does not parse; only a missing key is the service's.
+        let (viewer, state) = match seal::unseal(state) {
+            Ok(unsealed) => unsealed,
+            Err(seal::UnsealError::Invalid(err)) => return Ok(err.into()),
+            Err(seal::UnsealError::Key(err)) => return Err(err.into()),
+        };
+        match game_type.parse(&state) {
+            Ok(game) => (game, viewer),
+            Err(err) => return Ok(err.into()),
+        }
     } else {
-        tictactoe::TicTacToeGame::parse(state)?
+        let game = match game_type.parse(state) {
+            Ok(game) => game,
+            Err(err) => return Ok(err.into()),
+        };
+        let viewer = game.current_player();
+        (game, viewer)
     };
-    let player = game.player.to_string();
+    let player = game.current_player().to_string();
     let command;
-    let parsed_game_state = game.unparse();
+    let parsed_game_state = state_for(&*game, viewer)?;
+    let observation = if game.hidden_information() { Some(game.observation(viewer)) } else { None };
     let next_game_states;
     let selected_move;
     let text;
     let victory;
+    let mut solution = None;
 
     match c {
         Command::NewGame => {
@@ -110,14 +297,12 @@ pub(crate) async fn my_handler(event: Request, _ctx: Context) -> Result<Response
         }
         Command::List => {
             command = "list".to_string();
+            let visible = game.moves_for(viewer);
             next_game_states = Some(game.moves()
-                .into_iter()
-                .map(|m| MoveDescription {
-                    move_id: m.id.to_string(),
-                    next_board: m.next_state.unparse(),
-                    next_player: m.next_state.player.to_string(),
-                })
-                .collect());
+                .iter()
+                .filter(|m| visible.contains(&m.id))
+                .map(|m| MoveDescription::offered(&*game, m, viewer))
+                .collect::<Result<_, _>>()?);
             selected_move = None;
             victory = None;
             text = None;
@@ -127,38 +312,210 @@ pub(crate) async fn my_handler(event: Request, _ctx: Context) -> Result<Response
             next_game_states = None;
             selected_move = None;
             victory = None;
-            text = Some(game.render_to_text());
+            text = Some(game.render_for(viewer));
         }
         Command::Select => {
             command = "select".to_string();
             next_game_states = None;
-            let moves = game.moves();
-            let choice = game_core::search(&moves[..], game.current_player()).await;
-            selected_move = Some((choice.id.to_string(), choice.next_state.board.iter().collect()));
+            if game.outcome().is_over() {
+                return Ok(Response::bad_request(ErrorDescription {
+                    code: "game_over".to_string(),
+                    message: format!("the game is over ({}), so there is no move to select", game.outcome()),
+                    position: None,
+                    expected: "a game still in play".to_string(),
+                }));
+            }
+            let choice = game.select().await;
+            selected_move = Some(MoveDescription::new(&*game, &choice, viewer)?);
             victory = choice.end_game.as_ref().map(|v| {
                 v.iter().map(|c|c.to_string()).collect()
             });
             text = None;
         }
+        Command::Play => {
+            command = "play".to_string();
+            next_game_states = None;
+            let visible = game.moves_for(viewer);
+            let chosen = game.find_move(played)
+                .filter(|id| visible.contains(id))
+                .and_then(|id| game.moves().into_iter().find(|m| m.id == id));
+            let chosen = match chosen {
+                Some(m) => m,
+                None => return Ok(Response::bad_request(ErrorDescription {
+                    code: "unknown_move".to_string(),
+                    message: format!("`{}` is not one of the moves {} can make", played, viewer),
+                    position: None,
+                    expected: "one of the moves that list gives, by its id or notation".to_string(),
+                })),
+            };
+            selected_move = Some(MoveDescription::new(&*game, &chosen, viewer)?);
+            victory = chosen.end_game.as_ref().map(|v| {
+                v.iter().map(|c|c.to_string()).collect()
+            });
+            text = None;
+        }
+        Command::Solve => {
+            if !game.puzzle() {
+                return Ok(Response::bad_request(ErrorDescription {
+                    code: "not_a_puzzle".to_string(),
+                    message: format!("{} is not a puzzle, so it cannot be solved", game_type.name),
+                    position: None,
+                    expected: "a puzzle".to_string(),
+                }));
+            }
+            command = "solve".to_string();
+            next_game_states = None;
+            selected_move = None;
+            victory = None;
+            text = None;
+            // The solver has a budget, so that a hard puzzle cannot keep
+            // the service busy past its timeout.
+            solution = match game.solution() {
+                Ok(Some(steps)) => Some(steps
+                    .iter()
+                    .map(|(state, m)| MoveDescription::new(&**state, m, viewer))
+                    .collect::<Result<_, _>>()?),
+                Ok(None) => None,
+                Err(solver::TooBig) => return Ok(Response::bad_request(ErrorDescription {
+                    code: "too_hard".to_string(),
+                    message: "the solver gave up on this puzzle before finding its shortest solution".to_string(),
+                    position: None,
+                    expected: "a puzzle the solver can finish".to_string(),
+                })),
+            };
+        }
     }
 
     let resp = Response {
-        body: DoublyEncode(ResponseBody {
+        body: DoublyEncode(Body::Game(Box::new(ResponseBody {
             // request: format!("{:?}", event),
             // ctx: format!("{:?}", _ctx),
+            game: game_type.name.to_string(),
             command,
             parsed_game_state,
             player,
             next_game_states,
-            selected_move,
+            selected_move: selected_move.as_ref().map(|m: &MoveDescription| {
+                (m.move_id.clone(), m.next_board.clone().unwrap_or_default())
+            }),
+            selected_move_description: selected_move,
             text,
             victory,
-        }),
+            observation,
+            solution,
+            outcome: OutcomeDescription::of(&*game),
+            options: game.options(),
+        }))),
         status_code: String::from("200")
     };
 
     Ok(resp)
 }
 
+mod backgammon;
+mod battleship;
+// The service only plays strategies; measuring them is for `local train`.
+#[allow(dead_code)]
+mod cfr;
+mod chinese_checkers;
+#[cfg(test)]
+mod conformance;
+mod dots_and_boxes;
+mod dyn_game;
 mod game_core;
+mod go;
+mod grid;
+mod hex;
+mod kalah;
+mod lines;
+mod nim;
+mod nine_mens_morris;
+mod othello;
+mod pentago;
+mod poker;
+mod quarto;
+mod qubic;
+mod registry;
+mod seal;
+mod sliding_puzzle;
+mod solver;
+mod sprague_grundy;
 mod tictactoe;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The status code and the body of the response to `path`.
+    async fn ask(path: &str) -> (String, serde_json::Value) {
+        let response = serde_json::to_value(respond(path).await.unwrap()).unwrap();
+        let body = serde_json::from_str(response["body"].as_str().unwrap()).unwrap();
+        (response["statusCode"].as_str().unwrap().to_string(), body)
+    }
+
+    #[tokio::test]
+    async fn selecting_in_a_finished_game_is_a_bad_request() {
+        let (status, body) = ask("/s/XXXOO----").await;
+        assert_eq!(status, "400");
+        assert_eq!(body["error"]["code"], "game_over");
+    }
+
+    #[tokio::test]
+    async fn select_still_sends_the_move_and_board_as_a_pair() {
+        let (status, body) = ask("/s/XX-OO----").await;
+        assert_eq!(status, "200");
+        assert_eq!(body["selected_move"][0], body["selected_move_description"]["move_id"]);
+        assert_eq!(body["selected_move"][1], body["selected_move_description"]["next_board"]);
+    }
+
+    #[tokio::test]
+    async fn listed_battleship_shots_do_not_give_away_whether_they_hit() {
+        std::env::set_var(seal::KEY_VARIABLE, "07".repeat(32));
+        let (_, body) = ask("/Battleship/n/").await;
+        let state = body["parsed_game_state"].as_str().unwrap().to_string();
+        let (status, body) = ask(&format!("/Battleship/l/{}", state)).await;
+        assert_eq!(status, "200");
+        let moves = body["next_game_states"].as_array().unwrap();
+        assert_eq!(moves.len(), 100);
+        for m in moves {
+            assert!(m["next_board"].is_null(), "{} says where it leads", m["move_name"]);
+            assert_eq!(m["next_player"], "B");
+        }
+
+        // Playing a shot is what tells where it leads.
+        let (status, body) = ask(&format!("/Battleship/p/{}/c3", state)).await;
+        assert_eq!(status, "200");
+        assert_eq!(body["selected_move_description"]["move_name"], "C3");
+        assert!(body["selected_move_description"]["next_board"].is_string());
+        let (status, body) = ask(&format!("/Battleship/p/{}/k11", state)).await;
+        assert_eq!(status, "400");
+        assert_eq!(body["error"]["code"], "unknown_move");
+    }
+
+    #[tokio::test]
+    async fn a_state_not_sealed_here_is_a_bad_request() {
+        std::env::set_var(seal::KEY_VARIABLE, "07".repeat(32));
+        let (status, body) = ask("/Battleship/l/0123zz").await;
+        assert_eq!(status, "400");
+        assert_eq!(body["error"]["position"], 4);
+        let (status, body) = ask(&format!("/Battleship/l/{}", "ab".repeat(40))).await;
+        assert_eq!(status, "400");
+        assert_eq!(body["error"]["code"], "invalid_state");
+    }
+
+    #[tokio::test]
+    async fn the_outcome_says_what_a_win_is_worth() {
+        // White has borne off every checker, and Black none: a gammon.
+        let gammon = format!("W-{}o{}00f0", "0".repeat(11), "0".repeat(12));
+        let (status, body) = ask(&format!("/Backgammon/r/{}", gammon)).await;
+        assert_eq!(status, "200");
+        assert_eq!(body["outcome"]["result"], "win");
+        assert_eq!(body["outcome"]["victors"][0], "W");
+        assert_eq!(body["outcome"]["points"], 2);
+
+        let (_, body) = ask("/r/XXXOO----").await;
+        assert_eq!(body["outcome"]["points"], 1);
+        let (_, body) = ask("/r/XX-OO----").await;
+        assert_eq!(body["outcome"]["points"], 0);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::game_core::{Game, GameOptions, Move, MoveId, Outcome, ParseError, Player, VICTORY};
+use crate::sprague_grundy::{ImpartialGame, NimberCalculator};
+
+/// Heaps are limited to 99 objects (and games to 99 heaps), so that a move id
+/// can spell out the heap and the amount taken in decimal.
+pub const MAX_HEAP: u32 = 99;
+
+/// Nim: players alternately take any positive number of objects from a single
+/// heap. Under normal play whoever takes the last object wins; under misère
+/// play, whoever takes the last object loses.
+#[derive(Clone, Debug)]
+pub struct NimGame {
+    pub heaps: Vec<u32>,
+    pub misere: bool,
+    pub player: Player,
+    /// The heaps the game started with, and who moved first, which only the
+    /// options need.
+    pub start: Vec<u32>,
+    pub first: Player,
+}
+
+impl Default for NimGame {
+    fn default() -> Self {
+        Self::new(vec![3, 4, 5], false, 'A')
+    }
+}
+
+fn opponent(p: Player) -> Player {
+    if p == 'A' { 'B' } else { 'A' }
+}
+
+impl NimGame {
+    /// A game starting from `heaps`, with `first` to move.
+    pub fn new(heaps: Vec<u32>, misere: bool, first: Player) -> Self {
+        NimGame { start: heaps.clone(), heaps, misere, player: first, first }
+    }
+
+    /// Parses a state with nothing after its heaps, as the start of a game.
+    fn parse_start(input: &str) -> Result<Self, ParseError> {
+        let mut parts = input.split('-');
+        let header = parts.next().unwrap_or("");
+        let mut chars = header.chars();
+        let misere = match chars.next() {
+            Some('n') => false,
+            Some('m') => true,
+            _ => return Err(ParseError::unexpected(0, "the play mode, 'n' (normal) or 'm' (misère)")),
+        };
+        let player = match chars.next() {
+            Some(p @ 'A') | Some(p @ 'B') => p,
+            _ => return Err(ParseError::unexpected(1, "the player to move, A or B")),
+        };
+        if chars.next().is_some() {
+            return Err(ParseError::unexpected(2, "'-' and the first heap size"));
+        }
+        let mut heaps = Vec::new();
+        // Where the heap being parsed starts in the input.
+        let mut at = 3;
+        for part in parts {
+            match part.parse::<u32>() {
+                Ok(h) if h <= MAX_HEAP => heaps.push(h),
+                _ => match part.chars().position(|c| !c.is_ascii_digit()) {
+                    Some(i) => return Err(ParseError::unexpected(at + i, "a heap size as a decimal number")),
+                    None if part.is_empty() => return Err(ParseError::unexpected(at, "a heap size as a decimal number")),
+                    None => return Err(ParseError::invalid("heaps of at most 99 objects").at(at)),
+                },
+            }
+            at += part.chars().count() + 1;
+        }
+        if heaps.is_empty() { return Err(ParseError::wrong_length("at least one heap").at(header.chars().count())); }
+        if heaps.len() > MAX_HEAP as usize { return Err(ParseError::wrong_length("at most 99 heaps")); }
+        Ok(NimGame::new(heaps, misere, player))
+    }
+}
+
+impl Game for NimGame {
+    const NAME: &'static str = "Nim";
+
+    fn current_player(&self) -> Player {
+        self.player
+    }
+
+    /// The serialized form is 'n' (normal play) or 'm' (misère play), then the
+    /// player to move, then the heap sizes, each preceded by a '-'. For
+    /// example, "nA-3-4-5" is the default game. Once the game is under way,
+    /// a '.' follows, then who moved first and the heaps the game started
+    /// with, like "nB-3-4-4.A-3-4-5".
+    fn unparse(&self) -> String {
+        let push_heaps = |s: &mut String, heaps: &[u32]| {
+            for h in heaps {
+                s.push('-');
+                s.push_str(&h.to_string());
+            }
+        };
+        let mut s = String::new();
+        s.push(if self.misere { 'm' } else { 'n' });
+        s.push(self.player);
+        push_heaps(&mut s, &self.heaps);
+        if self.heaps != self.start || self.player != self.first {
+            s.push('.');
+            s.push(self.first);
+            push_heaps(&mut s, &self.start);
+        }
+        s
+    }
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let Some((now, start)) = input.split_once('.') else {
+            return NimGame::parse_start(input);
+        };
+        let mut g = NimGame::parse_start(now)?;
+        // The start reads as a state would, after the play mode.
+        let started = NimGame::parse_start(&format!("{}{}", &now[..1], start))
+            .map_err(|e| match e.position {
+                Some(at) => e.at(now.len() + at),
+                None => e,
+            })?;
+        let fits = started.heaps.len() == g.heaps.len()
+            && g.heaps.iter().zip(&started.heaps).all(|(h, s)| h <= s);
+        if !fits {
+            return Err(ParseError::invalid("as many heaps as the game started with, none of them bigger")
+                       .at(now.len() + 1));
+        }
+        g.start = started.heaps;
+        g.first = started.player;
+        Ok(g)
+    }
+
+    /// `heaps` (the heap sizes, separated by '-', like "3-4-5"), `misere`,
+    /// and `first` (A or B).
+    fn with_options(mut options: GameOptions) -> Result<Self, ParseError> {
+        let mut heaps = NimGame::default().heaps;
+        if let Some((text, at)) = options.text("heaps") {
+            // The heaps read as a game's would, after a header of three
+            // characters.
+            heaps = NimGame::parse_start(&format!("nA-{}", text))
+                .map_err(|e| ParseError::invalid_option(at + e.position.unwrap_or(3).saturating_sub(3), e.expected))?
+                .heaps;
+        }
+        let misere = options.flag("misere")?.unwrap_or(false);
+        let first = options.player("first", &['A', 'B'])?.unwrap_or('A');
+        options.finish(&["heaps", "misere", "first"])?;
+        Ok(NimGame::new(heaps, misere, first))
+    }
+
+    fn options(&self) -> String {
+        let heaps: Vec<String> = self.start.iter().map(|h| h.to_string()).collect();
+        format!("heaps={},misere={},first={}", heaps.join("-"), if self.misere { "yes" } else { "no" }, self.first)
+    }
+
+    /// Move `100 * h + n` takes `n` objects from heap `h` (counting from 1).
+    fn moves(&self) -> Vec<Move<Self>> {
+        let mut v = Vec::new();
+        for (i, h) in self.heaps.iter().enumerate() {
+            for n in 1..=*h {
+                let mut heaps = self.heaps.clone();
+                heaps[i] -= n;
+                let end_game = if heaps.iter().all(|h| *h == 0) {
+                    let victor = if self.misere { opponent(self.player) } else { self.player };
+                    Some(Some(victor).into_iter().collect())
+                } else {
+                    None
+                };
+                v.push(Move {
+                    id: 100 * (i as MoveId + 1) + n,
+                    end_game,
+                    next_state: NimGame {
+                        heaps,
+                        misere: self.misere,
+                        player: opponent(self.player),
+                        start: self.start.clone(),
+                        first: self.first,
+                    },
+                });
+            }
+        }
+        v
+    }
+
+    fn move_long_name(&self, id: MoveId) -> String {
+        format!("take {} from heap {}", id % 100, id / 100)
+    }
+
+    fn render_to_text(&self) -> String {
+        let mut s = String::new();
+        for (i, h) in self.heaps.iter().enumerate() {
+            s.push_str(&format!("{:>2}: {:>2} {}\n", i + 1, h, "|".repeat(*h as usize)));
+        }
+        s.push_str(&format!("{} play, {} to move\n",
+                            if self.misere { "misère" } else { "normal" },
+                            self.player));
+        s
+    }
+
+    /// Nim is solved, so this is exact: the nimbers say who can force a win.
+    fn value_for(&self, p: Player) -> i64 {
+        let mover_wins = NimberCalculator::new().is_winning(self);
+        if mover_wins == (p == self.player) { VICTORY } else { -VICTORY }
+    }
+
+    /// Once the heaps are empty, whoever took the last object has won (or,
+    /// in misère play, lost).
+    fn outcome(&self) -> Outcome {
+        if self.heaps.iter().any(|h| *h > 0) {
+            Outcome::Ongoing
+        } else if self.misere {
+            Outcome::win(self.player)
+        } else {
+            Outcome::win(opponent(self.player))
+        }
+    }
+}
+
+impl ImpartialGame for NimGame {
+    fn components(&self) -> Vec<Self> {
+        self.heaps.iter()
+            .map(|h| NimGame::new(vec![*h], self.misere, 'A'))
+            .collect()
+    }
+
+    fn is_misere(&self) -> bool {
+        self.misere
+    }
+}
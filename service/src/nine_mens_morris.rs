@@ -0,0 +1,486 @@
+use crate::game_core::{Game, Move, MoveId, Outcome, ParseError, Player, VICTORY};
+use smallvec::SmallVec;
+
+/// The 24 points, row by row from the top of the diagram, named by the usual
+/// coordinates.
+const POINTS: [&str; 24] = [
+    "a7", "d7", "g7",
+    "b6", "d6", "f6",
+    "c5", "d5", "e5",
+    "a4", "b4", "c4", "e4", "f4", "g4",
+    "c3", "d3", "e3",
+    "b2", "d2", "f2",
+    "a1", "d1", "g1",
+];
+
+/// The 16 lines of three points that make a mill. Neighboring points along a
+/// line are exactly the points connected on the board.
+const MILLS: [[usize; 3]; 16] = [
+    [0, 1, 2], [3, 4, 5], [6, 7, 8], [9, 10, 11],
+    [12, 13, 14], [15, 16, 17], [18, 19, 20], [21, 22, 23],
+    [0, 9, 21], [3, 10, 18], [6, 11, 15], [1, 4, 7],
+    [16, 19, 22], [8, 12, 17], [5, 13, 20], [2, 14, 23],
+];
+
+const PIECES: u8 = 9;
+
+/// Moving a piece from point `f` to point `t` (counting from 1) is move
+/// `MOVE * f + t`; placing a piece on `t` is just `t`.
+const MOVE: MoveId = 100;
+
+/// Removing the opponent's piece on point `t` is move `REMOVE + t`.
+const REMOVE: MoveId = 3000;
+
+/// The digits used to write position hashes.
+const DIGITS: &[u8; 62] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Each position hash is written as this many base-62 digits (enough for 32
+/// bits).
+const HASH_DIGITS: usize = 6;
+
+/// Nine Men's Morris, for White ('W', who starts) and Black ('B'). Players
+/// first take turns placing their nine pieces, then take turns moving a piece
+/// to a neighboring empty point; a player down to three pieces may fly to any
+/// empty point. Closing a mill (three in a row along a line) earns an extra
+/// action: removing one of the opponent's pieces, though not one in a mill
+/// unless they all are. A player loses when down to two pieces or left
+/// without a move, and the game is drawn when a position comes up for the
+/// third time.
+#[derive(Clone, Debug)]
+pub struct NineMensMorrisGame {
+    /// Each point is 'W', 'B' or '-'.
+    pub board: [char; 24],
+    /// The pieces still to be placed, White's then Black's.
+    pub in_hand: [u8; 2],
+    pub player: Player,
+    /// Whether the player has just closed a mill, and so must now remove one
+    /// of the opponent's pieces.
+    pub removing: bool,
+    /// A hash of each position since the last placement or removal (which
+    /// can never be repeated), including the current one.
+    pub history: Vec<u32>,
+}
+
+impl Default for NineMensMorrisGame {
+    fn default() -> Self {
+        let mut g = NineMensMorrisGame {
+            board: ['-'; 24],
+            in_hand: [PIECES, PIECES],
+            player: 'W',
+            removing: false,
+            history: Vec::new(),
+        };
+        g.history.push(g.hash());
+        g
+    }
+}
+
+fn opponent(p: Player) -> Player {
+    if p == 'W' { 'B' } else { 'W' }
+}
+
+fn index(p: Player) -> usize {
+    if p == 'W' { 0 } else { 1 }
+}
+
+/// The points connected to point `i`.
+fn neighbors(i: usize) -> impl Iterator<Item = usize> {
+    MILLS.iter()
+        .filter_map(move |mill| mill.iter().position(|j| *j == i).map(|k| (mill, k)))
+        .flat_map(|(mill, k)| {
+            let before = if k > 0 { Some(mill[k - 1]) } else { None };
+            before.into_iter().chain(mill.get(k + 1).cloned())
+        })
+}
+
+impl NineMensMorrisGame {
+    /// A 32-bit FNV-1a hash of the board and the player to move.
+    fn hash(&self) -> u32 {
+        self.board.iter()
+            .chain(Some(&self.player))
+            .fold(0x811c_9dc5, |h, c| (h ^ *c as u32).wrapping_mul(0x0100_0193))
+    }
+
+    fn on_board(&self, p: Player) -> usize {
+        self.board.iter().filter(|c| **c == p).count()
+    }
+
+    fn in_mill(&self, i: usize) -> bool {
+        MILLS.iter().any(|mill| mill.contains(&i) && mill.iter().all(|j| self.board[*j] == self.board[i]))
+    }
+
+    fn can_fly(&self, p: Player) -> bool {
+        self.in_hand[index(p)] == 0 && self.on_board(p) == 3
+    }
+
+    /// How many pieces of `p`'s could move to a neighboring empty point.
+    fn mobility(&self, p: Player) -> usize {
+        (0..24)
+            .filter(|i| self.board[*i] == p && neighbors(*i).any(|j| self.board[j] == '-'))
+            .count()
+    }
+
+    fn has_move(&self, p: Player) -> bool {
+        self.in_hand[index(p)] > 0 || self.can_fly(p) || self.mobility(p) > 0
+    }
+
+    /// The victors, if the game is over with the current player to move: the
+    /// opponent if the current player is down to two pieces or cannot move,
+    /// or nobody if the position has come up three times.
+    fn end_game(&self) -> Option<SmallVec<[Player; 1]>> {
+        if self.removing {
+            return None;
+        }
+        let p = self.player;
+        let current = self.hash();
+        if self.on_board(p) + (self.in_hand[index(p)] as usize) < 3 || !self.has_move(p) {
+            Some(Some(opponent(p)).into_iter().collect())
+        } else if self.history.iter().filter(|h| **h == current).count() >= 3 {
+            Some(SmallVec::new())
+        } else {
+            None
+        }
+    }
+
+    /// Finishes the move `id` that brought one of the mover's pieces to point
+    /// `to` in `next`: closing a mill leaves the mover to remove a piece, and
+    /// otherwise it is the opponent's turn. Placements are `irreversible`,
+    /// so they start a fresh history.
+    fn arrive(&self, mut next: Self, id: MoveId, to: usize, irreversible: bool) -> Move<Self> {
+        let closed_mill = MILLS.iter()
+            .any(|mill| mill.contains(&to) && mill.iter().all(|j| next.board[*j] == self.player));
+        // With none of the opponent's pieces on the board, there is nothing
+        // to remove.
+        if closed_mill && next.on_board(opponent(self.player)) > 0 {
+            next.removing = true;
+            return Move { id, end_game: None, next_state: next };
+        }
+        next.player = opponent(self.player);
+        if irreversible {
+            next.history.clear();
+        }
+        next.history.push(next.hash());
+        let end_game = next.end_game();
+        Move { id, end_game, next_state: next }
+    }
+}
+
+impl Game for NineMensMorrisGame {
+    const NAME: &'static str = "NineMensMorris";
+
+    fn current_player(&self) -> Player {
+        self.player
+    }
+
+    /// The serialized form is the player to move, 'x' if they must remove a
+    /// piece (and 'm' otherwise), the pieces left in White's and Black's
+    /// hands, the 24 points (in the order of `POINTS`, each 'W', 'B' or '0'
+    /// for empty), then a '-' and the hashes of the positions since the last
+    /// placement or removal (the only ones that can come up again), six
+    /// base-62 digits each. For example, the default game is
+    /// "Wm99000000000000000000000000-" followed by the hash of the empty board.
+    fn unparse(&self) -> String {
+        let mut s = String::new();
+        s.push(self.player);
+        s.push(if self.removing { 'x' } else { 'm' });
+        s.push((b'0' + self.in_hand[0]) as char);
+        s.push((b'0' + self.in_hand[1]) as char);
+        s.extend(self.board.iter().map(|c| if *c == '-' { '0' } else { *c }));
+        s.push('-');
+        for h in &self.history {
+            let mut h = *h as usize;
+            let mut digits = [b'0'; HASH_DIGITS];
+            for d in digits.iter_mut().rev() {
+                *d = DIGITS[h % 62];
+                h /= 62;
+            }
+            s.push_str(std::str::from_utf8(&digits).unwrap());
+        }
+        s
+    }
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let (head, hashes) = match input.find('-') {
+            Some(k) => (&input[..k], &input[k + 1..]),
+            None => {
+                let at = input.chars().count();
+                return Err(ParseError::wrong_length("<player><phase><hands><board>-<history>").at(at));
+            }
+        };
+        let head: Vec<char> = head.chars().collect();
+        if head.len() != 4 + 24 {
+            return Err(ParseError::wrong_length("the player, phase, hands and 24 points before the '-'")
+                .at(head.len().min(4 + 24)));
+        }
+        let player = match head[0] {
+            p @ 'W' | p @ 'B' => p,
+            _ => return Err(ParseError::unexpected(0, "the player to move, W or B")),
+        };
+        let removing = match head[1] {
+            'x' => true,
+            'm' => false,
+            _ => return Err(ParseError::unexpected(1, "'x' (to remove a piece) or 'm'")),
+        };
+        let mut in_hand = [0; 2];
+        for (k, c) in head[2..4].iter().enumerate() {
+            match c.to_digit(10) {
+                Some(n) if n <= PIECES as u32 => in_hand[k] = n as u8,
+                _ => return Err(ParseError::unexpected(2 + k, "the pieces in hand, from 0 to 9")),
+            }
+        }
+        let mut board = ['-'; 24];
+        for (i, c) in head[4..].iter().enumerate() {
+            board[i] = match c {
+                'W' | 'B' => *c,
+                '0' => '-',
+                _ => return Err(ParseError::unexpected(4 + i, "'W', 'B' or '0'")),
+            };
+        }
+
+        // Where the history starts in the input, past the '-'.
+        let hashes_at = head.len() + 1;
+        if hashes.len() % HASH_DIGITS != 0 {
+            let at = hashes_at + hashes.len() - hashes.len() % HASH_DIGITS;
+            return Err(ParseError::wrong_length("position hashes of six digits each").at(at));
+        }
+        let mut history = Vec::new();
+        for (k, hash) in hashes.as_bytes().chunks(HASH_DIGITS).enumerate() {
+            let at = hashes_at + k * HASH_DIGITS;
+            let mut h: u64 = 0;
+            for (j, d) in hash.iter().enumerate() {
+                match DIGITS.iter().position(|x| x == d) {
+                    Some(n) => h = 62 * h + n as u64,
+                    None => return Err(ParseError::unexpected(at + j, "a position hash digit, in base 62")),
+                }
+            }
+            if h > u32::MAX as u64 { return Err(ParseError::invalid("a position hash of at most 32 bits").at(at)); }
+            history.push(h as u32);
+        }
+
+        let g = NineMensMorrisGame { board, in_hand, player, removing, history };
+        for p in &['W', 'B'] {
+            if g.on_board(*p) + g.in_hand[index(*p)] as usize > PIECES as usize {
+                return Err(ParseError::invalid(format!("at most nine pieces for {}", p)));
+            }
+        }
+        if !removing && g.history.last() != Some(&g.hash()) {
+            return Err(ParseError::invalid("a position history ending with the current position"));
+        }
+        Ok(g)
+    }
+
+    fn moves(&self) -> Vec<Move<Self>> {
+        if self.end_game().is_some() {
+            return Vec::new();
+        }
+        let p = self.player;
+        let mut v = Vec::new();
+        if self.removing {
+            let them = opponent(p);
+            let theirs: Vec<usize> = (0..24).filter(|i| self.board[*i] == them).collect();
+            let all_in_mills = theirs.iter().all(|i| self.in_mill(*i));
+            for i in theirs {
+                if self.in_mill(i) && !all_in_mills { continue; }
+                let mut next = self.clone();
+                next.board[i] = '-';
+                next.removing = false;
+                next.player = them;
+                next.history = vec![next.hash()];
+                let end_game = next.end_game();
+                v.push(Move { id: REMOVE + i as MoveId + 1, end_game, next_state: next });
+            }
+        } else if self.in_hand[index(p)] > 0 {
+            for to in (0..24).filter(|i| self.board[*i] == '-') {
+                let mut next = self.clone();
+                next.board[to] = p;
+                next.in_hand[index(p)] -= 1;
+                v.push(self.arrive(next, to as MoveId + 1, to, true));
+            }
+        } else {
+            let flying = self.can_fly(p);
+            for from in (0..24).filter(|i| self.board[*i] == p) {
+                let targets: Vec<usize> = if flying {
+                    (0..24).filter(|j| self.board[*j] == '-').collect()
+                } else {
+                    neighbors(from).filter(|j| self.board[*j] == '-').collect()
+                };
+                for to in targets {
+                    let mut next = self.clone();
+                    next.board[from] = '-';
+                    next.board[to] = p;
+                    let id = MOVE * (from as MoveId + 1) + to as MoveId + 1;
+                    v.push(self.arrive(next, id, to, false));
+                }
+            }
+        }
+        v
+    }
+
+    /// Placements are named by point, like "d2", moves like "d2-d3", and
+    /// removals like "xg7".
+    fn move_name(&self, id: MoveId) -> String {
+        if id > REMOVE {
+            format!("x{}", POINTS[(id - REMOVE) as usize - 1])
+        } else if id > MOVE {
+            format!("{}-{}", POINTS[(id / MOVE) as usize - 1], POINTS[(id % MOVE) as usize - 1])
+        } else {
+            POINTS[id as usize - 1].to_string()
+        }
+    }
+
+    fn move_long_name(&self, id: MoveId) -> String {
+        if id > REMOVE {
+            format!("remove the piece on {}", POINTS[(id - REMOVE) as usize - 1])
+        } else if id > MOVE {
+            format!("move from {} to {}", POINTS[(id / MOVE) as usize - 1], POINTS[(id % MOVE) as usize - 1])
+        } else {
+            format!("place on {}", POINTS[id as usize - 1])
+        }
+    }
+
+    fn render_to_text(&self) -> String {
+        const DIAGRAM: [&str; 13] = [
+            "o-----------o-----------o",
+            "|           |           |",
+            "|   o-------o-------o   |",
+            "|   |       |       |   |",
+            "|   |   o---o---o   |   |",
+            "|   |   |       |   |   |",
+            "o---o---o       o---o---o",
+            "|   |   |       |   |   |",
+            "|   |   o---o---o   |   |",
+            "|   |       |       |   |",
+            "|   o-------o-------o   |",
+            "|           |           |",
+            "o-----------o-----------o",
+        ];
+        // The points appear in the diagram in the same order as in `board`.
+        let mut points = self.board.iter().map(|c| if *c == '-' { '.' } else { *c });
+        let mut s = String::new();
+        for (row, line) in DIAGRAM.iter().enumerate() {
+            let label = if row % 2 == 0 { (b'7' - row as u8 / 2) as char } else { ' ' };
+            s.push(label);
+            s.push(' ');
+            s.extend(line.chars().map(|c| if c == 'o' { points.next().unwrap() } else { c }));
+            s.push('\n');
+        }
+        s.push_str("  a   b   c   d   e   f   g\n");
+        s.push_str(&format!("in hand: W {}, B {}\n", self.in_hand[0], self.in_hand[1]));
+        let action = match self.end_game() {
+            Some(victors) if victors.is_empty() => "draws by repetition".to_string(),
+            Some(_) => "has lost".to_string(),
+            None if self.removing => "to remove a piece".to_string(),
+            None if self.in_hand[index(self.player)] > 0 => "to place".to_string(),
+            None if self.can_fly(self.player) => "to fly".to_string(),
+            None => "to move".to_string(),
+        };
+        s.push_str(&format!("{} {}\n", self.player, action));
+        s
+    }
+
+    /// Ten points per piece ahead (counting pieces in hand) plus one per piece
+    /// more that can move; once the game is over, a decisive victory or loss
+    /// (or nothing, for a draw).
+    fn value_for(&self, p: Player) -> i64 {
+        if let Some(victors) = self.end_game() {
+            return match victors.first() {
+                None => 0,
+                Some(v) if *v == p => VICTORY,
+                Some(_) => -VICTORY,
+            };
+        }
+        let q = opponent(p);
+        let pieces = |p: Player| (self.on_board(p) + self.in_hand[index(p)] as usize) as i64;
+        10 * (pieces(p) - pieces(q)) + self.mobility(p) as i64 - self.mobility(q) as i64
+    }
+
+    fn outcome(&self) -> Outcome {
+        Outcome::from_end_game(self.end_game())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_core::play;
+
+    /// `player` to move, with every piece placed on the named points.
+    fn position(player: Player, white: &[&str], black: &[&str]) -> NineMensMorrisGame {
+        let mut g = NineMensMorrisGame { in_hand: [0, 0], player, ..NineMensMorrisGame::default() };
+        for (names, p) in [(white, 'W'), (black, 'B')] {
+            for name in names {
+                g.board[POINTS.iter().position(|n| n == name).unwrap()] = p;
+            }
+        }
+        g.history = vec![g.hash()];
+        g
+    }
+
+    #[test]
+    fn with_three_pieces_left_a_player_may_fly() {
+        let g = position('W', &["a7", "d6", "g1"], &["b2", "d2", "e4", "c4"]);
+        assert_eq!(g.moves().len(), 3 * 17);
+        assert!(play(&g, "a7-f2").end_game.is_none());
+        // Black, with four pieces, only moves along the lines.
+        let g = NineMensMorrisGame { player: 'B', ..g };
+        assert!(g.moves().iter().all(|m| neighbors((m.id / MOVE) as usize - 1).any(|j| j == (m.id % MOVE) as usize - 1)));
+    }
+
+    #[test]
+    fn closing_a_mill_removes_a_piece_not_in_a_mill() {
+        let g = position('W', &["a7", "d7", "g4", "c5"], &["b6", "d6", "f6", "d1", "b2"]);
+        let closed = play(&g, "g4-g7").next_state;
+        assert!(closed.removing);
+        assert_eq!(closed.current_player(), 'W');
+        let mut removals: Vec<String> = closed.moves().iter().map(|m| closed.move_name(m.id)).collect();
+        removals.sort();
+        assert_eq!(removals, ["xb2", "xd1"]);
+        let removed = play(&closed, "xd1").next_state;
+        assert_eq!(removed.current_player(), 'B');
+        assert_eq!(removed.on_board('B'), 4);
+
+        // Once every piece is in a mill, any of them may go.
+        let g = position('W', &["a7", "d7", "g4", "c5"], &["b6", "d6", "f6"]);
+        let closed = play(&g, "g4-g7").next_state;
+        assert_eq!(closed.moves().len(), 3);
+    }
+
+    #[test]
+    fn down_to_two_pieces_loses() {
+        let g = position('W', &["a7", "d7", "g4", "c5"], &["b6", "d2", "f4"]);
+        let closed = play(&g, "g4-g7").next_state;
+        let m = play(&closed, "xb6");
+        assert_eq!(m.end_game, Some(Some('W').into_iter().collect()));
+        assert_eq!(m.next_state.outcome(), Outcome::win('W'));
+    }
+
+    #[test]
+    fn the_history_restarts_after_every_placement_and_removal() {
+        let mut g = NineMensMorrisGame::default();
+        for name in ["d2", "d6", "b4", "f4"] {
+            g = play(&g, name).next_state;
+            assert_eq!(g.history, [g.hash()]);
+            assert_eq!(g.unparse().len(), "Wm99".len() + 24 + 1 + HASH_DIGITS);
+        }
+
+        // Moves add to it, until a removal.
+        let g = position('W', &["a7", "d7", "g4", "c5"], &["b6", "d6", "f6", "d1", "b2"]);
+        let moved = play(&play(&g, "c5-c4").next_state, "b2-b4").next_state;
+        assert_eq!(moved.history.len(), 3);
+        let closed = play(&play(&moved, "c4-c5").next_state, "b4-b2").next_state;
+        let removed = play(&play(&closed, "g4-g7").next_state, "xd1").next_state;
+        assert_eq!(removed.history, [removed.hash()]);
+    }
+
+    #[test]
+    fn a_position_coming_up_a_third_time_is_a_draw() {
+        let mut g = position('W', &["a7", "c5", "e3", "g1"], &["g7", "e5", "b2", "d1"]);
+        let shuffle = ["a7-a4", "g7-g4", "a4-a7", "g4-g7"];
+        for (k, name) in shuffle.iter().chain(&shuffle).enumerate() {
+            let m = play(&g, name);
+            assert_eq!(m.end_game.is_some(), k == 7, "after {}", name);
+            g = m.next_state;
+        }
+        assert_eq!(g.outcome(), Outcome::Draw);
+    }
+}
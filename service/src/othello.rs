@@ -0,0 +1,293 @@
+use crate::game_core::{Game, Move, MoveId, Outcome, ParseError, Player, Rng, VICTORY};
+use smallvec::SmallVec;
+
+pub type OthelloBoard = [char; 64];
+
+/// The move id used when the current player has no legal placement and must
+/// hand the turn back to their opponent. The placements themselves use ids
+/// 1..=64, reading the board left-to-right, top-to-bottom.
+pub const PASS: MoveId = 65;
+
+#[derive(Clone, Debug)]
+pub struct OthelloGame {
+    pub board: OthelloBoard,
+    pub player: Player,
+}
+
+impl Default for OthelloGame {
+    fn default() -> Self {
+        let mut board = ['-'; 64];
+        board[3 * 8 + 3] = 'W';
+        board[3 * 8 + 4] = 'B';
+        board[4 * 8 + 3] = 'B';
+        board[4 * 8 + 4] = 'W';
+        Self { board, player: 'B' }
+    }
+}
+
+const DIRECTIONS: [(i32, i32); 8] = [
+    (-1, -1), (-1, 0), (-1, 1),
+    (0, -1),           (0, 1),
+    (1, -1),  (1, 0),  (1, 1),
+];
+
+/// Classic positional weights: corners are precious, the squares next to
+/// them are liabilities, and edges are mildly good.
+const WEIGHTS: [i64; 64] = [
+    100, -20, 10,  5,  5, 10, -20, 100,
+    -20, -50, -2, -2, -2, -2, -50, -20,
+     10,  -2, -1, -1, -1, -1,  -2,  10,
+      5,  -2, -1, -1, -1, -1,  -2,   5,
+      5,  -2, -1, -1, -1, -1,  -2,   5,
+     10,  -2, -1, -1, -1, -1,  -2,  10,
+    -20, -50, -2, -2, -2, -2, -50, -20,
+    100, -20, 10,  5,  5, 10, -20, 100,
+];
+
+fn opponent(p: Player) -> Player {
+    if p == 'B' { 'W' } else { 'B' }
+}
+
+impl Game for OthelloGame {
+    const NAME: &'static str = "Othello";
+
+    fn current_player(&self) -> Player {
+        self.player
+    }
+
+    /// The serialized form is the player to move followed by the 64 cells of
+    /// the board, each of which is 'B', 'W' or '-'. (Unlike tictactoe, the
+    /// player cannot be derived from the board, because of passes.)
+    fn unparse(&self) -> String {
+        Some(self.player).into_iter().chain(self.board.iter().cloned()).collect()
+    }
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let len = input.chars().count();
+        if len != 65 { return Err(ParseError::wrong_length("the player to move and 64 cells").at(len.min(65))); }
+        let mut chars = input.chars();
+        let player = match chars.next() {
+            Some(p @ 'B') | Some(p @ 'W') => p,
+            _ => return Err(ParseError::unexpected(0, "the player to move, B or W")),
+        };
+        let mut board = ['-'; 64];
+        for (i, c) in chars.enumerate() {
+            match c {
+                '-' | 'B' | 'W' => board[i] = c,
+                'b' | 'w' => return Err(ParseError::unexpected(i + 1, "upper-case 'B' or 'W'")),
+                _ => return Err(ParseError::unexpected(i + 1, "'B', 'W' or '-'")),
+            }
+        }
+        Ok(OthelloGame { board, player })
+    }
+
+    const LAZY_MOVES: bool = true;
+
+    fn moves(&self) -> Vec<Move<Self>> {
+        self.lazy_moves().collect()
+    }
+
+    fn lazy_moves(&self) -> Box<dyn Iterator<Item = Move<Self>> + '_> {
+        let placements = (0..64)
+            .filter_map(move |i| place(&self.board, i, self.player).map(|next_board| (i, next_board)))
+            .map(move |(i, next_board)| self.move_to(i as MoveId + 1, next_board));
+        let pass = std::iter::once_with(move || {
+            if !has_placement(&self.board, self.player) && has_placement(&self.board, opponent(self.player)) {
+                Some(self.move_to(PASS, self.board))
+            } else {
+                None
+            }
+        });
+        Box::new(placements.chain(pass.flatten()))
+    }
+
+    /// Finds every placement, but only works out whether the game is over
+    /// after the one it picks.
+    fn random_move(&self, rng: &mut Rng) -> Option<Move<Self>> {
+        let mut placements: Vec<(usize, OthelloBoard)> = (0..64)
+            .filter_map(|i| place(&self.board, i, self.player).map(|next_board| (i, next_board)))
+            .collect();
+        if placements.is_empty() {
+            return self.lazy_moves().next();
+        }
+        let (i, next_board) = placements.swap_remove(rng.below(placements.len()));
+        Some(self.move_to(i as MoveId + 1, next_board))
+    }
+
+    /// Cells are named by column letter and row, like "d3", as drawn.
+    fn move_name(&self, id: MoveId) -> String {
+        if id == PASS {
+            return "pass".to_string();
+        }
+        let i = id as usize - 1;
+        format!("{}{}", (b'a' + (i % 8) as u8) as char, i / 8 + 1)
+    }
+
+    fn move_long_name(&self, id: MoveId) -> String {
+        if id == PASS {
+            "pass, with nowhere to place".to_string()
+        } else {
+            format!("place on {}", self.move_name(id))
+        }
+    }
+
+    fn render_to_text(&self) -> String {
+        let mut s = String::from("  a b c d e f g h\n");
+        for row in 0..8 {
+            s.push_str(&(row + 1).to_string());
+            for col in 0..8 {
+                s.push(' ');
+                s.push(match self.board[row * 8 + col] { '-' => '.', c => c });
+            }
+            s.push('\n');
+        }
+        let (b, w) = disc_counts(&self.board);
+        s.push_str(&format!("B: {}  W: {}  ({} to move)\n", b, w, self.player));
+        s
+    }
+
+    /// The weighted squares each player holds; once neither player can
+    /// place, a decisive victory or loss (or nothing, for a tie).
+    fn value_for(&self, p: Player) -> i64 {
+        let other = opponent(p);
+        if !has_placement(&self.board, 'B') && !has_placement(&self.board, 'W') {
+            let count = |q: Player| self.board.iter().filter(|c| **c == q).count() as i64;
+            return (count(p) - count(other)).signum() * VICTORY;
+        }
+        self.board.iter().zip(WEIGHTS.iter())
+            .map(|(c, w)| if *c == p { *w } else if *c == other { -*w } else { 0 })
+            .sum()
+    }
+
+    /// The game is over once neither player can place a disc.
+    fn outcome(&self) -> Outcome {
+        if has_placement(&self.board, 'B') || has_placement(&self.board, 'W') {
+            Outcome::Ongoing
+        } else {
+            Outcome::from_end_game(Some(victors(&self.board)))
+        }
+    }
+}
+
+impl OthelloGame {
+    /// Builds the move with the given id that leads to `next_board`, handing
+    /// the turn to the opponent and checking whether the game is over.
+    fn move_to(&self, id: MoveId, next_board: OthelloBoard) -> Move<Self> {
+        let end_game = if !has_placement(&next_board, 'B') && !has_placement(&next_board, 'W') {
+            Some(victors(&next_board))
+        } else {
+            None
+        };
+        Move {
+            id,
+            end_game,
+            next_state: OthelloGame { board: next_board, player: opponent(self.player) },
+        }
+    }
+}
+
+/// Places a disc for `player` on cell `i`, returning the resulting board, or
+/// `None` if the placement is illegal (the cell is taken, or it would flip
+/// nothing).
+fn place(board: &OthelloBoard, i: usize, player: Player) -> Option<OthelloBoard> {
+    if board[i] != '-' { return None; }
+    let other = opponent(player);
+    let (row, col) = ((i / 8) as i32, (i % 8) as i32);
+    let mut next = *board;
+    let mut flipped_any = false;
+    for (dr, dc) in DIRECTIONS.iter() {
+        let mut run = Vec::new();
+        let (mut r, mut c) = (row + dr, col + dc);
+        while (0..8).contains(&r) && (0..8).contains(&c) && board[(r * 8 + c) as usize] == other {
+            run.push((r * 8 + c) as usize);
+            r += dr;
+            c += dc;
+        }
+        if !run.is_empty()
+            && (0..8).contains(&r) && (0..8).contains(&c)
+            && board[(r * 8 + c) as usize] == player
+        {
+            for j in run { next[j] = player; }
+            flipped_any = true;
+        }
+    }
+    if !flipped_any { return None; }
+    next[i] = player;
+    Some(next)
+}
+
+fn has_placement(board: &OthelloBoard, player: Player) -> bool {
+    (0..64).any(|i| place(board, i, player).is_some())
+}
+
+fn disc_counts(board: &OthelloBoard) -> (usize, usize) {
+    (board.iter().filter(|c| **c == 'B').count(), board.iter().filter(|c| **c == 'W').count())
+}
+
+/// The player with more discs wins; a tie has no victors.
+fn victors(board: &OthelloBoard) -> SmallVec<[Player; 1]> {
+    let (b, w) = disc_counts(board);
+    if b > w {
+        Some('B').into_iter().collect()
+    } else if w > b {
+        Some('W').into_iter().collect()
+    } else {
+        SmallVec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_core::play;
+
+    /// A game with `player` to move and discs on the named cells.
+    fn game(player: Player, discs: &[(&str, char)]) -> OthelloGame {
+        let mut g = OthelloGame { board: ['-'; 64], player };
+        for (name, c) in discs {
+            let name = name.as_bytes();
+            g.board[(name[1] - b'1') as usize * 8 + (name[0] - b'a') as usize] = *c;
+        }
+        g
+    }
+
+    fn names(g: &OthelloGame) -> Vec<String> {
+        g.moves().iter().map(|m| g.move_name(m.id)).collect()
+    }
+
+    #[test]
+    fn placing_flips_the_discs_it_flanks() {
+        let start = OthelloGame::default();
+        assert_eq!(names(&start), ["d3", "c4", "f5", "e6"]);
+        assert_eq!(play(&start, "d3").next_state.unparse(), game('W', &[("d3", 'B'), ("d4", 'B'), ("e4", 'B'), ("d5", 'B'), ("e5", 'W')]).unparse());
+    }
+
+    #[test]
+    fn a_player_with_nowhere_to_place_passes() {
+        // B cannot flank the W disc in the corner, but W can flank B's.
+        let g = game('B', &[("a1", 'W'), ("b1", 'B')]);
+        assert_eq!(names(&g), ["pass"]);
+        assert_eq!(g.outcome(), Outcome::Ongoing);
+        let pass = &g.moves()[0];
+        assert_eq!(pass.end_game, None);
+        assert_eq!(pass.next_state.player, 'W');
+        assert_eq!(pass.next_state.board, g.board);
+        assert_eq!(names(&pass.next_state), ["c1"]);
+    }
+
+    #[test]
+    fn the_game_ends_when_neither_player_can_place() {
+        let g = game('W', &[("a1", 'W'), ("b1", 'B')]);
+        let m = &g.moves()[0];
+        assert_eq!(m.end_game, Some(Some('W').into_iter().collect()));
+        assert_eq!(m.next_state.outcome(), Outcome::win('W'));
+        assert_eq!(m.next_state.value_for('W'), VICTORY);
+        assert_eq!(m.next_state.value_for('B'), -VICTORY);
+        assert!(m.next_state.moves().is_empty());
+
+        // With as many discs each, it is a draw.
+        let tie = game('B', &[("a1", 'B'), ("h8", 'W')]);
+        assert_eq!(tie.outcome(), Outcome::Draw);
+        assert_eq!(tie.value_for('B'), 0);
+    }
+}
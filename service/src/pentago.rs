@@ -0,0 +1,315 @@
+use crate::game_core::{Game, Move, MoveId, Outcome, ParseError, Player, VICTORY};
+use crate::lines;
+use smallvec::SmallVec;
+use std::sync::OnceLock;
+
+const SIZE: usize = 6;
+
+/// How many marbles in a row win.
+const WIN_LENGTH: usize = 5;
+
+/// Rotating quadrant `q` (0 to 3) is move `ROTATE + 2 * q`, clockwise, or
+/// `ROTATE + 2 * q + 1`, counterclockwise.
+pub const ROTATE: MoveId = 100;
+
+const QUADRANTS: [&str; 4] = ["NW", "NE", "SW", "SE"];
+
+/// Pentago on a 6x6 board made of four 3x3 quadrants, for White ('W', who
+/// starts) and Black ('B'). Five marbles in a row (across, down or
+/// diagonally) win.
+///
+/// Each turn has two actions: first the player places a marble on an empty
+/// cell (move ids 1 to 36, the cells row by row), then they rotate one of the
+/// quadrants a quarter turn either way (move ids `ROTATE` and up). A player
+/// who makes five in a row with their marble wins at once, without rotating.
+/// A rotation can complete rows for both players at once, in which case both
+/// are victors; if the board fills up with no five in a row, it's a draw.
+#[derive(Clone, Debug)]
+pub struct PentagoGame {
+    /// Each cell is 'W', 'B' or '-', row by row.
+    pub board: [char; SIZE * SIZE],
+    pub player: Player,
+    /// Whether the player has placed their marble and is now to rotate.
+    pub rotating: bool,
+}
+
+impl Default for PentagoGame {
+    fn default() -> Self {
+        PentagoGame { board: ['-'; SIZE * SIZE], player: 'W', rotating: false }
+    }
+}
+
+fn opponent(p: Player) -> Player {
+    if p == 'W' { 'B' } else { 'W' }
+}
+
+/// The runs of five cells that win.
+fn winning_lines() -> &'static [Vec<usize>] {
+    static LINES: OnceLock<Vec<Vec<usize>>> = OnceLock::new();
+    LINES.get_or_init(|| lines::lines(&[SIZE, SIZE], WIN_LENGTH))
+}
+
+fn cell_name(i: usize) -> String {
+    format!("{}{}", (b'a' + (i % SIZE) as u8) as char, i / SIZE + 1)
+}
+
+impl PentagoGame {
+    /// The players with five in a row.
+    fn victors(&self) -> SmallVec<[Player; 1]> {
+        ['W', 'B'].iter()
+            .filter(|p| lines::has_line(&self.board, winning_lines(), *p))
+            .cloned()
+            .collect()
+    }
+
+    fn is_full(&self) -> bool {
+        self.board.iter().all(|c| *c != '-')
+    }
+
+    /// How the game ended, if it is over: the victors, or none for a draw.
+    fn end_game(&self) -> Option<SmallVec<[Player; 1]>> {
+        let victors = self.victors();
+        if !victors.is_empty() {
+            Some(victors)
+        } else if self.is_full() && !self.rotating {
+            Some(SmallVec::new())
+        } else {
+            None
+        }
+    }
+
+    /// Turns quadrant `q` a quarter turn, clockwise or not.
+    fn rotate(&mut self, q: usize, clockwise: bool) {
+        let (top, left) = (q / 2 * 3, q % 2 * 3);
+        let old = self.board;
+        for r in 0..3 {
+            for c in 0..3 {
+                let (from_r, from_c) = if clockwise { (2 - c, r) } else { (c, 2 - r) };
+                self.board[(top + r) * SIZE + left + c] = old[(top + from_r) * SIZE + left + from_c];
+            }
+        }
+    }
+}
+
+impl Game for PentagoGame {
+    const NAME: &'static str = "Pentago";
+
+    fn current_player(&self) -> Player {
+        self.player
+    }
+
+    /// The serialized form is the player to move, 'r' if they are to rotate
+    /// a quadrant (and 'p' if they are to place a marble), then the 36 cells
+    /// row by row, each 'W', 'B' or '-'. For example, the default game is
+    /// "Wp" followed by 36 '-'s.
+    fn unparse(&self) -> String {
+        let mut s = String::new();
+        s.push(self.player);
+        s.push(if self.rotating { 'r' } else { 'p' });
+        s.extend(self.board.iter());
+        s
+    }
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let chars: Vec<char> = input.chars().collect();
+        let len = 2 + SIZE * SIZE;
+        if chars.len() != len {
+            return Err(ParseError::wrong_length("the player to move, the phase and 36 cells").at(chars.len().min(len)));
+        }
+        let player = match chars[0] {
+            p @ 'W' | p @ 'B' => p,
+            _ => return Err(ParseError::unexpected(0, "the player to move, W or B")),
+        };
+        let rotating = match chars[1] {
+            'r' => true,
+            'p' => false,
+            _ => return Err(ParseError::unexpected(1, "'p' (to place) or 'r' (to rotate)")),
+        };
+        let mut board = ['-'; SIZE * SIZE];
+        for (i, c) in chars[2..].iter().enumerate() {
+            board[i] = match c {
+                'W' | 'B' | '-' => *c,
+                _ => return Err(ParseError::unexpected(2 + i, "'W', 'B' or '-'")),
+            };
+        }
+        Ok(PentagoGame { board, player, rotating })
+    }
+
+    fn moves(&self) -> Vec<Move<Self>> {
+        if self.end_game().is_some() {
+            return Vec::new();
+        }
+        let mut v = Vec::new();
+        if self.rotating {
+            for q in 0..4 {
+                for &clockwise in &[true, false] {
+                    let mut next_state = self.clone();
+                    next_state.rotate(q, clockwise);
+                    next_state.rotating = false;
+                    next_state.player = opponent(self.player);
+                    let end_game = next_state.end_game();
+                    let id = ROTATE + 2 * q as MoveId + if clockwise { 0 } else { 1 };
+                    v.push(Move { id, end_game, next_state });
+                }
+            }
+        } else {
+            for i in (0..self.board.len()).filter(|i| self.board[*i] == '-') {
+                let mut next_state = self.clone();
+                next_state.board[i] = self.player;
+                next_state.rotating = true;
+                let victors = next_state.victors();
+                let end_game = if victors.is_empty() { None } else { Some(victors) };
+                v.push(Move { id: i as MoveId + 1, end_game, next_state });
+            }
+        }
+        v
+    }
+
+    /// Placements are named by cell, like "c4", and rotations by quadrant
+    /// and direction, like "NE cw" or "SW ccw".
+    fn move_name(&self, id: MoveId) -> String {
+        if id >= ROTATE {
+            let q = ((id - ROTATE) / 2) as usize;
+            let direction = match (id - ROTATE) % 2 { 0 => "cw", _ => "ccw" };
+            format!("{} {}", QUADRANTS[q], direction)
+        } else {
+            cell_name(id as usize - 1)
+        }
+    }
+
+    fn move_long_name(&self, id: MoveId) -> String {
+        if id >= ROTATE {
+            let q = ((id - ROTATE) / 2) as usize;
+            let direction = match (id - ROTATE) % 2 { 0 => "clockwise", _ => "counter-clockwise" };
+            format!("turn the {} quadrant {}", QUADRANTS[q], direction)
+        } else {
+            format!("place on {}", cell_name(id as usize - 1))
+        }
+    }
+
+    fn render_to_text(&self) -> String {
+        let mut s = String::from("   a b c   d e f\n");
+        for r in 0..SIZE {
+            if r == SIZE / 2 {
+                s.push_str("  -------+-------\n");
+            }
+            s.push_str(&format!("{} ", r + 1));
+            for c in 0..SIZE {
+                if c == SIZE / 2 {
+                    s.push_str(" |");
+                }
+                let cell = self.board[r * SIZE + c];
+                s.push(' ');
+                s.push(if cell == '-' { '.' } else { cell });
+            }
+            s.push('\n');
+        }
+        match self.end_game() {
+            Some(victors) if victors.len() > 1 => s.push_str("both players have five in a row\n"),
+            Some(victors) if victors.len() == 1 => s.push_str(&format!("{} has five in a row\n", victors[0])),
+            Some(_) => s.push_str("the board is full\n"),
+            None if self.rotating => s.push_str(&format!("{} to rotate a quadrant\n", self.player)),
+            None => s.push_str(&format!("{} to place a marble\n", self.player)),
+        }
+        s
+    }
+
+    /// A decisive victory or loss once someone has five in a row (and
+    /// nothing when both do, or neither can); before that, a score for the
+    /// rows each player could still complete, counting those nearer
+    /// completion for much more.
+    fn value_for(&self, p: Player) -> i64 {
+        if let Some(victors) = self.end_game() {
+            return match (victors.contains(&p), victors.len()) {
+                (true, 1) => VICTORY,
+                (false, 1) => -VICTORY,
+                _ => 0,
+            };
+        }
+        let score = |p: Player| -> i64 {
+            lines::open_lines(&self.board, winning_lines(), &p, &'-')
+                .map(|held| 1 << (3 * held))
+                .sum()
+        };
+        score(p) - score(opponent(p))
+    }
+
+    fn outcome(&self) -> Outcome {
+        Outcome::from_end_game(self.end_game())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_core::play;
+
+    /// A game with `player` to move (and to rotate, if `rotating`), and
+    /// marbles on the named cells.
+    fn position(player: Player, rotating: bool, white: &[&str], black: &[&str]) -> PentagoGame {
+        let mut g = PentagoGame { board: ['-'; SIZE * SIZE], player, rotating };
+        for (names, c) in [(white, 'W'), (black, 'B')] {
+            for name in names {
+                let name = name.as_bytes();
+                g.board[(name[1] - b'1') as usize * SIZE + (name[0] - b'a') as usize] = c;
+            }
+        }
+        g
+    }
+
+    #[test]
+    fn a_turn_is_placing_then_rotating_a_quadrant_either_way() {
+        let placed = play(&PentagoGame::default(), "a1").next_state;
+        assert_eq!((placed.player, placed.rotating), ('W', true));
+        assert_eq!(placed.moves().len(), 8);
+
+        let cw = play(&placed, "NW cw");
+        assert_eq!(cw.id, ROTATE);
+        assert_eq!(cw.next_state.board, position('B', false, &["c1"], &[]).board);
+        assert_eq!((cw.next_state.player, cw.next_state.rotating), ('B', false));
+        let ccw = play(&placed, "NW ccw");
+        assert_eq!(ccw.next_state.board, position('B', false, &["a3"], &[]).board);
+        // The other quadrants turn without moving the marble.
+        assert_eq!(play(&placed, "SE cw").next_state.board, placed.board);
+    }
+
+    #[test]
+    fn placing_five_in_a_row_wins_without_rotating() {
+        let g = position('W', false, &["a1", "b1", "c1", "d1"], &["a2", "b2", "c2", "d2"]);
+        let m = play(&g, "e1");
+        assert_eq!(m.end_game, Some(Some('W').into_iter().collect()));
+        assert_eq!(m.next_state.outcome(), Outcome::win('W'));
+        assert!(m.next_state.moves().is_empty());
+    }
+
+    #[test]
+    fn a_rotation_can_make_both_players_victors() {
+        // Turning NE clockwise brings d3 and d2 up to d1 and e1, finishing
+        // W's top row, and f3 and f2 down to d3 and e3, finishing B's.
+        let g = position('W', true, &["a1", "b1", "c1", "d2", "d3"], &["a3", "b3", "c3", "f2", "f3"]);
+        assert_eq!(g.outcome(), Outcome::Ongoing);
+        let m = play(&g, "NE cw");
+        let both: SmallVec<[Player; 1]> = ['W', 'B'].iter().cloned().collect();
+        assert_eq!(m.end_game, Some(both));
+        assert_eq!(m.next_state.value_for('W'), 0);
+        assert_eq!(m.next_state.value_for('B'), 0);
+        assert!(m.next_state.moves().is_empty());
+    }
+
+    #[test]
+    fn a_full_board_is_a_draw_once_rotated() {
+        // Pairs of cells alternate along the rows, and the rows alternate
+        // down the board, so nobody has five in a row.
+        let cells: String = (0..SIZE * SIZE)
+            .map(|i| if (i % SIZE / 2 + i / SIZE).is_multiple_of(2) { 'W' } else { 'B' })
+            .collect();
+        let full = PentagoGame::parse(&format!("Wp{}", cells)).unwrap();
+        assert_eq!(full.outcome(), Outcome::Draw);
+        assert!(full.moves().is_empty());
+
+        // Filling the last cell still leaves the rotation to make.
+        let rotating = PentagoGame::parse(&format!("Br{}", cells)).unwrap();
+        assert_eq!(rotating.outcome(), Outcome::Ongoing);
+        assert_eq!(rotating.moves().len(), 8);
+    }
+}
@@ -0,0 +1,103 @@
+//! Counts the game tree below a position, the way chess programmers count
+//! ("perft") to check move generation: a single missing or extra move shows
+//! up as a count that differs from the known one.
+//!
+//! Usage: `local perft <game> [depth] [state]`, from the start of the game
+//! unless a state is given.
+
+use std::collections::HashSet;
+
+use crate::dyn_game::DynGame;
+use crate::registry;
+
+/// What lies within some number of moves of a position.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Counts {
+    /// How many lines of play are `d` moves long, at index `d`: the leaf
+    /// nodes of the tree `d` moves deep. Lines that end the game sooner are
+    /// not among them.
+    pub leaves: Vec<u64>,
+    /// How many lines end the game.
+    pub games: u64,
+    /// How many different positions there are, the first one included.
+    pub positions: usize,
+}
+
+/// Counts every line of play from `state`, up to `depth` moves long.
+pub fn perft(state: &dyn DynGame, depth: usize) -> Counts {
+    let mut counts = Counts { leaves: vec![0; depth + 1], games: 0, positions: 0 };
+    let mut seen = HashSet::new();
+    walk(state, 0, &mut counts, &mut seen);
+    counts.positions = seen.len();
+    counts
+}
+
+fn walk(state: &dyn DynGame, ply: usize, counts: &mut Counts, seen: &mut HashSet<String>) {
+    counts.leaves[ply] += 1;
+    seen.insert(state.unparse());
+    if ply + 1 == counts.leaves.len() {
+        // No deeper, but this line may still have ended the game.
+        if state.outcome().is_over() {
+            counts.games += 1;
+        }
+        return;
+    }
+    let moves = state.moves();
+    if moves.is_empty() {
+        counts.games += 1;
+    }
+    for m in &moves {
+        walk(&*m.next_state, ply + 1, counts, seen);
+    }
+}
+
+/// Counts the tree of the game named by the first of `args`, as many moves
+/// deep as the second says, from the state in the third.
+pub fn run(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
+    let name = args.next().unwrap_or_else(|| registry::default_game().name.to_string());
+    let game_type = registry::find(&name).ok_or_else(|| {
+        format!("unknown game `{}`; the games are {}", name, registry::names().join(", "))
+    })?;
+    let depth = match args.next() {
+        Some(n) => n.parse()?,
+        None => 5,
+    };
+    let game = match args.next() {
+        Some(state) => game_type.parse(&state)?,
+        None => game_type.new_game_with("")?,
+    };
+
+    let counts = perft(&*game, depth);
+    println!("{} from {:?}", game_type.name, game.unparse());
+    println!("{:>5} {:>15}", "depth", "leaves");
+    for (d, leaves) in counts.leaves.iter().enumerate() {
+        println!("{:>5} {:>15}", d, leaves);
+    }
+    println!("{} complete games and {} distinct positions within {} moves",
+             counts.games, counts.positions, depth);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::othello::OthelloGame;
+    use crate::tictactoe::TicTacToeGame;
+
+    #[test]
+    fn tictactoe() {
+        let counts = perft(&TicTacToeGame::default(), 9);
+        assert_eq!(counts.leaves,
+                   [1, 9, 72, 504, 3024, 15120, 54720, 148176, 200448, 127872]);
+        assert_eq!(counts.games, 255168);
+        assert_eq!(counts.positions, 5478);
+    }
+
+    /// The counts of games after each ply are OEIS A124004.
+    #[test]
+    fn othello() {
+        let counts = perft(&OthelloGame::default(), 6);
+        assert_eq!(counts.leaves, [1, 4, 12, 56, 244, 1396, 8200]);
+        assert_eq!(counts.games, 0);
+    }
+}
@@ -0,0 +1,600 @@
+use crate::cfr::{Cfr, Strategy};
+use crate::game_core::{Game, Move, MoveId, Outcome, ParseError, Player, Rng, CHANCE, VICTORY};
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::sync::OnceLock;
+
+const RANKS: [char; 3] = ['J', 'Q', 'K'];
+
+const FOLD: MoveId = 1;
+/// Checking, or calling a bet.
+const CALL: MoveId = 2;
+/// Betting, or raising a bet.
+const RAISE: MoveId = 3;
+
+/// The rules that tell the poker games apart.
+pub trait Variant: Clone + Debug + Default + 'static {
+    const NAME: &'static str;
+    /// How many of each card (jack, queen and king) the deck holds.
+    const COPIES: u8;
+    /// The size of a bet or raise in each betting round. A card is turned
+    /// face up for both players between rounds.
+    const BET_SIZES: &'static [u32];
+    /// How many bets and raises each round allows.
+    const MAX_RAISES: usize;
+    /// How many iterations of CFR train the strategy the AI plays.
+    const TRAINING_ITERATIONS: usize;
+
+    /// The strategy the AI plays, trained by CFR the first time it is needed.
+    fn strategy() -> &'static Strategy;
+}
+
+/// Kuhn poker: three cards, one each, and a single round with bets of 1.
+#[derive(Clone, Debug, Default)]
+pub struct Kuhn;
+
+impl Variant for Kuhn {
+    const NAME: &'static str = "KuhnPoker";
+    const COPIES: u8 = 1;
+    const BET_SIZES: &'static [u32] = &[1];
+    const MAX_RAISES: usize = 1;
+    const TRAINING_ITERATIONS: usize = 1000;
+
+    fn strategy() -> &'static Strategy {
+        static STRATEGY: OnceLock<Strategy> = OnceLock::new();
+        STRATEGY.get_or_init(train::<Kuhn>)
+    }
+}
+
+/// Leduc hold'em: six cards (two of each), one each and one face up between
+/// the rounds, with bets of 2 and then 4 and up to two per round. A player
+/// whose card pairs the face-up card wins the showdown.
+#[derive(Clone, Debug, Default)]
+pub struct Leduc;
+
+impl Variant for Leduc {
+    const NAME: &'static str = "LeducPoker";
+    const COPIES: u8 = 2;
+    const BET_SIZES: &'static [u32] = &[2, 4];
+    const MAX_RAISES: usize = 2;
+    const TRAINING_ITERATIONS: usize = 300;
+
+    fn strategy() -> &'static Strategy {
+        static STRATEGY: OnceLock<Strategy> = OnceLock::new();
+        STRATEGY.get_or_init(train::<Leduc>)
+    }
+}
+
+fn train<V: Variant>() -> Strategy {
+    let mut cfr = Cfr::new(PokerGame::<V>::undealt());
+    cfr.train(V::TRAINING_ITERATIONS);
+    cfr.strategy()
+}
+
+pub type KuhnPokerGame = PokerGame<Kuhn>;
+pub type LeducPokerGame = PokerGame<Leduc>;
+
+/// A hand of poker with a small deck of jacks, queens and kings, for players
+/// 'A' and 'B', who each ante 1 chip. Cards are dealt by chance moves, and
+/// each player sees only their own (and any face-up card).
+///
+/// In each betting round, A acts first. A player not facing a bet may check
+/// (move 2) or bet (move 3); one facing a bet may fold (move 1), call (move
+/// 2) or raise (move 3). The round ends on a call, or on a check by B after
+/// A checked. If nobody folds, the higher card wins at the showdown (unless
+/// a card pairs the face-up card), and equal cards split the pot.
+///
+/// Finished hands are valued at `VICTORY` per chip won.
+#[derive(Clone, Debug)]
+pub struct PokerGame<V> {
+    /// The ranks of A's and B's cards (0 to 2, for jack to king), once dealt.
+    pub cards: [Option<u8>; 2],
+    /// The rank of the face-up card, once dealt.
+    pub public: Option<u8>,
+    /// The actions of each betting round so far: 'f' to fold, 'c' to check
+    /// or call, 'r' to bet or raise.
+    pub rounds: Vec<String>,
+    _variant: PhantomData<V>,
+}
+
+impl<V: Variant> Default for PokerGame<V> {
+    /// A hand with both players' cards dealt at random.
+    fn default() -> Self {
+        Self::random_start(&mut Rng::from_entropy())
+    }
+}
+
+fn opponent(p: Player) -> Player {
+    if p == 'A' { 'B' } else { 'A' }
+}
+
+fn index(p: Player) -> usize {
+    if p == 'A' { 0 } else { 1 }
+}
+
+fn rank_name(rank: Option<u8>) -> char {
+    rank.map_or('0', |r| RANKS[r as usize])
+}
+
+/// Whether a betting round with these actions is over.
+fn round_over(actions: &str) -> bool {
+    actions.ends_with('f') || (actions.len() >= 2 && actions.ends_with('c'))
+}
+
+impl<V: Variant> PokerGame<V> {
+    /// A hand before any cards are dealt, as CFR trains from.
+    pub fn undealt() -> Self {
+        PokerGame { cards: [None, None], public: None, rounds: vec![String::new()], _variant: PhantomData }
+    }
+
+    fn actions(&self) -> &str {
+        self.rounds.last().unwrap()
+    }
+
+    fn folded(&self) -> bool {
+        self.actions().ends_with('f')
+    }
+
+    fn is_over(&self) -> bool {
+        self.folded() || (round_over(self.actions()) && self.rounds.len() == V::BET_SIZES.len())
+    }
+
+    /// The player to act in the current betting round.
+    fn actor(&self) -> Player {
+        match self.actions().len() % 2 {
+            0 => 'A',
+            _ => 'B',
+        }
+    }
+
+    /// How many chips A and B have put in the pot.
+    fn contributions(&self) -> [u32; 2] {
+        let mut put_in = [1, 1];
+        for (round, actions) in self.rounds.iter().enumerate() {
+            for (k, action) in actions.chars().enumerate() {
+                let (me, other) = (k % 2, 1 - k % 2);
+                match action {
+                    'c' => put_in[me] = put_in[other],
+                    'r' => put_in[me] = put_in[other] + V::BET_SIZES[round],
+                    _ => {}
+                }
+            }
+        }
+        put_in
+    }
+
+    fn facing_bet(&self) -> bool {
+        let put_in = self.contributions();
+        let me = index(self.actor());
+        put_in[me] < put_in[1 - me]
+    }
+
+    /// How many copies of `rank` are left in the deck.
+    fn left_in_deck(&self, rank: u8) -> u8 {
+        let dealt = self.cards.iter().chain(Some(&self.public)).filter(|c| **c == Some(rank)).count();
+        V::COPIES - dealt as u8
+    }
+
+    /// The chips that `p` wins (or, if negative, loses) in a finished hand.
+    fn winnings(&self, p: Player) -> i64 {
+        let put_in = self.contributions();
+        let pot_share = |q: Player| put_in[index(q)] as i64;
+        let winner = if self.folded() {
+            // The last to act folded.
+            let folder = if self.actions().len() % 2 == 1 { 'A' } else { 'B' };
+            Some(opponent(folder))
+        } else {
+            let strength = |q: Player| {
+                let card = self.cards[index(q)].unwrap();
+                if Some(card) == self.public { 10 + card } else { card }
+            };
+            match strength('A').cmp(&strength('B')) {
+                std::cmp::Ordering::Greater => Some('A'),
+                std::cmp::Ordering::Less => Some('B'),
+                std::cmp::Ordering::Equal => None,
+            }
+        };
+        match winner {
+            Some(w) if w == p => pot_share(opponent(p)),
+            Some(_) => -pot_share(p),
+            None => 0,
+        }
+    }
+
+    fn action_name(&self, id: MoveId) -> &'static str {
+        let opened = self.actions().contains('r');
+        match id {
+            FOLD => "fold",
+            CALL if self.facing_bet() => "call",
+            CALL => "check",
+            _ if opened => "raise",
+            _ => "bet",
+        }
+    }
+
+    fn describe_rounds(&self) -> String {
+        let mut s = String::new();
+        for (round, actions) in self.rounds.iter().enumerate() {
+            if actions.is_empty() { continue; }
+            let mut replay = PokerGame::<V> { rounds: self.rounds[..round].to_vec(), ..self.clone() };
+            replay.rounds.push(String::new());
+            let mut names = Vec::new();
+            for action in actions.chars() {
+                let id = match action { 'f' => FOLD, 'c' => CALL, _ => RAISE };
+                names.push(format!("{} {}", replay.actor(), replay.action_name(id)));
+                replay.rounds.last_mut().unwrap().push(action);
+            }
+            s.push_str(&format!("round {}: {}\n", round + 1, names.join(", ")));
+        }
+        s
+    }
+
+    /// Whether the hand went to a showdown, where both cards are shown.
+    fn shown_down(&self) -> bool {
+        self.is_over() && !self.folded()
+    }
+
+    fn render(&self, p: Option<Player>) -> String {
+        let mut s = String::new();
+        for q in &['A', 'B'] {
+            let card = match p {
+                Some(p) if p != *q && !self.shown_down() => '?',
+                _ => rank_name(self.cards[index(*q)]),
+            };
+            let you = if p == Some(*q) { " (you)" } else { "" };
+            s.push_str(&format!("{}{}: {}\n", q, you, card));
+        }
+        if V::BET_SIZES.len() > 1 {
+            s.push_str(&format!("face up: {}\n", self.public.map_or('-', |r| RANKS[r as usize])));
+        }
+        let put_in = self.contributions();
+        s.push_str(&format!("pot: {} (A put in {}, B put in {})\n", put_in[0] + put_in[1], put_in[0], put_in[1]));
+        s.push_str(&self.describe_rounds());
+        if self.is_over() {
+            let won = self.winnings('A');
+            match won.signum() {
+                1 => s.push_str(&format!("A wins {}\n", won)),
+                -1 => s.push_str(&format!("B wins {}\n", -won)),
+                _ => s.push_str("the pot is split\n"),
+            }
+        } else if self.current_player() == CHANCE {
+            s.push_str("to deal\n");
+        } else {
+            s.push_str(&format!("{} to act\n", self.actor()));
+        }
+        s
+    }
+}
+
+impl<V: Variant> Game for PokerGame<V> {
+    const NAME: &'static str = V::NAME;
+
+    const HIDDEN_INFORMATION: bool = true;
+
+    const HAS_CHANCE_MOVES: bool = true;
+
+    /// A hand with both players' cards dealt.
+    fn random_start(rng: &mut Rng) -> Self {
+        let mut g = Self::undealt();
+        while g.current_player() == CHANCE {
+            let moves = g.moves();
+            g = crate::game_core::sample_chance(&g, &moves, rng).next_state.clone();
+        }
+        g
+    }
+
+    fn current_player(&self) -> Player {
+        if self.cards.contains(&None) {
+            CHANCE
+        } else if !self.is_over() && round_over(self.actions()) {
+            // The face-up card is yet to be dealt.
+            CHANCE
+        } else {
+            self.actor()
+        }
+    }
+
+    /// The serialized form is A's card, B's card and the face-up card (each
+    /// 'J', 'Q', 'K', or '0' if not dealt yet), then the actions of each
+    /// betting round, each round after a '-'. For example, "KQ0-cr" is a
+    /// hand where A has a king, B a queen, A checked and B bet.
+    fn unparse(&self) -> String {
+        let mut s: String = self.cards.iter().chain(Some(&self.public)).map(|c| rank_name(*c)).collect();
+        for actions in &self.rounds {
+            s.push('-');
+            s.push_str(actions);
+        }
+        s
+    }
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let mut parts = input.split('-');
+        let cards: Vec<char> = parts.next().unwrap_or("").chars().collect();
+        if cards.len() != 3 {
+            return Err(ParseError::wrong_length("three cards: A's, B's and the face-up one").at(cards.len().min(3)));
+        }
+        let mut ranks = [None; 3];
+        for (k, c) in cards.iter().enumerate() {
+            ranks[k] = match c {
+                '0' => None,
+                c => match RANKS.iter().position(|r| r == c) {
+                    Some(r) => Some(r as u8),
+                    None => return Err(ParseError::unexpected(k, "a card: J, Q, K or 0")),
+                },
+            };
+        }
+        let mut g = PokerGame::<V> { cards: [ranks[0], ranks[1]], public: ranks[2], ..Self::undealt() };
+        for rank in 0..RANKS.len() as u8 {
+            if ranks.iter().filter(|r| **r == Some(rank)).count() > V::COPIES as usize {
+                return Err(ParseError::invalid(format!("at most {} {}s, as in the deck", V::COPIES, RANKS[rank as usize])));
+            }
+        }
+        if g.public.is_some() && V::BET_SIZES.len() == 1 {
+            return Err(ParseError::unexpected(2, "'0', as there is no face-up card in this game"));
+        }
+
+        // Replay the betting, to check it was legal.
+        let rounds: Vec<&str> = parts.collect();
+        if rounds.is_empty() {
+            return Err(ParseError::wrong_length("'-' and the betting after the cards").at(3));
+        }
+        // Where the round being replayed starts in the input, past its '-'.
+        let mut at = 4;
+        for (round, actions) in rounds.iter().enumerate() {
+            if round > 0 {
+                if g.public.is_none() || !round_over(g.actions()) || g.folded() || round >= V::BET_SIZES.len() {
+                    return Err(ParseError::invalid("betting rounds after finished rounds and the face-up card").at(at - 1));
+                }
+                g.rounds.push(String::new());
+            }
+            for (i, action) in actions.chars().enumerate() {
+                let id = match action {
+                    'f' => FOLD,
+                    'c' => CALL,
+                    'r' => RAISE,
+                    _ => return Err(ParseError::unexpected(at + i, "an action, f, c or r")),
+                };
+                if g.current_player() == CHANCE || !g.moves().iter().any(|m| m.id == id) {
+                    return Err(ParseError::invalid(format!("a legal action after {}", g.unparse())).at(at + i));
+                }
+                g.rounds.last_mut().unwrap().push(action);
+            }
+            at += actions.chars().count() + 1;
+        }
+        if g.public.is_some() && g.rounds.len() < 2 {
+            return Err(ParseError::invalid("no face-up card until the first round is over").at(2));
+        }
+        Ok(g)
+    }
+
+    fn moves(&self) -> Vec<Move<Self>> {
+        if self.is_over() {
+            return Vec::new();
+        }
+        let mut v = Vec::new();
+        if self.current_player() == CHANCE {
+            for rank in 0..RANKS.len() as u8 {
+                if self.left_in_deck(rank) == 0 { continue; }
+                let mut next_state = self.clone();
+                if self.cards[0].is_none() {
+                    next_state.cards[0] = Some(rank);
+                } else if self.cards[1].is_none() {
+                    next_state.cards[1] = Some(rank);
+                } else {
+                    next_state.public = Some(rank);
+                    next_state.rounds.push(String::new());
+                }
+                v.push(Move { id: rank as MoveId + 1, end_game: None, next_state });
+            }
+            return v;
+        }
+
+        let raises = self.actions().matches('r').count();
+        let mut actions = Vec::new();
+        if self.facing_bet() {
+            actions.push((FOLD, 'f'));
+        }
+        actions.push((CALL, 'c'));
+        if raises < V::MAX_RAISES {
+            actions.push((RAISE, 'r'));
+        }
+        for (id, action) in actions {
+            let mut next_state = self.clone();
+            next_state.rounds.last_mut().unwrap().push(action);
+            let end_game = next_state.outcome().end_game();
+            v.push(Move { id, end_game, next_state });
+        }
+        v
+    }
+
+    /// Dealing a card to a player is named without saying what it is, like
+    /// "deal to A", and turning up a card names it, like "turn up K".
+    fn move_name(&self, id: MoveId) -> String {
+        if self.current_player() == CHANCE {
+            if self.cards[0].is_none() {
+                "deal to A".to_string()
+            } else if self.cards[1].is_none() {
+                "deal to B".to_string()
+            } else {
+                format!("turn up {}", RANKS[id as usize - 1])
+            }
+        } else {
+            self.action_name(id).to_string()
+        }
+    }
+
+    fn render_to_text(&self) -> String {
+        self.render(None)
+    }
+
+    fn value_for(&self, p: Player) -> i64 {
+        if self.is_over() { VICTORY * self.winnings(p) } else { 0 }
+    }
+
+    /// Whoever takes chips from the pot wins; a split pot is a draw.
+    fn outcome(&self) -> Outcome {
+        if !self.is_over() {
+            return Outcome::Ongoing;
+        }
+        match self.winnings('A').signum() {
+            1 => Outcome::win('A'),
+            -1 => Outcome::win('B'),
+            _ => Outcome::Draw,
+        }
+    }
+
+    /// A player sees their own card, the face-up card and the betting (and,
+    /// after a showdown, the other card too): `p`, their card and the face-up
+    /// card, then the betting as in the serialized form.
+    fn observation(&self, p: Player) -> String {
+        let other = if self.shown_down() { self.cards[index(opponent(p))] } else { None };
+        let mut s = String::new();
+        s.push(p);
+        s.push(rank_name(self.cards[index(p)]));
+        s.push(rank_name(other));
+        s.push(rank_name(self.public));
+        for actions in &self.rounds {
+            s.push('-');
+            s.push_str(actions);
+        }
+        s
+    }
+
+    fn render_for(&self, p: Player) -> String {
+        self.render(Some(p))
+    }
+
+    /// The other player's card is drawn at random from the cards `p` has not
+    /// seen.
+    fn determinize(&self, p: Player, seed: u64) -> Self {
+        let mut guess = self.clone();
+        let other = index(opponent(p));
+        if self.shown_down() || guess.cards[other].is_none() {
+            return guess;
+        }
+        guess.cards[other] = None;
+        let unseen: Vec<u8> = (0..RANKS.len() as u8)
+            .flat_map(|rank| std::iter::repeat_n(rank, guess.left_in_deck(rank) as usize))
+            .collect();
+        guess.cards[other] = Some(unseen[Rng::new(seed).below(unseen.len())]);
+        guess
+    }
+
+    fn chance_weight(&self, id: MoveId) -> u32 {
+        self.left_in_deck(id as u8 - 1) as u32
+    }
+
+    /// The strategy trained by CFR, for what the player to move can see.
+    fn policy(&self) -> Option<Vec<(MoveId, f64)>> {
+        let p = self.current_player();
+        if p == CHANCE {
+            return None;
+        }
+        V::strategy().policy(&self.observation(p)).map(|policy| policy.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kuhn(state: &str) -> KuhnPokerGame {
+        KuhnPokerGame::parse(state).unwrap()
+    }
+
+    fn leduc(state: &str) -> LeducPokerGame {
+        LeducPokerGame::parse(state).unwrap()
+    }
+
+    fn ids<V: Variant>(g: &PokerGame<V>) -> Vec<MoveId> {
+        g.moves().iter().map(|m| m.id).collect()
+    }
+
+    #[test]
+    fn a_fold_gives_up_the_chips_put_in() {
+        let g = kuhn("KQ0-rf");
+        assert_eq!(g.contributions(), [2, 1]);
+        assert_eq!((g.winnings('A'), g.winnings('B')), (1, -1));
+        assert_eq!(g.outcome(), Outcome::win('A'));
+        // Even with the better card.
+        assert_eq!(kuhn("KQ0-crf").winnings('A'), -1);
+    }
+
+    #[test]
+    fn the_better_card_takes_the_pot_at_showdown() {
+        assert_eq!(kuhn("JK0-cc").winnings('B'), 1);
+        let g = kuhn("QK0-rc");
+        assert_eq!(g.contributions(), [2, 2]);
+        assert_eq!(g.winnings('A'), -2);
+        assert_eq!(g.value_for('B'), 2 * VICTORY);
+
+        // Bets of 2 and then 4 in Leduc: A opens and B calls, then A bets,
+        // B raises and A calls.
+        let g = leduc("QKJ-rc-rrc");
+        assert_eq!(g.contributions(), [11, 11]);
+        assert_eq!(g.winnings('B'), 11);
+        // A pair with the face-up card beats a higher card.
+        assert_eq!(leduc("JKJ-cc-cc").winnings('A'), 1);
+        // Equal cards split the pot.
+        let g = leduc("KKQ-rc-cc");
+        assert_eq!(g.winnings('A'), 0);
+        assert_eq!(g.outcome(), Outcome::Draw);
+    }
+
+    #[test]
+    fn raises_are_capped_each_round() {
+        assert_eq!(ids(&kuhn("KQ0-r")), [FOLD, CALL]);
+        assert!(KuhnPokerGame::parse("KQ0-rr").is_err());
+        assert_eq!(ids(&leduc("KQ0-r")), [FOLD, CALL, RAISE]);
+        assert_eq!(ids(&leduc("KQ0-rr")), [FOLD, CALL]);
+        assert!(LeducPokerGame::parse("KQ0-rrr").is_err());
+        // The cap starts afresh in the next round.
+        assert_eq!(ids(&leduc("KQJ-rrc-r")), [FOLD, CALL, RAISE]);
+    }
+
+    #[test]
+    fn parse_rejects_betting_out_of_turn() {
+        // Nothing follows a fold, in the same round or the next.
+        assert!(KuhnPokerGame::parse("KQ0-rfc").is_err());
+        assert!(LeducPokerGame::parse("KQJ-rf-c").is_err());
+        // The face-up card comes once the first round is over, not before.
+        assert!(LeducPokerGame::parse("KQJ-c").is_err());
+        assert!(LeducPokerGame::parse("KQJ-").is_err());
+        assert!(LeducPokerGame::parse("KQ0-cc-").is_err());
+        assert!(LeducPokerGame::parse("KQJ-cc-").is_ok());
+        // Kuhn poker has no face-up card at all.
+        assert!(KuhnPokerGame::parse("KQJ-").is_err());
+    }
+
+    #[test]
+    fn the_other_card_is_hidden_until_showdown() {
+        let g = leduc("KQJ-cc-r");
+        assert_eq!(g.observation('A'), "AK0J-cc-r");
+        assert_eq!(g.observation('B'), "BQ0J-cc-r");
+        assert_eq!(g.observation('A'), leduc("KKJ-cc-r").observation('A'));
+        assert!(g.render_for('A').contains("B: ?"));
+
+        // A fold shows nothing.
+        assert_eq!(kuhn("KQ0-rf").observation('B'), "BQ00-rf");
+        // A showdown shows everything.
+        let g = leduc("KQJ-cc-cc");
+        assert_eq!(g.observation('A'), "AKQJ-cc-cc");
+        assert!(g.render_for('A').contains("B: Q"));
+    }
+
+    #[test]
+    fn chance_follows_the_cards_left_in_the_deck() {
+        let weights = |g: &LeducPokerGame| -> Vec<(MoveId, u32)> {
+            g.moves().iter().map(|m| (m.id, g.chance_weight(m.id))).collect()
+        };
+        assert_eq!(weights(&LeducPokerGame::undealt()), [(1, 2), (2, 2), (3, 2)]);
+        assert_eq!(weights(&leduc("K00-")), [(1, 2), (2, 2), (3, 1)]);
+        // Both kings are out, so the face-up card is a jack or a queen.
+        let g = leduc("KK0-cc");
+        assert_eq!(g.current_player(), CHANCE);
+        assert_eq!(weights(&g), [(1, 2), (2, 2)]);
+        // Kuhn poker has one of each.
+        let g = kuhn("K00-");
+        assert_eq!(g.moves().iter().map(|m| (m.id, g.chance_weight(m.id))).collect::<Vec<_>>(), [(1, 1), (2, 1)]);
+    }
+}
@@ -0,0 +1,293 @@
+use crate::game_core::{Game, Move, MoveId, Outcome, ParseError, Player, VICTORY};
+use smallvec::SmallVec;
+
+/// Each piece is a number from 0 to 15 whose four bits are its attributes;
+/// a piece is named by a letter for each attribute, e.g. "TDQH" for the tall,
+/// dark, square, hollow piece and "SLRF" for its opposite.
+const ATTRIBUTES: [(char, char); 4] = [('S', 'T'), ('L', 'D'), ('R', 'Q'), ('F', 'H')];
+/// The same attributes, spelled out.
+const ATTRIBUTE_NAMES: [(&str, &str); 4] = [("short", "tall"), ("light", "dark"), ("round", "square"), ("flat", "hollow")];
+
+const PIECES: u8 = 16;
+
+/// Choosing piece `n` for the opponent is move `CHOOSE + n`.
+pub const CHOOSE: MoveId = 100;
+
+/// The rows, columns and diagonals of the board.
+const LINES: [[usize; 4]; 10] = [
+    [0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11], [12, 13, 14, 15],
+    [0, 4, 8, 12], [1, 5, 9, 13], [2, 6, 10, 14], [3, 7, 11, 15],
+    [0, 5, 10, 15], [3, 6, 9, 12],
+];
+
+/// Quarto on a 4x4 board, for players 'A' and 'B'. The 16 pieces are each
+/// tall or short, light or dark, round or square, and solid (flat) or hollow.
+/// Whoever completes a line of four pieces that share an attribute wins.
+///
+/// Each turn has two actions: first the player places the piece their
+/// opponent gave them (move ids 1 to 16, the cells row by row), then they
+/// choose the piece their opponent must place next (move ids `CHOOSE` and
+/// up). The game opens with A choosing a piece for B.
+#[derive(Clone, Debug)]
+pub struct QuartoGame {
+    pub board: [Option<u8>; 16],
+    /// The piece the player must place, or `None` if they are to choose one.
+    pub given: Option<u8>,
+    pub player: Player,
+}
+
+impl Default for QuartoGame {
+    fn default() -> Self {
+        QuartoGame { board: [None; 16], given: None, player: 'A' }
+    }
+}
+
+fn opponent(p: Player) -> Player {
+    if p == 'A' { 'B' } else { 'A' }
+}
+
+fn piece_name(piece: u8) -> String {
+    ATTRIBUTES.iter()
+        .enumerate()
+        .map(|(bit, (off, on))| if piece & (1 << bit) != 0 { *on } else { *off })
+        .collect()
+}
+
+fn cell_name(i: usize) -> String {
+    format!("{}{}", (b'a' + (i % 4) as u8) as char, i / 4 + 1)
+}
+
+impl QuartoGame {
+    /// The pieces that are neither on the board nor in hand.
+    pub fn pool(&self) -> Vec<u8> {
+        (0..PIECES)
+            .filter(|piece| Some(*piece) != self.given && !self.board.contains(&Some(*piece)))
+            .collect()
+    }
+
+    /// Whether some line is full of pieces sharing an attribute.
+    fn has_quarto(&self) -> bool {
+        LINES.iter().any(|line| {
+            let pieces: Option<Vec<u8>> = line.iter().map(|i| self.board[*i]).collect();
+            match pieces {
+                // An attribute is shared if every piece has its bit set, or
+                // every piece has it clear.
+                Some(pieces) => {
+                    let all = pieces.iter().fold(0xf, |acc, p| acc & p);
+                    let none = pieces.iter().fold(0xf, |acc, p| acc & !p);
+                    (all | none) & 0xf != 0
+                }
+                None => false,
+            }
+        })
+    }
+
+    fn is_over(&self) -> bool {
+        self.has_quarto() || self.board.iter().all(|c| c.is_some())
+    }
+}
+
+impl Game for QuartoGame {
+    const NAME: &'static str = "Quarto";
+
+    fn current_player(&self) -> Player {
+        self.player
+    }
+
+    /// The serialized form is the player to move, the piece they must place
+    /// (or '-' if they are to choose one), then the 16 cells row by row, each
+    /// a piece or '-'. Pieces are written as hexadecimal digits. For example,
+    /// the default game is "A-----------------".
+    fn unparse(&self) -> String {
+        let piece = |p: Option<u8>| p.map_or('-', |p| std::char::from_digit(p as u32, 16).unwrap());
+        let mut s = String::new();
+        s.push(self.player);
+        s.push(piece(self.given));
+        s.extend(self.board.iter().map(|c| piece(*c)));
+        s
+    }
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let chars: Vec<char> = input.chars().collect();
+        if chars.len() != 18 {
+            return Err(ParseError::wrong_length("the player to move, the piece in hand and 16 cells").at(chars.len().min(18)));
+        }
+        let player = match chars[0] {
+            p @ 'A' | p @ 'B' => p,
+            _ => return Err(ParseError::unexpected(0, "the player to move, A or B")),
+        };
+        let piece = |i: usize| match chars[i] {
+            '-' => Ok(None),
+            c => c.to_digit(16)
+                .map(|p| Some(p as u8))
+                .ok_or_else(|| ParseError::unexpected(i, "a piece as a hexadecimal digit, or '-'")),
+        };
+        let given = piece(1)?;
+        let mut board = [None; 16];
+        for (i, cell) in board.iter_mut().enumerate() {
+            *cell = piece(2 + i)?;
+        }
+        let mut seen = [false; PIECES as usize];
+        for p in board.iter().chain(Some(&given)).flatten() {
+            if seen[*p as usize] { return Err(ParseError::invalid(format!("piece {} used only once", piece_name(*p)))); }
+            seen[*p as usize] = true;
+        }
+        Ok(QuartoGame { board, given, player })
+    }
+
+    fn moves(&self) -> Vec<Move<Self>> {
+        if self.is_over() {
+            return Vec::new();
+        }
+        let mut v = Vec::new();
+        match self.given {
+            Some(piece) => {
+                for i in 0..self.board.len() {
+                    if self.board[i].is_some() { continue; }
+                    let mut next_state = self.clone();
+                    next_state.board[i] = Some(piece);
+                    next_state.given = None;
+                    let end_game = if next_state.has_quarto() {
+                        Some(Some(self.player).into_iter().collect())
+                    } else if next_state.board.iter().all(|c| c.is_some()) {
+                        Some(SmallVec::new())
+                    } else {
+                        None
+                    };
+                    v.push(Move { id: i as MoveId + 1, end_game, next_state });
+                }
+            }
+            None => {
+                for piece in self.pool() {
+                    let next_state = QuartoGame {
+                        board: self.board,
+                        given: Some(piece),
+                        player: opponent(self.player),
+                    };
+                    v.push(Move { id: CHOOSE + piece as MoveId, end_game: None, next_state });
+                }
+            }
+        }
+        v
+    }
+
+    /// Placements are named by cell, like "b3", and choices by the piece,
+    /// like "give TDQH".
+    fn move_name(&self, id: MoveId) -> String {
+        if id >= CHOOSE {
+            format!("give {}", piece_name((id - CHOOSE) as u8))
+        } else {
+            cell_name(id as usize - 1)
+        }
+    }
+
+    fn move_long_name(&self, id: MoveId) -> String {
+        if id >= CHOOSE {
+            let piece = (id - CHOOSE) as u8;
+            let words: Vec<&str> = ATTRIBUTE_NAMES.iter()
+                .enumerate()
+                .map(|(bit, (off, on))| if piece & (1 << bit) != 0 { *on } else { *off })
+                .collect();
+            format!("give the {} piece", words.join(" "))
+        } else {
+            format!("place on {}", cell_name(id as usize - 1))
+        }
+    }
+
+    fn render_to_text(&self) -> String {
+        let mut s = String::from("     a    b    c    d\n");
+        for r in 0..4 {
+            s.push_str(&format!("{} ", r + 1));
+            for c in 0..4 {
+                let cell = self.board[r * 4 + c].map_or("....".to_string(), piece_name);
+                s.push_str(&format!(" {}", cell));
+            }
+            s.push('\n');
+        }
+        let pool: Vec<String> = self.pool().into_iter().map(piece_name).collect();
+        s.push_str(&format!("pool: {}\n", pool.join(" ")));
+        s.push_str("(Tall/Short, Dark/Light, sQuare/Round, Hollow/Flat)\n");
+        match self.given {
+            _ if self.has_quarto() => s.push_str(&format!("{} completed a line\n", self.player)),
+            _ if self.is_over() => s.push_str("the board is full\n"),
+            Some(piece) => s.push_str(&format!("{} to place {}\n", self.player, piece_name(piece))),
+            None => s.push_str(&format!("{} to choose a piece for {}\n", self.player, opponent(self.player))),
+        }
+        s
+    }
+
+    /// Quarto positions are hard to judge without looking ahead, so only
+    /// finished games are scored: whoever placed the last piece either won or
+    /// filled the board for a draw.
+    fn value_for(&self, p: Player) -> i64 {
+        if self.has_quarto() {
+            // The winner keeps the turn after placing, so they are still the
+            // current player.
+            if self.player == p { VICTORY } else { -VICTORY }
+        } else {
+            0
+        }
+    }
+
+    fn outcome(&self) -> Outcome {
+        if self.has_quarto() {
+            Outcome::win(self.player)
+        } else if self.is_over() {
+            Outcome::Draw
+        } else {
+            Outcome::Ongoing
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_core::play;
+
+    /// `player` to place `given`, with the top row holding `row` from a1.
+    fn placing(player: Player, given: u8, row: &[u8]) -> QuartoGame {
+        let mut board = [None; 16];
+        for (i, piece) in row.iter().enumerate() {
+            board[i] = Some(*piece);
+        }
+        QuartoGame { board, given: Some(given), player }
+    }
+
+    #[test]
+    fn a_turn_is_placing_the_given_piece_then_choosing_the_next() {
+        let start = QuartoGame::default();
+        let choices = start.moves();
+        assert_eq!(choices.len(), 16);
+        assert_eq!(start.move_name(CHOOSE + 0xb), "give TDRH");
+        let given = &choices[0xb].next_state;
+        assert_eq!((given.current_player(), given.given), ('B', Some(0xb)));
+
+        let placed = play(given, "c2").next_state;
+        assert_eq!(placed.board[6], Some(0xb));
+        assert_eq!(placed.current_player(), 'B');
+        assert_eq!(placed.moves().len(), 15);
+        assert!(placed.moves().iter().all(|m| m.id >= CHOOSE && m.id != CHOOSE + 0xb));
+    }
+
+    #[test]
+    fn a_line_sharing_an_attribute_wins() {
+        // All tall.
+        let m = play(&placing('B', 7, &[1, 3, 5]), "d1");
+        assert_eq!(m.end_game, Some(Some('B').into_iter().collect()));
+        assert_eq!(m.next_state.outcome(), Outcome::win('B'));
+        assert_eq!(m.next_state.value_for('B'), VICTORY);
+        assert!(m.next_state.moves().is_empty());
+        // All short counts as much.
+        assert!(play(&placing('A', 6, &[0, 2, 4]), "d1").end_game.is_some());
+        // Each attribute once, and otherwise not: no line.
+        assert_eq!(play(&placing('A', 8, &[1, 2, 4]), "d1").end_game, None);
+    }
+
+    #[test]
+    fn each_piece_is_used_once() {
+        assert!(QuartoGame::parse("A11---------------").is_err());
+        assert!(QuartoGame::parse("A-1-1-------------").is_err());
+        assert!(QuartoGame::parse("A21---------------").is_ok());
+    }
+}
@@ -0,0 +1,274 @@
+use crate::game_core::{Game, MadeMove, Move, MoveId, Outcome, ParseError, Player, Undo, VICTORY};
+use crate::lines::{self, Lines};
+use smallvec::SmallVec;
+use std::sync::OnceLock;
+
+const SIZE: usize = 4;
+const CELLS: usize = SIZE * SIZE * SIZE;
+
+/// Qubic, tictactoe on a 4x4x4 cube, for players 'X' (who starts) and 'O'.
+/// Whoever first fills one of the 76 lines of four (along any row, column,
+/// pillar or diagonal, within a layer or through all four) wins. With
+/// perfect play, X wins.
+///
+/// The cube is four layers of 4x4 cells. A cell's move id spells out where
+/// it is as three digits from 1 to 4: its layer, row and column, so that
+/// move 243 is in the second layer, fourth row, third column.
+#[derive(Clone, Debug)]
+pub struct QubicGame {
+    /// Each cell is 'X', 'O' or '-', layer by layer, each layer row by row.
+    pub board: [char; CELLS],
+    pub player: Player,
+}
+
+impl Default for QubicGame {
+    fn default() -> Self {
+        QubicGame { board: ['-'; CELLS], player: 'X' }
+    }
+}
+
+fn opponent(p: Player) -> Player {
+    if p == 'X' { 'O' } else { 'X' }
+}
+
+/// The 76 lines of four cells, and the lines through each cell.
+fn winning_lines() -> &'static Lines {
+    static LINES: OnceLock<Lines> = OnceLock::new();
+    LINES.get_or_init(|| Lines::new(&[SIZE, SIZE, SIZE], SIZE))
+}
+
+fn move_id(i: usize) -> MoveId {
+    let (layer, row, col) = (i / (SIZE * SIZE), i / SIZE % SIZE, i % SIZE);
+    (100 * (layer + 1) + 10 * (row + 1) + col + 1) as MoveId
+}
+
+fn cell_of(id: MoveId) -> usize {
+    let (layer, row, col) = (id / 100 - 1, id / 10 % 10 - 1, id % 10 - 1);
+    (layer as usize * SIZE + row as usize) * SIZE + col as usize
+}
+
+impl QubicGame {
+    fn victor(&self) -> Option<Player> {
+        ['X', 'O'].iter().cloned().find(|p| lines::has_line(&self.board, &winning_lines().all, p))
+    }
+}
+
+impl Game for QubicGame {
+    const NAME: &'static str = "Qubic";
+
+    fn current_player(&self) -> Player {
+        self.player
+    }
+
+    /// The serialized form is the 64 cells, layer by layer and each layer row
+    /// by row, each 'X', 'O' or '-'. As in tictactoe, the player to move
+    /// follows from the number of each.
+    fn unparse(&self) -> String {
+        self.board.iter().collect()
+    }
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let chars: Vec<char> = input.chars().collect();
+        if chars.len() != CELLS { return Err(ParseError::wrong_length("64 cells").at(chars.len().min(CELLS))); }
+        let mut board = ['-'; CELLS];
+        for (i, c) in chars.iter().enumerate() {
+            board[i] = match c {
+                'X' | 'O' | '-' => *c,
+                _ => return Err(ParseError::unexpected(i, "'X', 'O' or '-'")),
+            };
+        }
+        let count = |p: char| board.iter().filter(|c| **c == p).count();
+        let player = match count('X') as isize - count('O') as isize {
+            0 => 'X',
+            1 => 'O',
+            n if n < 0 => return Err(ParseError::invalid("no more O moves than X moves")),
+            _ => return Err(ParseError::invalid("at most one more X move than O moves")),
+        };
+        Ok(QubicGame { board, player })
+    }
+
+    fn moves(&self) -> Vec<Move<Self>> {
+        if self.victor().is_some() {
+            return Vec::new();
+        }
+        let mut v = Vec::new();
+        for i in (0..CELLS).filter(|i| self.board[*i] == '-') {
+            let mut next_state = QubicGame { board: self.board, player: opponent(self.player) };
+            next_state.board[i] = self.player;
+            let end_game = if lines::has_line(&next_state.board, winning_lines().through(i), &self.player) {
+                Some(Some(self.player).into_iter().collect())
+            } else if next_state.board.iter().all(|c| *c != '-') {
+                Some(SmallVec::new())
+            } else {
+                None
+            };
+            v.push(Move { id: move_id(i), end_game, next_state });
+        }
+        v
+    }
+
+    const IN_PLACE_MOVES: bool = true;
+
+    fn move_ids(&self) -> Vec<MoveId> {
+        if self.victor().is_some() {
+            return Vec::new();
+        }
+        (0..CELLS).filter(|i| self.board[*i] == '-').map(move_id).collect()
+    }
+
+    fn make_move(&mut self, id: MoveId) -> MadeMove<Self> {
+        let i = cell_of(id);
+        let mover = self.player;
+        self.board[i] = mover;
+        self.player = opponent(mover);
+        let end_game = if lines::has_line(&self.board, winning_lines().through(i), &mover) {
+            Some(Some(mover).into_iter().collect())
+        } else if self.board.iter().all(|c| *c != '-') {
+            Some(SmallVec::new())
+        } else {
+            None
+        };
+        MadeMove { end_game, undo: Undo::Packed(0) }
+    }
+
+    fn unmake_move(&mut self, id: MoveId, _undo: Undo<Self>) {
+        let i = cell_of(id);
+        self.player = self.board[i];
+        self.board[i] = '-';
+    }
+
+    /// Cells are named by layer, then column letter and row, like "2c4" for
+    /// move 243.
+    fn move_name(&self, id: MoveId) -> String {
+        let i = cell_of(id);
+        format!("{}{}{}", i / (SIZE * SIZE) + 1, (b'a' + (i % SIZE) as u8) as char, i / SIZE % SIZE + 1)
+    }
+
+    fn move_long_name(&self, id: MoveId) -> String {
+        let name = self.move_name(id);
+        format!("place on {} of layer {}", &name[1..], &name[..1])
+    }
+
+    /// The four layers are drawn side by side, from the first to the last.
+    fn render_to_text(&self) -> String {
+        let mut s = String::new();
+        for layer in 0..SIZE {
+            s.push_str(&format!("  layer {}   ", layer + 1));
+        }
+        s.push('\n');
+        for _ in 0..SIZE {
+            s.push_str("   a b c d  ");
+        }
+        s.push('\n');
+        for row in 0..SIZE {
+            for layer in 0..SIZE {
+                s.push_str(&format!("{} ", row + 1));
+                for col in 0..SIZE {
+                    let cell = self.board[(layer * SIZE + row) * SIZE + col];
+                    s.push(' ');
+                    s.push(if cell == '-' { '.' } else { cell });
+                }
+                s.push_str("  ");
+            }
+            s.push('\n');
+        }
+        match self.victor() {
+            Some(p) => s.push_str(&format!("{} has four in a row\n", p)),
+            None if self.board.iter().all(|c| *c != '-') => s.push_str("the cube is full\n"),
+            None => s.push_str(&format!("{} to move\n", self.player)),
+        }
+        s
+    }
+
+    /// A decisive victory or loss once someone has four in a row; before
+    /// that, a score for the lines each player could still complete,
+    /// counting those nearer completion for much more.
+    fn value_for(&self, p: Player) -> i64 {
+        match self.victor() {
+            Some(v) if v == p => return VICTORY,
+            Some(_) => return -VICTORY,
+            None => {}
+        }
+        let score = |p: Player| -> i64 {
+            lines::open_lines(&self.board, &winning_lines().all, &p, &'-')
+                .map(|held| 1 << (3 * held))
+                .sum()
+        };
+        score(p) - score(opponent(p))
+    }
+
+    fn outcome(&self) -> Outcome {
+        match self.victor() {
+            Some(p) => Outcome::win(p),
+            None if self.board.iter().all(|c| *c != '-') => Outcome::Draw,
+            None => Outcome::Ongoing,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_core::{play, search};
+
+    /// A full cube, 32 of each, with no four in a row.
+    const FULL: &str = "OOOXOXXOXOOOOOOXXXOXXOXXOXOXOOXOXOXOOOXOOXXOXXOXXOXOXXOXXXOOOXXX";
+
+    /// An otherwise empty cube with marks on the given cells.
+    fn cube(x: &[usize], o: &[usize]) -> String {
+        (0..CELLS).map(|i| if x.contains(&i) { 'X' } else if o.contains(&i) { 'O' } else { '-' }).collect()
+    }
+
+    #[test]
+    fn the_player_to_move_follows_from_the_counts() {
+        assert_eq!(QubicGame::parse(&cube(&[], &[])).unwrap().player, 'X');
+        assert_eq!(QubicGame::parse(&cube(&[5], &[])).unwrap().player, 'O');
+        assert_eq!(QubicGame::parse(&cube(&[5], &[9])).unwrap().player, 'X');
+        assert!(QubicGame::parse(&cube(&[], &[9])).is_err());
+        assert!(QubicGame::parse(&cube(&[5, 6], &[])).is_err());
+    }
+
+    #[test]
+    fn a_line_through_all_four_layers_wins() {
+        // The long diagonal from 1a1 to 4d4, one cell in each layer.
+        let game = QubicGame::parse(&cube(&[0, 21, 42], &[1, 2, 3])).unwrap();
+        assert_eq!(game.outcome(), Outcome::Ongoing);
+        let m = play(&game, "4d4");
+        assert_eq!(m.id, 444);
+        assert_eq!(m.end_game, Some(Some('X').into_iter().collect()));
+        assert_eq!(m.next_state.outcome(), Outcome::win('X'));
+        assert!(m.next_state.moves().is_empty());
+        // Straight down a pillar too.
+        let game = QubicGame::parse(&cube(&[6, 22, 38], &[1, 2, 3])).unwrap();
+        assert!(game.moves().into_iter().any(|m| m.id == 423 && m.end_game.is_some()));
+    }
+
+    #[test]
+    fn a_full_cube_without_a_line_is_a_draw() {
+        let full = QubicGame::parse(FULL).unwrap();
+        assert_eq!(full.outcome(), Outcome::Draw);
+        assert!(full.moves().is_empty());
+
+        let last = QubicGame::parse(&format!("-{}", &FULL[1..])).unwrap();
+        let moves = last.moves();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].end_game, Some(SmallVec::new()));
+    }
+
+    #[tokio::test]
+    async fn search_finds_a_forced_win() {
+        // X has b1, c1, a2 and a3 of the first layer; a1 makes three in a row
+        // both along the top row and down the first column, and O can only
+        // block one of them. O's stones are scattered, with no threats.
+        let game = QubicGame::parse("-XX-X---X---------------------O----O-----------------O----O-----").unwrap();
+        let moves = game.moves();
+        assert!(moves.iter().all(|m| m.end_game.is_none()), "no win in one");
+
+        let chosen = search(&game, &moves, 'X').await;
+        assert_eq!(game.move_name(chosen.id), "1a1");
+        for reply in chosen.next_state.moves() {
+            assert!(reply.next_state.moves().iter().any(|m| m.end_game == Some(Some('X').into_iter().collect())),
+                    "O escapes with {}", chosen.next_state.move_name(reply.id));
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Every game this crate implements, by name, for the service, the REPL and
+//! anything else that picks its game at run time.
+
+use crate::dyn_game::GameType;
+
+use crate::backgammon::BackgammonGame;
+use crate::battleship::BattleshipGame;
+use crate::chinese_checkers::ChineseCheckersGame;
+use crate::dots_and_boxes::DotsAndBoxesGame;
+use crate::go::GoGame;
+use crate::hex::HexGame;
+use crate::kalah::KalahGame;
+use crate::nim::NimGame;
+use crate::nine_mens_morris::NineMensMorrisGame;
+use crate::othello::OthelloGame;
+use crate::pentago::PentagoGame;
+use crate::poker::{KuhnPokerGame, LeducPokerGame};
+use crate::quarto::QuartoGame;
+use crate::qubic::QubicGame;
+use crate::sliding_puzzle::SlidingPuzzleGame;
+use crate::tictactoe::TicTacToeGame;
+
+/// The games, with the default (tic-tac-toe) first.
+pub static GAMES: &[GameType] = &[
+    GameType::of::<TicTacToeGame>(),
+    GameType::of::<OthelloGame>(),
+    GameType::of::<NimGame>(),
+    GameType::of::<KalahGame>(),
+    GameType::of::<DotsAndBoxesGame>(),
+    GameType::of::<HexGame>(),
+    GameType::of::<GoGame>(),
+    GameType::of::<BattleshipGame>(),
+    GameType::of::<BackgammonGame>(),
+    GameType::of::<QuartoGame>(),
+    GameType::of::<NineMensMorrisGame>(),
+    GameType::of::<PentagoGame>(),
+    GameType::of::<QubicGame>(),
+    GameType::of::<ChineseCheckersGame>(),
+    GameType::of::<SlidingPuzzleGame>(),
+    GameType::of::<KuhnPokerGame>(),
+    GameType::of::<LeducPokerGame>(),
+];
+
+/// The game played when none is named.
+pub fn default_game() -> &'static GameType {
+    &GAMES[0]
+}
+
+/// The game called `name` (ignoring case), if there is one.
+pub fn find(name: &str) -> Option<&'static GameType> {
+    GAMES.iter().find(|g| g.name.eq_ignore_ascii_case(name))
+}
+
+/// The names of all the games, for messages listing them.
+pub fn names() -> Vec<&'static str> {
+    GAMES.iter().map(|g| g.name).collect()
+}
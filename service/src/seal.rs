@@ -0,0 +1,152 @@
+//! The service keeps no state of its own: every response carries the game
+//! state, and every request brings it back. For games with hidden information
+//! that would give the secrets away, so such states are sealed instead: they
+//! are encrypted and authenticated (with ChaCha20-Poly1305) under a key known
+//! only to the service, which means clients can neither read them nor tamper
+//! with them.
+//!
+//! A sealed state is also bound to its viewer, the player it is shown to, so
+//! that a response never describes the game from anyone else's side.
+//!
+//! Sealing cannot stop a client from replaying a state it was handed earlier,
+//! e.g. to take back a shot that missed and try another: that would need the
+//! service to remember the games in play.
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::borrow::Cow;
+
+use crate::game_core::{ParseError, Player};
+
+/// The environment variable holding the service's 256-bit key, as 64
+/// hexadecimal digits.
+pub const KEY_VARIABLE: &str = "GAME_STATE_KEY";
+
+const NONCE_LEN: usize = 12;
+
+fn cipher() -> Result<ChaCha20Poly1305, Cow<'static, str>> {
+    cipher_from(std::env::var(KEY_VARIABLE).ok())
+}
+
+/// The cipher for the key `hex`, the value of `KEY_VARIABLE` if it is set.
+fn cipher_from(hex: Option<String>) -> Result<ChaCha20Poly1305, Cow<'static, str>> {
+    let hex = hex.ok_or_else(|| format!("{} must be set to seal hidden game states", KEY_VARIABLE))?;
+    match from_hex(&hex) {
+        Some(key) if key.len() == 32 => Ok(ChaCha20Poly1305::new(Key::from_slice(&key))),
+        _ => Err(format!("{} must be 64 hexadecimal digits", KEY_VARIABLE).into()),
+    }
+}
+
+/// Seals `state` for `viewer`, as a string of hexadecimal digits (so that it
+/// remains a valid path segment).
+pub fn seal(viewer: Player, state: &str) -> Result<String, Cow<'static, str>> {
+    seal_with(cipher()?, viewer, state)
+}
+
+fn seal_with(cipher: ChaCha20Poly1305, viewer: Player, state: &str) -> Result<String, Cow<'static, str>> {
+    let mut plaintext = viewer.to_string();
+    plaintext.push_str(state);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| "failed to seal game state")?;
+    Ok(to_hex(nonce.iter().chain(ciphertext.iter())))
+}
+
+/// Why a sealed state could not be unsealed.
+#[derive(Debug)]
+pub enum UnsealError {
+    /// The service has no usable key, which is no fault of the client's.
+    Key(Cow<'static, str>),
+    /// The state was not sealed by this service: it is garbled, tampered
+    /// with, or sealed under some other key.
+    Invalid(ParseError),
+}
+
+/// Recovers the viewer and the game state from a sealed state.
+pub fn unseal(sealed: &str) -> Result<(Player, String), UnsealError> {
+    unseal_with(cipher(), sealed)
+}
+
+fn unseal_with(cipher: Result<ChaCha20Poly1305, Cow<'static, str>>,
+               sealed: &str) -> Result<(Player, String), UnsealError> {
+    if let Some(i) = sealed.chars().position(|c| !c.is_ascii_hexdigit()) {
+        return Err(UnsealError::Invalid(ParseError::unexpected(i, "a sealed game state, in hexadecimal digits")));
+    }
+    let bytes = match from_hex(sealed) {
+        Some(bytes) if bytes.len() > NONCE_LEN => bytes,
+        _ => return Err(UnsealError::Invalid(ParseError::wrong_length("a whole sealed game state"))),
+    };
+    let not_sealed_here = || UnsealError::Invalid(ParseError::invalid("a game state sealed by this service"));
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    let plaintext = cipher
+        .map_err(UnsealError::Key)?
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| not_sealed_here())?;
+    let plaintext = String::from_utf8(plaintext).map_err(|_| not_sealed_here())?;
+    let mut chars = plaintext.chars();
+    match chars.next() {
+        Some(viewer) => Ok((viewer, chars.collect())),
+        None => Err(not_sealed_here()),
+    }
+}
+
+fn to_hex<'a>(bytes: impl Iterator<Item = &'a u8>) -> String {
+    bytes.map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [hi, lo] if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() => {
+                let digit = |d: u8| (d as char).to_digit(16).unwrap() as u8;
+                Some(digit(*hi) << 4 | digit(*lo))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: &str) -> Option<String> {
+        Some(byte.repeat(32))
+    }
+
+    #[test]
+    fn sealed_states_round_trip() {
+        // The same key as the service tests use, since they share the
+        // environment.
+        std::env::set_var(KEY_VARIABLE, "07".repeat(32));
+        let sealed = seal('B', "state").unwrap();
+        assert!(!sealed.contains("state"));
+        assert_eq!(unseal(&sealed).unwrap(), ('B', "state".to_string()));
+        // Each sealing is different, so equal states cannot be spotted.
+        assert_ne!(seal('B', "state").unwrap(), sealed);
+    }
+
+    #[test]
+    fn tampered_or_foreign_states_are_invalid() {
+        let sealed = seal_with(cipher_from(key("07")).unwrap(), 'A', "state").unwrap();
+        let mut tampered = sealed.clone().into_bytes();
+        let last = tampered.len() - 1;
+        tampered[last] = if tampered[last] == b'0' { b'1' } else { b'0' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        assert!(matches!(unseal_with(cipher_from(key("07")), &tampered), Err(UnsealError::Invalid(_))));
+        assert!(matches!(unseal_with(cipher_from(key("08")), &sealed), Err(UnsealError::Invalid(_))));
+        assert!(matches!(unseal_with(cipher_from(key("07")), "0123"), Err(UnsealError::Invalid(_))));
+        assert!(matches!(unseal_with(cipher_from(key("07")), "xyz"), Err(UnsealError::Invalid(_))));
+    }
+
+    #[test]
+    fn a_missing_or_bad_key_is_the_services_fault() {
+        let sealed = seal_with(cipher_from(key("07")).unwrap(), 'A', "state").unwrap();
+        for bad in [None, Some("07".repeat(31)), Some("zz".repeat(32))] {
+            assert!(cipher_from(bad.clone()).is_err());
+            assert!(matches!(unseal_with(cipher_from(bad), &sealed), Err(UnsealError::Key(_))));
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use crate::game_core::{Game, Move, MoveId, Outcome, ParseError, Player, Rng};
+
+/// The only player.
+const PLAYER: Player = 'P';
+
+/// The sliding tile puzzle: the 8-puzzle on a 3x3 board or the 15-puzzle on a
+/// 4x4 board. Numbered tiles fill all but one cell, and a tile next to the
+/// gap can slide into it; the puzzle is solved once the tiles are in order,
+/// row by row, with the gap last.
+///
+/// A move is named by the tile it slides, and its id is that tile's number.
+#[derive(Clone, Debug)]
+pub struct SlidingPuzzleGame {
+    pub size: usize,
+    /// The tile in each cell, row by row, with 0 for the gap.
+    pub tiles: Vec<u8>,
+}
+
+impl Default for SlidingPuzzleGame {
+    /// A random (but solvable) 8-puzzle.
+    fn default() -> Self {
+        Self::random_start(&mut Rng::from_entropy())
+    }
+}
+
+impl SlidingPuzzleGame {
+    /// A puzzle of `size` by `size` tiles, in a random order that can be
+    /// solved (but is not already).
+    pub fn scrambled(size: usize, rng: &mut Rng) -> Self {
+        loop {
+            let mut tiles: Vec<u8> = (0..(size * size) as u8).collect();
+            rng.shuffle(&mut tiles);
+            let g = SlidingPuzzleGame { size, tiles };
+            if g.is_solvable() && !g.is_solved() {
+                return g;
+            }
+        }
+    }
+
+    fn gap(&self) -> usize {
+        self.tiles.iter().position(|t| *t == 0).unwrap()
+    }
+
+    fn is_solved(&self) -> bool {
+        self.tiles.iter().enumerate().all(|(i, t)| *t as usize == (i + 1) % self.tiles.len())
+    }
+
+    /// Only half of all arrangements can be solved: each slide changes the
+    /// number of pairs of tiles out of order (ignoring the gap) in a way that
+    /// keeps its parity, taken together with the gap's row when the board has
+    /// an even width.
+    fn is_solvable(&self) -> bool {
+        let tiles: Vec<u8> = self.tiles.iter().cloned().filter(|t| *t != 0).collect();
+        let inversions = (0..tiles.len())
+            .map(|i| tiles[i + 1..].iter().filter(|t| **t < tiles[i]).count())
+            .sum::<usize>();
+        if self.size % 2 == 1 {
+            inversions % 2 == 0
+        } else {
+            let gap_row_from_bottom = self.size - self.gap() / self.size;
+            (inversions + gap_row_from_bottom) % 2 == 1
+        }
+    }
+
+    /// The sum of each tile's distance (along rows and columns) from where it
+    /// belongs. Every slide moves one tile by one, so this is never more than
+    /// the number of moves left.
+    fn manhattan_distance(&self) -> usize {
+        let n = self.size;
+        self.tiles.iter()
+            .enumerate()
+            .filter(|(_, t)| **t != 0)
+            .map(|(i, t)| {
+                let home = *t as usize - 1;
+                (i / n).abs_diff(home / n) + (i % n).abs_diff(home % n)
+            })
+            .sum()
+    }
+}
+
+impl Game for SlidingPuzzleGame {
+    const NAME: &'static str = "SlidingPuzzle";
+
+    const PUZZLE: bool = true;
+
+    /// A random 8-puzzle.
+    fn random_start(rng: &mut Rng) -> Self {
+        Self::scrambled(3, rng)
+    }
+
+    fn current_player(&self) -> Player {
+        PLAYER
+    }
+
+    /// The serialized form is the tiles row by row, as hexadecimal digits with
+    /// 0 for the gap: 9 of them for the 8-puzzle and 16 for the 15-puzzle. For
+    /// example, the solved 8-puzzle is "123456780".
+    fn unparse(&self) -> String {
+        self.tiles.iter().map(|t| std::char::from_digit(*t as u32, 16).unwrap()).collect()
+    }
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let size = match input.chars().count() {
+            9 => 3,
+            16 => 4,
+            _ => return Err(ParseError::wrong_length("9 tiles (for the 8-puzzle) or 16 (for the 15-puzzle)")),
+        };
+        let mut tiles = Vec::new();
+        for (i, c) in input.chars().enumerate() {
+            match c.to_digit(16) {
+                Some(t) if (t as usize) < size * size => tiles.push(t as u8),
+                _ => return Err(ParseError::unexpected(i, format!("a tile as a hexadecimal digit below {}", size * size))),
+            }
+        }
+        for t in 0..(size * size) as u8 {
+            if !tiles.contains(&t) {
+                return Err(ParseError::invalid(format!("every tile once, including tile {}", t)));
+            }
+        }
+        let g = SlidingPuzzleGame { size, tiles };
+        if !g.is_solvable() {
+            return Err(ParseError::invalid("tiles that can be put in order"));
+        }
+        Ok(g)
+    }
+
+    fn moves(&self) -> Vec<Move<Self>> {
+        if self.is_solved() {
+            return Vec::new();
+        }
+        let n = self.size;
+        let gap = self.gap();
+        let (row, col) = (gap / n, gap % n);
+        let mut neighbors = Vec::new();
+        if row > 0 { neighbors.push(gap - n); }
+        if row + 1 < n { neighbors.push(gap + n); }
+        if col > 0 { neighbors.push(gap - 1); }
+        if col + 1 < n { neighbors.push(gap + 1); }
+
+        let mut v = Vec::new();
+        for from in neighbors {
+            let mut next_state = self.clone();
+            next_state.tiles.swap(gap, from);
+            let end_game = if next_state.is_solved() { Some(Some(PLAYER).into_iter().collect()) } else { None };
+            v.push(Move { id: self.tiles[from] as MoveId, end_game, next_state });
+        }
+        v
+    }
+
+    /// Moves are named by the tile and the way it slides, like "7 left".
+    fn move_name(&self, id: MoveId) -> String {
+        let n = self.size;
+        let gap = self.gap();
+        let from = self.tiles.iter().position(|t| *t as MoveId == id).unwrap();
+        let direction = if from + n == gap {
+            "down"
+        } else if gap + n == from {
+            "up"
+        } else if from + 1 == gap {
+            "right"
+        } else {
+            "left"
+        };
+        format!("{} {}", id, direction)
+    }
+
+    fn move_long_name(&self, id: MoveId) -> String {
+        format!("slide {}", self.move_name(id))
+    }
+
+    fn render_to_text(&self) -> String {
+        let mut s = String::new();
+        for row in self.tiles.chunks(self.size) {
+            for t in row {
+                if *t == 0 {
+                    s.push_str("  .");
+                } else {
+                    s.push_str(&format!("{:3}", t));
+                }
+            }
+            s.push('\n');
+        }
+        if self.is_solved() {
+            s.push_str("solved!\n");
+        } else {
+            s.push_str(&format!("at least {} moves to go\n", self.manhattan_distance()));
+        }
+        s
+    }
+
+    fn value_for(&self, _p: Player) -> i64 {
+        -(self.manhattan_distance() as i64)
+    }
+
+    fn outcome(&self) -> Outcome {
+        if self.is_solved() { Outcome::win(PLAYER) } else { Outcome::Ongoing }
+    }
+}
@@ -0,0 +1,264 @@
+//! Solving puzzles (see `Game::PUZZLE`): finding the shortest sequence of
+//! moves that solves one.
+//!
+//! Both searches here count every move as one step and take minus
+//! `value_for` as their estimate of the steps left, so their solutions are
+//! as short as can be whenever that estimate is never too high.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::game_core::{Game, Move, MoveId};
+
+/// How many states A* may expand before `solve` gives up on it, since it
+/// remembers every state it has seen. That is enough to search every
+/// arrangement of the 8-puzzle.
+const MAX_EXPANSIONS: usize = 250_000;
+
+/// The longest solution IDA* looks for.
+const MAX_SOLUTION_LENGTH: usize = 200;
+
+/// How many states IDA* may visit, over all its rounds, before `solve` gives
+/// up on the puzzle. Together with `MAX_EXPANSIONS`, this keeps a solve to a
+/// second or two.
+const MAX_VISITS: usize = 1_000_000;
+
+/// The moves that solve `start` in as few as possible, or `None` if it
+/// cannot be solved. A puzzle that is already solved has the empty solution.
+///
+/// This runs A*, and falls back to IDA*, which is slower but needs hardly
+/// any memory, for puzzles too big for A* to finish. When neither finishes,
+/// the puzzle is `TooBig`.
+pub fn solve<B: Game>(start: &B) -> Result<Option<Vec<MoveId>>, TooBig> {
+    match a_star(start, MAX_EXPANSIONS) {
+        Ok(solution) => Ok(solution),
+        Err(TooBig) => ida_star(start, MAX_SOLUTION_LENGTH, MAX_VISITS),
+    }
+}
+
+/// The estimated number of moves left to solve `state`.
+fn estimate<B: Game>(state: &B) -> usize {
+    (-state.value_for(state.current_player())).max(0) as usize
+}
+
+/// Whether `state`, which has no moves, is solved (rather than stuck).
+fn is_solved<B: Game>(state: &B) -> bool {
+    state.value_for(state.current_player()) >= 0
+}
+
+/// A search used up its budget before it could say how to solve a puzzle,
+/// or that it cannot be solved.
+#[derive(Debug)]
+pub struct TooBig;
+
+/// The shortest solution for `start` by A* search, expanding at most
+/// `max_expansions` states.
+pub fn a_star<B: Game>(start: &B, max_expansions: usize) -> Result<Option<Vec<MoveId>>, TooBig> {
+    let p = start.current_player();
+    if start.moves().is_empty() {
+        return Ok(if is_solved(start) { Some(Vec::new()) } else { None });
+    }
+
+    // Every state reached so far: the state, how it was reached (from which
+    // state, by which move), the moves it took, and whether it is solved.
+    struct Node<B> {
+        state: B,
+        parent: Option<(usize, MoveId)>,
+        steps: usize,
+        solved: bool,
+    }
+    let mut nodes = vec![Node { state: start.clone(), parent: None, steps: 0, solved: false }];
+    let mut best_steps = HashMap::new();
+    best_steps.insert(start.unparse(), 0);
+    // The nodes to expand, by estimated total length (then fewest steps
+    // left, to finish sooner among equals).
+    let mut frontier = BinaryHeap::new();
+    frontier.push(Reverse((estimate(start), estimate(start), 0)));
+
+    let mut expansions = 0;
+    while let Some(Reverse((_, _, n))) = frontier.pop() {
+        if nodes[n].solved {
+            let mut solution = Vec::new();
+            let mut at = n;
+            while let Some((parent, id)) = nodes[at].parent {
+                solution.push(id);
+                at = parent;
+            }
+            solution.reverse();
+            return Ok(Some(solution));
+        }
+        if best_steps.get(&nodes[n].state.unparse()) != Some(&nodes[n].steps) {
+            // A shorter way here was found after this node was queued.
+            continue;
+        }
+        expansions += 1;
+        if expansions > max_expansions {
+            return Err(TooBig);
+        }
+        let steps = nodes[n].steps + 1;
+        for m in nodes[n].state.moves() {
+            let key = m.next_state.unparse();
+            if best_steps.get(&key).is_some_and(|s| *s <= steps) {
+                continue;
+            }
+            let solved = m.end_game.as_ref().is_some_and(|victors| victors.contains(&p));
+            if m.end_game.is_some() && !solved {
+                continue;
+            }
+            best_steps.insert(key, steps);
+            let left = if solved { 0 } else { estimate(&m.next_state) };
+            frontier.push(Reverse((steps + left, left, nodes.len())));
+            nodes.push(Node { state: m.next_state, parent: Some((n, m.id)), steps, solved });
+        }
+    }
+    Ok(None)
+}
+
+/// The shortest solution for `start` by iterative-deepening A* search, if
+/// there is one with at most `max_length` moves, visiting at most
+/// `max_visits` states.
+pub fn ida_star<B: Game>(start: &B, max_length: usize, max_visits: usize) -> Result<Option<Vec<MoveId>>, TooBig> {
+    if start.moves().is_empty() {
+        return Ok(if is_solved(start) { Some(Vec::new()) } else { None });
+    }
+    let mut bound = estimate(start);
+    let mut path = vec![start.unparse()];
+    let mut solution = Vec::new();
+    let mut visits_left = max_visits;
+    while bound <= max_length {
+        match bounded_search(start, 0, bound, &mut path, &mut solution, &mut visits_left) {
+            Bounded::Solved => return Ok(Some(solution)),
+            Bounded::Exceeded(next_bound) => bound = next_bound,
+            Bounded::Unsolvable => return Ok(None),
+            Bounded::OutOfVisits => return Err(TooBig),
+        }
+    }
+    Ok(None)
+}
+
+enum Bounded {
+    Solved,
+    /// No solution within the bound; the smallest estimate that exceeded it.
+    Exceeded(usize),
+    /// No solution at all: everything was searched without reaching the
+    /// bound.
+    Unsolvable,
+    /// The search visited as many states as it may before it could tell.
+    OutOfVisits,
+}
+
+/// Searches depth-first from `state`, reached in `steps` moves, for a
+/// solution whose estimated length is within `bound`. `path` holds the states
+/// along the way, which are not revisited, and `solution` the moves. Each
+/// state visited uses up one of `visits_left`.
+fn bounded_search<B: Game>(state: &B,
+                           steps: usize,
+                           bound: usize,
+                           path: &mut Vec<String>,
+                           solution: &mut Vec<MoveId>,
+                           visits_left: &mut usize) -> Bounded
+{
+    if *visits_left == 0 {
+        return Bounded::OutOfVisits;
+    }
+    *visits_left -= 1;
+    let p = state.current_player();
+    let mut smallest_excess = None;
+    for m in state.moves() {
+        let solved = m.end_game.as_ref().is_some_and(|victors| victors.contains(&p));
+        if m.end_game.is_some() && !solved {
+            continue;
+        }
+        let total = steps + 1 + if solved { 0 } else { estimate(&m.next_state) };
+        if solved && total <= bound {
+            solution.push(m.id);
+            return Bounded::Solved;
+        }
+        if total > bound {
+            smallest_excess = Some(smallest_excess.map_or(total, |s: usize| s.min(total)));
+            continue;
+        }
+        let key = m.next_state.unparse();
+        if path.contains(&key) {
+            continue;
+        }
+        path.push(key);
+        solution.push(m.id);
+        match bounded_search(&m.next_state, steps + 1, bound, path, solution, visits_left) {
+            Bounded::Solved => return Bounded::Solved,
+            Bounded::Exceeded(total) => {
+                smallest_excess = Some(smallest_excess.map_or(total, |s: usize| s.min(total)));
+            }
+            Bounded::Unsolvable => {}
+            Bounded::OutOfVisits => return Bounded::OutOfVisits,
+        }
+        path.pop();
+        solution.pop();
+    }
+    match smallest_excess {
+        Some(total) => Bounded::Exceeded(total),
+        None => Bounded::Unsolvable,
+    }
+}
+
+/// Each move of `solution` in turn, starting from `start`, with the state it
+/// is made from.
+pub fn moves_along<B: Game>(start: &B, solution: &[MoveId]) -> Vec<(B, Move<B>)> {
+    let mut state = start.clone();
+    let mut steps = Vec::new();
+    for id in solution {
+        let m = state.moves().into_iter().find(|m| m.id == *id).expect("solutions are made of legal moves");
+        let next = m.next_state.clone();
+        steps.push((state, m));
+        state = next;
+    }
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sliding_puzzle::SlidingPuzzleGame;
+
+    fn puzzle(tiles: &str) -> SlidingPuzzleGame {
+        SlidingPuzzleGame::parse(tiles).unwrap()
+    }
+
+    /// The length of the solution each search finds.
+    fn lengths(start: &SlidingPuzzleGame) -> (usize, usize) {
+        let by_a_star = a_star(start, MAX_EXPANSIONS).unwrap().unwrap();
+        let by_ida_star = ida_star(start, MAX_SOLUTION_LENGTH, MAX_VISITS).unwrap().unwrap();
+        // Whatever the search, the moves must solve the puzzle.
+        for solution in [&by_a_star, &by_ida_star] {
+            let steps = moves_along(start, solution);
+            assert!(steps.last().is_none_or(|(_, m)| m.end_game.is_some()));
+        }
+        (by_a_star.len(), by_ida_star.len())
+    }
+
+    #[test]
+    fn finds_the_shortest_solutions_of_the_8_puzzle() {
+        assert_eq!(lengths(&puzzle("123456780")), (0, 0));
+        assert_eq!(lengths(&puzzle("123456708")), (1, 1));
+        assert_eq!(lengths(&puzzle("123456078")), (2, 2));
+        assert_eq!(lengths(&puzzle("123405786")), (2, 2));
+        // The two hardest arrangements, which take 31 moves.
+        assert_eq!(lengths(&puzzle("867254301")), (31, 31));
+        assert_eq!(lengths(&puzzle("647850321")), (31, 31));
+    }
+
+    #[test]
+    fn the_wrong_parity_cannot_be_solved() {
+        // Swapping two tiles of a solved puzzle makes one that cannot be
+        // solved, which `parse` would refuse.
+        let swapped = SlidingPuzzleGame { size: 3, tiles: vec![2, 1, 3, 4, 5, 6, 7, 8, 0] };
+        assert!(solve(&swapped).unwrap().is_none());
+    }
+
+    #[test]
+    fn gives_up_once_over_budget() {
+        let hard = puzzle("867254301");
+        assert!(a_star(&hard, 1000).is_err());
+        assert!(ida_star(&hard, MAX_SOLUTION_LENGTH, 1000).is_err());
+    }
+}
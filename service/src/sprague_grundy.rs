@@ -0,0 +1,143 @@
+//! Sprague-Grundy theory for impartial games: games where both players have
+//! the same moves available from every position, such as Nim.
+//!
+//! Every position of an impartial game is equivalent to a single Nim heap,
+//! whose size is the position's *nimber*, and the nimber of a sum of
+//! independent games is the exclusive-or of their nimbers. This lets a solver
+//! play such games perfectly by computing nimbers of small components, rather
+//! than searching the whole game tree.
+
+use crate::game_core::Game;
+use std::collections::HashMap;
+
+/// An impartial game that can describe itself as a sum of independent
+/// components.
+pub trait ImpartialGame: Game {
+    /// Splits this position into independent sub-positions whose disjunctive
+    /// sum is this position (e.g. the individual heaps of Nim). Components
+    /// should be normalized, so that equivalent components unparse to the same
+    /// string; this is what makes the nimbers of components reusable.
+    fn components(&self) -> Vec<Self> {
+        vec![self.clone()]
+    }
+
+    /// Under misère play, the player who makes the last move loses.
+    fn is_misere(&self) -> bool {
+        false
+    }
+}
+
+/// Computes (and caches) nimbers of positions of an impartial game.
+pub struct NimberCalculator<G> {
+    memo: HashMap<String, u32>,
+    _game: std::marker::PhantomData<G>,
+}
+
+impl<G: ImpartialGame> Default for NimberCalculator<G> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<G: ImpartialGame> NimberCalculator<G> {
+    pub fn new() -> Self {
+        NimberCalculator { memo: HashMap::new(), _game: std::marker::PhantomData }
+    }
+
+    /// The nimber of `g`: the exclusive-or of the nimbers of its components.
+    pub fn nimber(&mut self, g: &G) -> u32 {
+        g.components().iter().fold(0, |acc, c| acc ^ self.component_nimber(c))
+    }
+
+    /// The nimber of a single component: the minimum excluded value among the
+    /// nimbers of the positions it can move to.
+    fn component_nimber(&mut self, c: &G) -> u32 {
+        let key = c.unparse();
+        if let Some(n) = self.memo.get(&key) {
+            return *n;
+        }
+        let mut options: Vec<u32> = c.moves().iter().map(|m| self.nimber(&m.next_state)).collect();
+        options.sort_unstable();
+        options.dedup();
+        let mex = options.iter().enumerate()
+            .find(|(i, n)| *i as u32 != **n)
+            .map(|(i, _)| i as u32)
+            .unwrap_or(options.len() as u32);
+        self.memo.insert(key, mex);
+        mex
+    }
+
+    /// Whether the player to move in `g` can force a win.
+    ///
+    /// Under normal play, that is exactly when the nimber is non-zero. Under
+    /// misère play we use Bouton's adjustment, which is exact for Nim and for
+    /// other "tame" games: when every component has nimber 0 or 1, the winning
+    /// positions are instead those with nimber 0.
+    pub fn is_winning(&mut self, g: &G) -> bool {
+        let nimbers: Vec<u32> = g.components().iter().map(|c| self.component_nimber(c)).collect();
+        let sum = nimbers.iter().fold(0, |acc, n| acc ^ n);
+        if g.is_misere() && nimbers.iter().all(|n| *n <= 1) {
+            sum == 0
+        } else {
+            sum != 0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_core::{Move, Player};
+    use crate::nim::NimGame;
+
+    /// Determines by exhaustive search whether the player to move can force a
+    /// win.
+    fn brute_force_wins<G: Game>(g: &G) -> bool {
+        let p = g.current_player();
+        g.moves().iter().any(|m| brute_force_move_wins(m, p))
+    }
+
+    fn brute_force_move_wins<G: Game>(m: &Move<G>, p: Player) -> bool {
+        match &m.end_game {
+            Some(victors) => victors.contains(&p),
+            None => !brute_force_wins(&m.next_state),
+        }
+    }
+
+    fn check_small_instances(misere: bool) {
+        let mut calc = NimberCalculator::new();
+        for a in 0..=4 {
+            for b in 0..=4 {
+                for c in 1..=4 {
+                    let g = NimGame::new(vec![a, b, c], misere, 'A');
+                    assert_eq!(calc.is_winning(&g), brute_force_wins(&g), "{}", g.unparse());
+                    for m in g.moves() {
+                        let leaves_loss = m.end_game.is_none() && !calc.is_winning(&m.next_state);
+                        if leaves_loss {
+                            assert!(brute_force_move_wins(&m, 'A'), "{} via {}", g.unparse(), m.id);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn normal_play_agrees_with_brute_force() {
+        check_small_instances(false);
+    }
+
+    #[test]
+    fn misere_play_agrees_with_brute_force() {
+        check_small_instances(true);
+    }
+
+    #[test]
+    fn nim_heaps_are_their_own_nimbers() {
+        let mut calc = NimberCalculator::new();
+        for h in 0..10 {
+            let g = NimGame::new(vec![h], false, 'A');
+            assert_eq!(calc.nimber(&g), h);
+        }
+    }
+}
@@ -1,17 +1,51 @@
-use crate::game_core::{Game, Move, Player};
-use std::borrow::Cow;
+use crate::game_core::{Game, GameOptions, MadeMove, Move, MoveId, Outcome, ParseError, Player, Undo, VICTORY};
+use crate::grid::Grid;
 
-pub type TicTacToeBoard = [char; 9];
+pub type TicTacToeBoard = Grid<char>;
 
+/// Boards range from 3x3 to 9x9.
+pub const MAX_SIZE: usize = 9;
+
+/// Tictactoe on a square board. The usual game is on a 3x3 board, three in a
+/// row wins, and X moves first; as options, the board can be bigger (`size`),
+/// winning can take more in a row (`win`), and O can move first (`first`).
 #[derive(Clone, Debug)]
 pub struct TicTacToeGame {
     pub board: TicTacToeBoard,
     pub player: Player,
+    /// How many in a row win.
+    pub win: usize,
+    /// Who moved first, which is needed to work out who moves next.
+    pub first: Player,
 }
 
 impl Default for TicTacToeGame {
     fn default() -> Self {
-        Self { board: ['-'; 9], player: 'X' }
+        Self::new(3, 3, 'X')
+    }
+}
+
+fn opponent(p: Player) -> Player {
+    if p == 'X' { 'O' } else { 'X' }
+}
+
+impl TicTacToeGame {
+    pub fn new(size: usize, win: usize, first: Player) -> Self {
+        TicTacToeGame { board: Grid::new(size, size, '-'), player: first, win, first }
+    }
+
+    /// Whether this is the usual game, which is written as just its cells.
+    fn standard(&self) -> bool {
+        self.board.rows() == 3 && self.win == 3 && self.first == 'X'
+    }
+
+    fn cells(&self) -> usize {
+        self.board.cells().len()
+    }
+
+    /// `player`, if placing on cell `i` of `board` gave them a line.
+    fn victory(&self, board: &TicTacToeBoard, i: usize, player: Player) -> Option<Player> {
+        if board.in_a_row_through(board.coord(i), self.win, &player) { Some(player) } else { None }
     }
 }
 
@@ -22,41 +56,89 @@ impl Game for TicTacToeGame {
         self.player
     }
 
+    /// The usual game is written as its 9 cells, row by row, each 'X', 'O' or
+    /// '-'. Other variants start with the board size, 'k', the number in a
+    /// row that wins, and who moved first, like "4k3O" for a 4x4 board with
+    /// three in a row winning where O moved first.
     fn unparse(&self) -> String {
-        self.board.iter().collect()
-    }
-
-    fn parse(input: &str) -> Result<Self, Cow<str>> {
-        let mut g = TicTacToeGame { board: ['-'; 9], player: 'X' };
-        if input.chars().count() != 9 { return Err("input must be length 9".into());}
-        let mut num_x = 0;
-        let mut num_o = 0;
-        for (i, c) in input.chars().enumerate() {
-            match c {
-                '-' | 'X' | 'O' => g.board[i] = c,
-                'x' | 'o' => return Err("only upper-case moves allowed".into()),
-                _ => return Err("unexpected chacter found in board".into()),
+        let cells = self.board.encode(false, |c| *c);
+        if self.standard() {
+            cells
+        } else {
+            format!("{}k{}{}{}", self.board.rows(), self.win, self.first, cells)
+        }
+    }
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let mut g = TicTacToeGame::default();
+        // Where the cells start.
+        let mut at = 0;
+        if input.starts_with(|c: char| c.is_ascii_digit()) {
+            let digits = |from: usize| input[from..].chars().take_while(|c| c.is_ascii_digit()).count();
+            let size = match input[..digits(0)].parse() {
+                Ok(n) if (3..=MAX_SIZE).contains(&n) => n,
+                _ => return Err(ParseError::unexpected(0, "a board size from 3 to 9")),
+            };
+            at = digits(0);
+            if !input[at..].starts_with('k') {
+                return Err(ParseError::unexpected(at, "'k' and the number in a row that wins"));
             }
-            if c == 'X' { num_x += 1; }
-            if c == 'O' { num_o += 1; }
+            at += 1;
+            let win = match input[at..at + digits(at)].parse() {
+                Ok(n) if (2..=size).contains(&n) => n,
+                _ => return Err(ParseError::unexpected(at, format!("a number in a row from 2 to {}", size))),
+            };
+            at += digits(at);
+            let first = match input[at..].chars().next() {
+                Some(p @ 'X') | Some(p @ 'O') => p,
+                _ => return Err(ParseError::unexpected(at, "who moved first, X or O")),
+            };
+            at += 1;
+            g = TicTacToeGame::new(size, win, first);
+        }
+        g.board = Grid::decode(g.board.rows(), g.board.cols(), &input[at..], |c| match c {
+            '-' | 'X' | 'O' => Ok(c),
+            'x' | 'o' => Err("upper-case 'X' or 'O'".into()),
+            _ => Err("'X', 'O' or '-'".into()),
+        }).map_err(|e| e.shifted(at))?;
+        let second = opponent(g.first);
+        let num_first = g.board.cells().iter().filter(|c| **c == g.first).count();
+        let num_second = g.board.cells().iter().filter(|c| **c == second).count();
+        if num_second > num_first {
+            return Err(ParseError::invalid(format!("no more {} moves than {} moves", second, g.first)));
         }
-        if num_o > num_x { return Err("too many O moves".into()); }
-        match num_x - num_o {
-            0 => g.player = 'X',
-            1 => g.player = 'O',
-            _ => return Err("too many X moves".into()),
+        match num_first - num_second {
+            0 => g.player = g.first,
+            1 => g.player = second,
+            _ => return Err(ParseError::invalid(format!("at most one more {} move than {} moves", g.first, second))),
         }
-        return Ok(g);
+        Ok(g)
+    }
+
+    /// `size` (from 3 to 9), `win` (from 2 to the size) and `first` (X or O).
+    fn with_options(mut options: GameOptions) -> Result<Self, ParseError> {
+        let size = options.number("size", 3..=MAX_SIZE)?.unwrap_or(3);
+        let win = options.number("win", 2..=size)?.unwrap_or(3);
+        let first = options.player("first", &['X', 'O'])?.unwrap_or('X');
+        options.finish(&["size", "win", "first"])?;
+        Ok(TicTacToeGame::new(size, win, first))
+    }
+
+    fn options(&self) -> String {
+        format!("size={},win={},first={}", self.board.rows(), self.win, self.first)
     }
 
     fn moves(&self) -> Vec<Move<Self>> {
         let mut v = Vec::new();
-        let next_player = if self.player == 'X' { 'O' } else { 'X' };
-        for i in 1..=9 {
+        if self.outcome().is_over() {
+            return v;
+        }
+        let next_player = opponent(self.player);
+        for i in 1..=self.cells() {
             if self.board[i-1] == '-' {
-                let mut next_board = self.board;
+                let mut next_board = self.board.clone();
                 next_board[i-1] = self.player;
-                let victor = victory(&next_board, self.player);
+                let victor = self.victory(&next_board, i - 1, self.player);
                 let avail = space_available(&next_board);
                 let end_game = if let Some(p) = victor {
                     Some(Some(p).into_iter().collect())
@@ -70,7 +152,8 @@ impl Game for TicTacToeGame {
                     end_game,
                     next_state: TicTacToeGame {
                         board: next_board,
-                        player: next_player
+                        player: next_player,
+                        ..*self
                     },
                 });
             }
@@ -78,83 +161,73 @@ impl Game for TicTacToeGame {
         return v;
     }
 
-    fn render_to_text(&self) -> String {
-        match self.board {
-            [a, b, c,
-             m, n, o,
-             x, y, z] => {
-                // converts the char for a cell state into a three character string.
-                fn pad(c: char) -> String {
-                    format!(" {} ", if c == '-' { ' ' } else { c })
-                }
-                format!(" {a} | {b} | {c} \n\
-                         -----|-----|-----\n \
-                          {m} | {n} | {o} \n\
-                         -----|-----|-----\n \
-                          {x} | {y} | {z} \n",
-                         a=pad(a), b=pad(b), c=pad(c),
-                         m=pad(m), n=pad(n), o=pad(o),
-                         x=pad(x), y=pad(y), z=pad(z))
-            }
+    const IN_PLACE_MOVES: bool = true;
+
+    fn move_ids(&self) -> Vec<MoveId> {
+        if self.outcome().is_over() {
+            return Vec::new();
         }
+        (1..=self.cells() as MoveId).filter(|i| self.board[*i as usize - 1] == '-').collect()
     }
 
-    fn value_for(&self, p: Player) -> i64 {
-        let other = if p == 'X' { 'O' } else { 'X' };
-        if victory(&self.board, p) == Some(p) {
-            100000
-        } else if victory(&self.board, other) == Some(other) {
-            -100000
+    fn make_move(&mut self, id: MoveId) -> MadeMove<Self> {
+        let mover = self.player;
+        self.board[id as usize - 1] = mover;
+        self.player = opponent(mover);
+        let end_game = if let Some(p) = self.victory(&self.board, id as usize - 1, mover) {
+            Some(Some(p).into_iter().collect())
+        } else if !space_available(&self.board) {
+            Some(None.into_iter().collect())
         } else {
-            0
-        }
+            None
+        };
+        MadeMove { end_game, undo: Undo::Packed(0) }
     }
-}
-
-fn victory(board: &TicTacToeBoard, player: Player) -> Option<Player> {
-    match board {
-        [x,y, z,
-        _, _, _,
-        _, _, _] |
 
-        [_, _, _,
-        x, y, z,
-        _, _, _] |
-
-        [_, _, _,
-        _, _, _,
-        x, y, z] |
-
-        [x, _, _,
-         y, _, _,
-         z, _, _] |
-
-        [_, x, _,
-         _, y, _,
-         _, z, _] |
-
-        [_, _, x,
-         _, _, y,
-         _, _, z] |
-
-        [x, _, _, 
-        _, y, _,
-        _, _, z] |
+    fn unmake_move(&mut self, id: MoveId, _undo: Undo<Self>) {
+        self.player = self.board[id as usize - 1];
+        self.board[id as usize - 1] = '-';
+    }
 
-        [_, _, x,
-        _, y, _, 
-        z, _, _]
+    /// On the usual board, cells are spelled out by where they are, like
+    /// "top left" for move 1; on bigger ones, they are named like "b3".
+    fn move_long_name(&self, id: MoveId) -> String {
+        const CELLS: [&str; 9] = [
+            "top left", "top", "top right",
+            "left", "center", "right",
+            "bottom left", "bottom", "bottom right",
+        ];
+        match self.board.rows() {
+            3 => CELLS[id as usize - 1].to_string(),
+            _ => self.board.coord(id as usize - 1).to_string(),
+        }
+    }
 
-        if all_eq(x,y,z,&player) => Some(player),
+    fn render_to_text(&self) -> String {
+        // Each cell is three characters wide, with a space for an empty cell.
+        self.board.render(false, true, |c| format!(" {} ", if *c == '-' { ' ' } else { *c }))
+    }
 
-        _ => None,
+    fn value_for(&self, p: Player) -> i64 {
+        if self.board.in_a_row(self.win, &p) {
+            VICTORY
+        } else if self.board.in_a_row(self.win, &opponent(p)) {
+            -VICTORY
+        } else {
+            0
+        }
     }
-}
 
-fn all_eq(x: &char, y: &char, z: &char, p: &char) -> bool {
-    x == y && y == z && z == p
+    fn outcome(&self) -> Outcome {
+        for p in ['X', 'O'] {
+            if self.board.in_a_row(self.win, &p) {
+                return Outcome::win(p);
+            }
+        }
+        if space_available(&self.board) { Outcome::Ongoing } else { Outcome::Draw }
+    }
 }
 
 fn space_available(board: &TicTacToeBoard) -> bool {
-    board.iter().any(|c| *c == '-')
+    board.cells().contains(&'-')
 }
@@ -0,0 +1,66 @@
+//! Trains a strategy for a poker game by CFR, reporting how exploitable it is
+//! as it goes, then prints the strategy and plays a few hands with it.
+//!
+//! Usage: `local train <kuhnpoker|leducpoker> [iterations]`
+
+use crate::cfr::Cfr;
+use crate::game_core::{sample_chance, Game, Rng, CHANCE, VICTORY};
+use crate::poker::{Kuhn, KuhnPokerGame, Leduc, LeducPokerGame, PokerGame, Variant};
+
+/// Trains the game named by the first of `args`, for as many iterations as
+/// the second says.
+pub fn run(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
+    let name = args.next().unwrap_or_else(|| KuhnPokerGame::NAME.to_string());
+    let iterations = match args.next() {
+        Some(n) => n.parse()?,
+        None => 1000,
+    };
+    if name.eq_ignore_ascii_case(KuhnPokerGame::NAME) {
+        train::<Kuhn>(iterations);
+    } else if name.eq_ignore_ascii_case(LeducPokerGame::NAME) {
+        train::<Leduc>(iterations);
+    } else {
+        return Err(format!("unknown game `{}`; try {} or {}", name, KuhnPokerGame::NAME, LeducPokerGame::NAME).into());
+    }
+    Ok(())
+}
+
+fn train<V: Variant>(iterations: usize) {
+    let mut cfr = Cfr::new(PokerGame::<V>::undealt());
+    let report_every = (iterations / 10).max(1);
+    while cfr.iterations() < iterations {
+        cfr.train(report_every.min(iterations - cfr.iterations()));
+        // Payoffs are VICTORY per chip.
+        println!("after {:6} iterations: exploitability {:.5} chips per hand",
+                 cfr.iterations(),
+                 cfr.exploitability() / VICTORY as f64);
+    }
+
+    let strategy = cfr.strategy();
+    println!();
+    println!("strategy (player, own card, their card, face-up card, betting):");
+    for (info_set, policy) in strategy.iter() {
+        let odds: Vec<String> = policy.iter().map(|(id, q)| format!("{}={:.3}", id, q)).collect();
+        println!("  {:12} {}", info_set, odds.join(" "));
+    }
+
+    println!();
+    println!("sample hands:");
+    let mut rng = Rng::from_entropy();
+    for _ in 0..5 {
+        let mut g = PokerGame::<V>::undealt();
+        loop {
+            let moves = g.moves();
+            if moves.is_empty() {
+                break;
+            }
+            let m = if g.current_player() == CHANCE {
+                sample_chance(&g, &moves, &mut rng)
+            } else {
+                strategy.sample(&g, &moves, &mut rng)
+            };
+            g = m.next_state.clone();
+        }
+        println!("  {:12} A nets {}", g.unparse(), g.value_for('A') / VICTORY);
+    }
+}